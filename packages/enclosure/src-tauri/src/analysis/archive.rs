@@ -9,16 +9,18 @@ use bzip2::read::{BzDecoder, BzEncoder};
 use bzip2::Compression;
 use flate2::read::GzDecoder;
 use log::info;
+use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use xz2::read::XzEncoder;
 
 pub struct Archive;
 
 impl Prepare<HttpResponse> for Archive {
-    fn with_file_reader(reader: BufReader<File>, response: HttpResponse) -> Result<HttpResponse, String> {
+    fn with_file_reader(reader: BufReader<File>, response: HttpResponse) -> Result<HttpResponse, Error> {
         let suffix = response.file_props.suffix.clone();
 
         // zip
@@ -78,18 +80,26 @@ impl Prepare<HttpResponse> for Archive {
         }
 
         let mut res = response.clone();
-        res.error = "读取压缩包失败, 不支持的格式".to_string();
+        res.set_error(Error::unsupported_format("archive", &suffix));
         return Ok(res);
     }
 }
 
 impl Archive {
     /// 解压
-    fn decompress<F>(kind: String, reader: BufReader<File>, unzip_path: &PathBuf, mut response: HttpResponse, func: F) -> Result<HttpResponse, String>
+    fn decompress<F>(kind: String, reader: BufReader<File>, unzip_path: &PathBuf, mut response: HttpResponse, func: F) -> Result<HttpResponse, Error>
     where
-        F: FnOnce(BufReader<File>, &PathBuf, HttpResponse) -> Result<(), String>,
+        F: FnOnce(BufReader<File>, &PathBuf, HttpResponse) -> Result<(), Error>,
     {
-        func(reader, &unzip_path, response.clone())?;
+        // 取消时删除已解压的文件
+        if let Err(err) = func(reader, &unzip_path, response.clone()).and_then(|_| response.options.check_cancelled()) {
+            if response.options.is_cancelled() {
+                info!("decompress `{}` cancelled, remove unzip dir", &response.file_props.name);
+                let _ = fs::remove_dir_all(unzip_path);
+            }
+
+            return Err(err);
+        }
 
         // 读取目录下的所有文件,并归纳目录
         let (files, size) = Process::read_directory(unzip_path, &response.file_props.prefix)?;
@@ -113,12 +123,38 @@ impl Archive {
     }
 
     /// zip
-    pub fn prepare_zip(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    pub fn prepare_zip(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare zip ...");
 
-        let res = Self::decompress("ZIP Archive".to_string(), reader, exec_path, response, |reader, unzip_path, _| {
-            let mut archive = zip::ZipArchive::new(reader).map_err(|err| Error::Error(err.to_string()).to_string())?;
-            archive.extract(unzip_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let res = Self::decompress("ZIP Archive".to_string(), reader, exec_path, response, |reader, unzip_path, response| {
+            let mut archive = zip::ZipArchive::new(reader).map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+
+            // 逐个解压, 每个文件之前检查任务是否已取消
+            for i in 0..archive.len() {
+                response.options.check_cancelled()?;
+                let mut file = archive.by_index(i).map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+                let output_path = match file.enclosed_name() {
+                    Some(name) => unzip_path.join(name),
+                    None => continue,
+                };
+
+                if file.is_dir() {
+                    fs::create_dir_all(&output_path).map_err(|err| Error::io(err, &output_path.to_string_lossy()))?;
+                    continue;
+                }
+
+                if let Some(parent) = output_path.parent() {
+                    fs::create_dir_all(parent).map_err(|err| Error::io(err, &parent.to_string_lossy()))?;
+                }
+
+                let mut output_file = File::create(&output_path).map_err(|err| Error::io(err, &output_path.to_string_lossy()))?;
+                io::copy(&mut file, &mut output_file).map_err(|err| Error::io(err, &output_path.to_string_lossy()))?;
+                if let Some(mode) = file.unix_mode() {
+                    fs::set_permissions(&output_path, fs::Permissions::from_mode(mode))
+                        .map_err(|err| Error::io(err, &output_path.to_string_lossy()))?;
+                }
+            }
+
             Ok(())
         })?;
 
@@ -127,7 +163,7 @@ impl Archive {
     }
 
     /// bz2
-    pub fn prepare_bz2(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    pub fn prepare_bz2(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare bz2 ...");
         let res = Self::decompress("BZ2 Archive".to_string(), reader, exec_path, response, |reader, unzip_path, response| {
             let compressor = BzEncoder::new(reader, Compression::best());
@@ -137,15 +173,15 @@ impl Archive {
             let mut buffer = Vec::new();
             decompressor
                 .read_to_end(&mut buffer)
-                .map_err(|err| Error::Error(err.to_string()).to_string())?;
+                .map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
 
             let bz2 = ARCHIVE_SUFFIXES.get(1).unwrap();
             let mut name = response.file_props.name.clone();
             name = name.replace(&format!(".{}", bz2), "");
 
             let file_path = unzip_path.join(&name);
-            let mut output_file = File::create(file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
-            output_file.write_all(&buffer).map_err(|err| Error::Error(err.to_string()).to_string())?;
+            let mut output_file = File::create(&file_path).map_err(|err| Error::io(err, &file_path.to_string_lossy()))?;
+            output_file.write_all(&buffer).map_err(|err| Error::io(err, &file_path.to_string_lossy()))?;
             Ok(())
         })?;
 
@@ -154,14 +190,13 @@ impl Archive {
     }
 
     /// gz、zlib、tar
-    pub fn prepare_tar(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    pub fn prepare_tar(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare tar ...");
 
-        let res = Self::decompress("TAR Archive".to_string(), reader, exec_path, response, |reader, unzip_path, _| {
+        let res = Self::decompress("TAR Archive".to_string(), reader, exec_path, response, |reader, unzip_path, response| {
             let gz_decoder = GzDecoder::new(reader);
             let mut file_archive = tar::Archive::new(gz_decoder);
-            file_archive.unpack(unzip_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
-            Ok(())
+            Self::unpack_tar(&mut file_archive, unzip_path, &response)
         })?;
 
         info!("prepare tar success!");
@@ -169,15 +204,15 @@ impl Archive {
     }
 
     /// rar: `rar a xxx.rar .`
-    pub fn prepare_rar(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    pub fn prepare_rar(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare rar ...");
 
         let res = Self::decompress("Rar Archive".to_string(), reader, exec_path, response, |_, unzip_path, response| {
             let file_path = response.file_props.path.clone();
             let mut archive = unrar::Archive::new(file_path)
                 .extract_to(unzip_path.to_string_lossy().to_string())
-                .map_err(|err| Error::Error(err.to_string()).to_string())?;
-            archive.process().map_err(|err| Error::Error(err.to_string()).to_string())?;
+                .map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+            archive.process().map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
             Ok(())
         })?;
 
@@ -186,13 +221,12 @@ impl Archive {
     }
 
     /// tar.xz
-    pub fn prepare_tar_xz(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    pub fn prepare_tar_xz(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare tar.xz ...");
-        let res = Self::decompress("XZ Archive".to_string(), reader, exec_path, response, |reader, _, _| {
+        let res = Self::decompress("XZ Archive".to_string(), reader, exec_path, response, |reader, _, response| {
             let decoder = XzEncoder::new(reader, 9);
             let mut archive = tar::Archive::new(decoder);
-            archive.unpack(exec_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
-            Ok(())
+            Self::unpack_tar(&mut archive, exec_path, &response)
         })?;
 
         info!("prepare tar.xz success !");
@@ -200,15 +234,15 @@ impl Archive {
     }
 
     /// xz
-    pub fn prepare_xz(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    pub fn prepare_xz(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare xz ...");
         let res = Self::decompress("XZ Archive".to_string(), reader, exec_path, response, |reader, unzip_path, response| {
             let name = &response.file_props.prefix;
             let file_path = unzip_path.join(name);
 
-            let mut output_file = File::create(&file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+            let mut output_file = File::create(&file_path).map_err(|err| Error::io(err, &file_path.to_string_lossy()))?;
             let mut decoder = XzEncoder::new(reader, 9);
-            io::copy(&mut decoder, &mut output_file).map_err(|err| Error::Error(err.to_string()).to_string())?;
+            io::copy(&mut decoder, &mut output_file).map_err(|err| Error::io(err, &file_path.to_string_lossy()))?;
             Ok(())
         })?;
 
@@ -217,43 +251,68 @@ impl Archive {
     }
 
     /// 7z
-    pub fn prepare_7z(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    pub fn prepare_7z(reader: BufReader<File>, exec_path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare 7z ...");
         let res = Self::decompress("7Z Archive".to_string(), reader, exec_path, response, |_, unzip_path, response| {
-            sevenz_rust::decompress_file(&Path::new(&response.file_props.path), unzip_path)
-                .map_err(|err| Error::Error(err.to_string()).to_string())?;
-            Ok(())
+            // 返回 false 时停止解压
+            sevenz_rust::decompress_file_with_extract_fn(&Path::new(&response.file_props.path), unzip_path, |entry, reader, dest| {
+                if response.options.is_cancelled() {
+                    return Ok(false);
+                }
+
+                sevenz_rust::default_entry_extract_fn(entry, reader, dest)
+            })
+            .map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+            response.options.check_cancelled()
         })?;
 
         info!("prepare 7z success !");
         Ok(res)
     }
 
+    /// 逐个解压 tar 中的文件, 每个文件之前检查任务是否已取消
+    fn unpack_tar<R: Read>(archive: &mut tar::Archive<R>, unzip_path: &PathBuf, response: &HttpResponse) -> Result<(), Error> {
+        fs::create_dir_all(unzip_path).map_err(|err| Error::io(err, &unzip_path.to_string_lossy()))?;
+        let entries = archive.entries().map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+        for entry in entries {
+            response.options.check_cancelled()?;
+            let mut entry = entry.map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+            entry.unpack_in(unzip_path).map_err(|err| Error::io(err, &unzip_path.to_string_lossy()))?;
+        }
+
+        Ok(())
+    }
+
     /// 解压文件夹
-    pub fn unarchive(file_path: &str, full_path: &str) -> Result<HttpResponse, String> {
+    pub fn unarchive(file_path: &str, full_path: &str) -> Result<HttpResponse, Error> {
         let mut response = HttpResponse::default();
         let unarchive_path = Path::new(file_path);
         let archive_path = Path::new(full_path);
 
-        if !unarchive_path.exists() || !archive_path.exists() {
-            response.error = "文件解压失败, 路径不存在!".to_string();
+        if !unarchive_path.exists() {
+            response.set_error(Error::not_found("file", file_path));
+            return Ok(response);
+        }
+
+        if !archive_path.exists() {
+            response.set_error(Error::not_found("file", full_path));
             return Ok(response);
         }
 
         if unarchive_path.is_dir() {
-            response.error = format!("{} is a directory !", file_path);
+            response.set_error(Error::invalid_argument("file", file_path, "it is a directory"));
             return Ok(response);
         }
 
         let download_path = unarchive_path.parent();
         if download_path.is_none() {
-            response.error = "文件解压失败, 父路径不存在!".to_string();
+            response.set_error(Error::not_found("directory", file_path));
             return Ok(response);
         }
 
         let download_path = download_path.unwrap();
         if !download_path.exists() {
-            response.error = "文件解压失败, 父路径不存在!".to_string();
+            response.set_error(Error::not_found("directory", &download_path.to_string_lossy()));
             return Ok(response);
         }
 
@@ -262,7 +321,7 @@ impl Archive {
         let mut copy_options = fs_extra::dir::CopyOptions::new();
         copy_options.overwrite = true;
         let download_path = download_path.to_string_lossy().to_string();
-        fs_extra::copy_items(&[full_path], &download_path, &copy_options).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        fs_extra::copy_items(&[full_path], &download_path, &copy_options).map_err(|err| Error::Error(err.to_string()))?;
 
         response.code = 200;
         info!("unarchive success!");
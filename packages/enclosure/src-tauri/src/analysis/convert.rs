@@ -0,0 +1,301 @@
+//! 文档转换成纯文本、markdown
+
+use crate::analysis::document::{Document, DocumentBlock};
+use crate::config::{HttpResponse, MUPDF_SUFFIXES};
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use log::info;
+
+pub struct Convert;
+
+// 转换格式
+const CONVERT_FORMATS: [&str; 2] = ["text", "markdown"];
+
+// 列表符号
+const LIST_MARKERS: [&str; 6] = ["•", "◦", "▪", "‣", "-", "*"];
+
+impl Convert {
+    /// 转换文档, 指定 `output_path` 时写入文件, 否则返回到 body 中
+    pub fn convert_document(file_path: &str, format: &str, output_path: Option<&str>, password: Option<&str>) -> Result<HttpResponse, Error> {
+        info!("convert document `{}` to `{}` ...", file_path, format);
+        let mut response = HttpResponse::default();
+
+        let format = if format.eq_ignore_ascii_case("md") {
+            "markdown".to_string()
+        } else {
+            format.to_lowercase()
+        };
+        if !CONVERT_FORMATS.contains(&format.as_str()) {
+            response.set_error(Error::unsupported_format("convert", &format));
+            return Ok(response);
+        }
+
+        let output_path = output_path.filter(|path| !path.is_empty());
+        if let Some(output_path) = output_path {
+            if FileUtils::is_same_file(file_path, output_path) {
+                response.set_error(Error::invalid_argument("output path", output_path, "the same as the source file"));
+                return Ok(response);
+            }
+        }
+
+        let suffix = FileUtils::get_file_suffix(file_path);
+        let blocks = match suffix.as_str() {
+            "docx" => Document::read_docx_blocks(file_path)?,
+            "pptx" => Document::read_pptx_blocks(file_path)?,
+            _ if MUPDF_SUFFIXES.contains(&suffix.as_str()) => Self::read_mupdf(file_path, password)?,
+            _ => {
+                response.set_error(Error::unsupported_format("convert", &suffix));
+                return Ok(response);
+            }
+        };
+
+        let content = if format == "markdown" {
+            Self::to_markdown(&blocks)
+        } else {
+            Self::to_text(&blocks)
+        };
+
+        response.code = 200;
+        match output_path {
+            Some(output_path) => {
+                FileUtils::write_to_file_atomic(output_path, &content)?;
+                response.body = output_path.to_string();
+            }
+            None => response.body = content,
+        }
+
+        info!("convert document `{}` success !", file_path);
+        Ok(response)
+    }
+
+    /// pdf、epub 等, 按字体大小识别标题, 按行首符号识别列表
+    fn read_mupdf(file_path: &str, password: Option<&str>) -> Result<Vec<DocumentBlock>, Error> {
+        let text_blocks = Self::merge_hyphens(Document::read_text_blocks(file_path, password)?);
+
+        // 正文字体大小取出现最多的字体大小
+        let mut sizes: Vec<(i32, usize)> = Vec::new();
+        for (text, size) in text_blocks.iter().flatten() {
+            let size = size.round() as i32;
+            match sizes.iter_mut().find(|(s, _)| *s == size) {
+                Some((_, count)) => *count += text.chars().count(),
+                None => sizes.push((size, text.chars().count())),
+            }
+        }
+
+        let body_size = sizes.iter().max_by_key(|(_, count)| *count).map(|(size, _)| *size as f32).unwrap_or(0.0);
+
+        let mut blocks: Vec<DocumentBlock> = Vec::new();
+        for lines in text_blocks.iter() {
+            let mut paragraph: Vec<String> = Vec::new();
+            for (text, size) in lines.iter() {
+                let level = if body_size > 0.0 && *size >= body_size * 1.5 {
+                    1
+                } else if body_size > 0.0 && *size >= body_size * 1.2 {
+                    2
+                } else {
+                    0
+                };
+
+                if level > 0 {
+                    Self::push_paragraph(&mut blocks, &mut paragraph);
+                    match blocks.last_mut() {
+                        Some(DocumentBlock::Heading(last_level, last_text)) if *last_level == level => {
+                            last_text.push(' ');
+                            last_text.push_str(text);
+                        }
+                        _ => blocks.push(DocumentBlock::Heading(level, text.clone())),
+                    }
+                    continue;
+                }
+
+                if let Some(item) = Self::strip_list_marker(text) {
+                    Self::push_paragraph(&mut blocks, &mut paragraph);
+                    blocks.push(DocumentBlock::ListItem(item));
+                    continue;
+                }
+
+                // 列表项的续行
+                if paragraph.is_empty() {
+                    if let Some(DocumentBlock::ListItem(item)) = blocks.last_mut() {
+                        item.push(' ');
+                        item.push_str(text);
+                        continue;
+                    }
+                }
+
+                paragraph.push(text.clone());
+            }
+
+            Self::push_paragraph(&mut blocks, &mut paragraph);
+        }
+
+        Ok(blocks)
+    }
+
+    /// 合并行尾连字符
+    fn merge_hyphens(blocks: Vec<Vec<(String, f32)>>) -> Vec<Vec<(String, f32)>> {
+        blocks
+            .into_iter()
+            .map(|lines| {
+                let mut merged: Vec<(String, f32)> = Vec::new();
+                for (text, size) in lines {
+                    match merged.last_mut() {
+                        Some((last, _)) if last.ends_with('-') && !last.ends_with(" -") => {
+                            last.pop();
+                            last.push_str(&text);
+                        }
+                        _ => merged.push((text, size)),
+                    }
+                }
+                merged
+            })
+            .collect()
+    }
+
+    fn push_paragraph(blocks: &mut Vec<DocumentBlock>, paragraph: &mut Vec<String>) {
+        if paragraph.is_empty() {
+            return;
+        }
+
+        blocks.push(DocumentBlock::Paragraph(paragraph.join(" ")));
+        paragraph.clear();
+    }
+
+    /// 去掉列表符号, 不是列表时返回 None
+    fn strip_list_marker(text: &str) -> Option<String> {
+        for marker in LIST_MARKERS.iter() {
+            if let Some(item) = text.strip_prefix(marker) {
+                if item.starts_with(' ') || !marker.is_ascii() {
+                    return Some(item.trim().to_string());
+                }
+            }
+        }
+
+        // 1. 或 1)
+        let (number, item) = text.split_once(|c: char| c == '.' || c == ')')?;
+        let is_number = !number.is_empty() && number.len() <= 3 && number.chars().all(|c| c.is_ascii_digit());
+        if is_number && item.starts_with(' ') {
+            return Some(item.trim().to_string());
+        }
+
+        None
+    }
+
+    /// 转换成 markdown
+    fn to_markdown(blocks: &Vec<DocumentBlock>) -> String {
+        let mut content = String::new();
+        for (i, block) in blocks.iter().enumerate() {
+            // 连续的列表项之间不空行
+            let is_list = matches!(block, DocumentBlock::ListItem(_));
+            let last_is_list = i > 0 && matches!(blocks[i - 1], DocumentBlock::ListItem(_));
+            if i > 0 {
+                content.push_str(if is_list && last_is_list { "\n" } else { "\n\n" });
+            }
+
+            match block {
+                DocumentBlock::Heading(level, text) => content.push_str(&format!("{} {}", "#".repeat(*level), text)),
+                DocumentBlock::Paragraph(text) => content.push_str(text),
+                DocumentBlock::ListItem(text) => content.push_str(&format!("- {}", text)),
+                DocumentBlock::Table(rows) => {
+                    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+                    let lines: Vec<String> = rows
+                        .iter()
+                        .map(|row| {
+                            let cells: Vec<String> = (0..columns)
+                                .map(|i| row.get(i).map(|cell| cell.replace('|', "\\|")).unwrap_or_default())
+                                .collect();
+                            format!("| {} |", cells.join(" | "))
+                        })
+                        .collect();
+
+                    for (j, line) in lines.iter().enumerate() {
+                        if j > 0 {
+                            content.push('\n');
+                        }
+
+                        content.push_str(line);
+
+                        // 第一行作为表头
+                        if j == 0 {
+                            content.push_str(&format!("\n|{}", " --- |".repeat(columns)));
+                        }
+                    }
+                }
+            }
+        }
+
+        content.push('\n');
+        content
+    }
+
+    /// 转换成纯文本
+    fn to_text(blocks: &Vec<DocumentBlock>) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        for block in blocks.iter() {
+            match block {
+                DocumentBlock::Heading(_, text) | DocumentBlock::Paragraph(text) => lines.push(text.clone()),
+                DocumentBlock::ListItem(text) => lines.push(format!("• {}", text)),
+                DocumentBlock::Table(rows) => {
+                    for row in rows.iter() {
+                        lines.push(row.join("\t"));
+                    }
+                }
+            }
+        }
+
+        let mut content = lines.join("\n");
+        content.push('\n');
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_list_marker() {
+        assert_eq!(Convert::strip_list_marker("• first"), Some("first".to_string()));
+        assert_eq!(Convert::strip_list_marker("•second"), Some("second".to_string()));
+        assert_eq!(Convert::strip_list_marker("- third"), Some("third".to_string()));
+        assert_eq!(Convert::strip_list_marker("12. fourth"), Some("fourth".to_string()));
+        assert_eq!(Convert::strip_list_marker("3) fifth"), Some("fifth".to_string()));
+        assert_eq!(Convert::strip_list_marker("-1 degrees"), None);
+        assert_eq!(Convert::strip_list_marker("*emphasis*"), None);
+        assert_eq!(Convert::strip_list_marker("3.14 is pi"), None);
+        assert_eq!(Convert::strip_list_marker("2024. year"), None);
+        assert_eq!(Convert::strip_list_marker("plain text"), None);
+    }
+
+    #[test]
+    fn test_merge_hyphens() {
+        let blocks = vec![vec![
+            ("conver-".to_string(), 12.0),
+            ("sion done".to_string(), 12.0),
+            ("a -".to_string(), 12.0),
+            ("b".to_string(), 12.0),
+        ]];
+
+        let merged = Convert::merge_hyphens(blocks);
+        let lines: Vec<&str> = merged[0].iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(lines, vec!["conversion done", "a -", "b"]);
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let blocks = vec![
+            DocumentBlock::Heading(2, "Title".to_string()),
+            DocumentBlock::Paragraph("Body".to_string()),
+            DocumentBlock::ListItem("one".to_string()),
+            DocumentBlock::ListItem("two".to_string()),
+            DocumentBlock::Table(vec![
+                vec!["a".to_string(), "b|c".to_string()],
+                vec!["1".to_string()],
+            ]),
+        ];
+
+        let expected = "## Title\n\nBody\n\n- one\n- two\n\n| a | b\\|c |\n| --- | --- |\n| 1 |  |\n";
+        assert_eq!(Convert::to_markdown(&blocks), expected);
+        assert_eq!(Convert::to_text(&blocks), "Title\nBody\n• one\n• two\na\tb|c\n1\n");
+    }
+}
@@ -0,0 +1,492 @@
+//! csv、tsv、psv, 按块流式读取, 与 excel 共用块文件和清单
+
+use crate::analysis::excel::{Excel, SHEET_CHUNK, SHEET_FINISHED, SHEET_PROGRESS, SHEET_STARTED, TAKE_EXCEL_COUNT};
+use crate::analysis::profile::Profiler;
+use crate::analysis::xlsx::CellFormats;
+use crate::config::{ExcelRow, ExcelSheet, ExcelSheetMetadata, HttpResponse};
+use crate::error::Error;
+use crate::prepare::Prepare;
+use crate::utils::file::FileUtils;
+use calamine::DataType;
+use chardetng::EncodingDetector;
+use encoding_rs::{Decoder, Encoding, UTF_8};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+pub struct Delimited;
+
+/// 分隔符、引号、编码
+#[derive(Debug, Clone)]
+pub struct Dialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub encoding: &'static Encoding,
+    pub has_header: bool,
+}
+
+/// 保存到临时目录的格式, 后续读取沿用打开时的表头设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DialectFile {
+    delimiter: u8,
+    quote: u8,
+    encoding: String,
+    has_header: bool,
+}
+
+/// 按编码解码为 utf-8 的 reader
+pub struct DecodeReader<R: Read> {
+    reader: R,
+    decoder: Decoder,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+// 用于检测格式的字节数
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+// 用于检测分隔符的行数
+const SAMPLE_LINES: usize = 20;
+
+// 候选分隔符
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+const DIALECT_FILE: &str = "dialect.json";
+
+impl Prepare<HttpResponse> for Delimited {
+    fn with_response(response: HttpResponse) -> Result<HttpResponse, Error> {
+        info!("prepare delimited file ...");
+        let temp_path = FileUtils::create_temp_dir(&response.file_props.prefix, true)?;
+        let dialect = Self::detect(&response.file_props.path, &response.file_props.suffix, response.options.has_header)?;
+        info!("delimited dialect: {:?}", &dialect);
+        Self::save_dialect(&temp_path, &dialect)?;
+
+        let mut res = response.clone();
+        res.code = 200;
+
+        let res_cloned = res.clone();
+        async_std::task::spawn_blocking(move || {
+            let mut sheet = ExcelSheet::default();
+            sheet.name = res_cloned.file_props.name.clone();
+            sheet.index = 1;
+            sheet.layout.visibility = "visible".to_string();
+
+            if let Err(err) = Self::handle_file(&res_cloned, &dialect, &temp_path, &mut sheet) {
+                error!("handle delimited file error: {}", &err);
+                Excel::send_error(&res_cloned, &temp_path, &sheet, sheet.rows_count, &err);
+            }
+        });
+
+        Ok(res)
+    }
+}
+
+impl Delimited {
+    /// 逐块读取并写入块文件, 内存中最多保留一个块
+    fn handle_file(response: &HttpResponse, dialect: &Dialect, temp_path: &PathBuf, sheet: &mut ExcelSheet) -> Result<(), Error> {
+        Excel::send_event(response, SHEET_STARTED, sheet, 0, "");
+
+        let mut reader = Self::open(&response.file_props.path, dialect)?;
+        let formats = CellFormats::default();
+        let mut chunks: Vec<Vec<DataType>> = Vec::new();
+        let mut records = reader.records();
+        let mut index: usize = 0;
+        let mut task_index: usize = 0;
+        let mut cells_count: usize = 0;
+
+        if dialect.has_header {
+            if let Some(record) = records.next() {
+                let record = record.map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+                sheet.header = record.iter().map(|field| field.to_string()).collect();
+            }
+        }
+
+        let mut profiler = Profiler::new(sheet.header.clone(), false);
+        loop {
+            response.options.check_cancelled()?;
+            let record = records.next();
+            let finished = record.is_none();
+            if let Some(record) = record {
+                let record = record.map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
+                cells_count = cells_count.max(record.len());
+                chunks.push(record.iter().map(|field| Self::convert_field(field)).collect());
+                index += 1;
+            }
+
+            if chunks.len() == TAKE_EXCEL_COUNT || (finished && !chunks.is_empty()) {
+                task_index += 1;
+                let row_start = index - chunks.len() + 1;
+                let file_name = Excel::get_chunk_file_name(sheet.index, task_index);
+                let rows = Excel::get_row(&chunks, &formats, row_start, temp_path.join(&file_name))?;
+                profiler.add_rows(&rows);
+
+                let mut metadata = ExcelSheetMetadata::default();
+                metadata.name = sheet.name.clone();
+                metadata.row_start = row_start;
+                metadata.row_end = index;
+                metadata.task_id = task_index;
+                metadata.file = file_name;
+
+                sheet.rows_count = index;
+                sheet.cells_count = cells_count;
+                sheet.metadata.push(metadata.clone());
+
+                let mut chunk_sheet = sheet.clone();
+                chunk_sheet.metadata = vec![metadata];
+                Excel::send_event(response, SHEET_PROGRESS, sheet, index, "");
+                Excel::send_event(response, SHEET_CHUNK, &chunk_sheet, index, "");
+                chunks.clear();
+            }
+
+            if finished {
+                break;
+            }
+        }
+
+        sheet.rows_count = index;
+        sheet.cells_count = cells_count;
+        sheet.profile = profiler.finish();
+        Excel::write_result_to_file(temp_path.clone(), sheet.clone())?;
+        Excel::send_event(response, SHEET_FINISHED, sheet, index, "");
+        info!("handle delimited file success, rows: {}", index);
+        Ok(())
+    }
+
+    /// 逐行读取, `f` 返回 false 时停止
+    pub fn scan<F>(file_path: &str, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(ExcelRow) -> bool,
+    {
+        let dialect = Self::get_dialect(file_path)?;
+        let mut reader = Self::open(file_path, &dialect)?;
+        let formats = CellFormats::default();
+        let skip = if dialect.has_header { 1 } else { 0 };
+
+        for (index, record) in reader.records().skip(skip).enumerate() {
+            let record = record.map_err(|err| Error::corrupt_file(file_path, err))?;
+            let cells: Vec<DataType> = record.iter().map(|field| Self::convert_field(field)).collect();
+            if !f(Excel::convert_row(&cells, &formats, index)) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 读取表头, 没有表头时返回空
+    pub fn read_header(file_path: &str) -> Result<Vec<String>, Error> {
+        let dialect = Self::get_dialect(file_path)?;
+        if !dialect.has_header {
+            return Ok(Vec::new());
+        }
+
+        let mut reader = Self::open(file_path, &dialect)?;
+        match reader.records().next() {
+            Some(record) => {
+                let record = record.map_err(|err| Error::corrupt_file(file_path, err))?;
+                Ok(record.iter().map(|field| field.trim().to_string()).collect())
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// 按行读取, 块文件未生成时使用
+    pub fn read_rows(file_path: &str, start: usize, count: usize) -> Result<Vec<ExcelRow>, Error> {
+        let dialect = Self::get_dialect(file_path)?;
+        let mut reader = Self::open(file_path, &dialect)?;
+        let formats = CellFormats::default();
+        let skip = if dialect.has_header { start.saturating_add(1) } else { start };
+
+        let mut rows: Vec<ExcelRow> = Vec::new();
+        for (index, record) in reader.records().skip(skip).take(count).enumerate() {
+            let record = record.map_err(|err| Error::corrupt_file(file_path, err))?;
+            let cells: Vec<DataType> = record.iter().map(|field| Self::convert_field(field)).collect();
+            rows.push(Excel::convert_row(&cells, &formats, start.saturating_add(index)));
+        }
+
+        Ok(rows)
+    }
+
+    /// 打开时保存的格式, 没有时重新检测
+    pub fn get_dialect(file_path: &str) -> Result<Dialect, Error> {
+        let (_, temp_dir) = Excel::get_temp_dir(file_path)?;
+        let path = temp_dir.join(DIALECT_FILE);
+        if path.exists() {
+            let content = FileUtils::read_file_string(&path.to_string_lossy().to_string())?;
+            match serde_json::from_str::<DialectFile>(&content) {
+                Ok(saved) => {
+                    if let Some(encoding) = Encoding::for_label(saved.encoding.as_bytes()) {
+                        return Ok(Dialect {
+                            delimiter: saved.delimiter,
+                            quote: saved.quote,
+                            encoding,
+                            has_header: saved.has_header,
+                        });
+                    }
+                }
+                Err(err) => error!("read delimited dialect error: {}", err),
+            }
+        }
+
+        let suffix = FileUtils::get_file_suffix(file_path);
+        Self::detect(file_path, &suffix, None)
+    }
+
+    /// 保存格式
+    fn save_dialect(temp_path: &Path, dialect: &Dialect) -> Result<(), Error> {
+        let saved = DialectFile {
+            delimiter: dialect.delimiter,
+            quote: dialect.quote,
+            encoding: dialect.encoding.name().to_string(),
+            has_header: dialect.has_header,
+        };
+        let content = serde_json::to_string(&saved).map_err(|err| Error::Error(err.to_string()))?;
+        FileUtils::write_to_file_atomic(&temp_path.join(DIALECT_FILE).to_string_lossy(), &content)
+    }
+
+    /// 打开文件
+    pub fn open(file_path: &str, dialect: &Dialect) -> Result<csv::Reader<DecodeReader<BufReader<File>>>, Error> {
+        let reader = FileUtils::read_file_buffer(file_path)?;
+        let reader = DecodeReader::new(reader, dialect.encoding);
+        let reader = csv::ReaderBuilder::new()
+            .delimiter(dialect.delimiter)
+            .quote(dialect.quote)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        Ok(reader)
+    }
+
+    /// 检测分隔符、引号、编码和表头, `has_header` 为 None 时自动检测
+    pub fn detect(file_path: &str, suffix: &str, has_header: Option<bool>) -> Result<Dialect, Error> {
+        let mut file = FileUtils::open_file(file_path)?;
+        let mut sample: Vec<u8> = Vec::new();
+        (&mut file)
+            .take(SAMPLE_SIZE as u64)
+            .read_to_end(&mut sample)
+            .map_err(|err| Error::io(err, file_path))?;
+
+        let encoding = Self::detect_encoding(&sample);
+        let (text, _, _) = encoding.decode(&sample);
+        let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).take(SAMPLE_LINES).collect();
+
+        let delimiter = match suffix {
+            "tsv" => b'\t',
+            "psv" => b'|',
+            _ => Self::detect_delimiter(&lines),
+        };
+
+        let quote = Self::detect_quote(&lines, delimiter);
+        let mut dialect = Dialect {
+            delimiter,
+            quote,
+            encoding,
+            has_header: false,
+        };
+
+        dialect.has_header = match has_header {
+            Some(has_header) => has_header,
+            None => Self::detect_header(&lines, &dialect),
+        };
+
+        Ok(dialect)
+    }
+
+    /// BOM、utf-8, 其余按内容推测
+    fn detect_encoding(sample: &[u8]) -> &'static Encoding {
+        if let Some((encoding, _)) = Encoding::for_bom(sample) {
+            return encoding;
+        }
+
+        match std::str::from_utf8(sample) {
+            Ok(_) => UTF_8,
+            // 截断在多字节字符中间
+            Err(err) if err.error_len().is_none() => UTF_8,
+            Err(_) => {
+                let mut detector = EncodingDetector::new();
+                detector.feed(sample, sample.len() < SAMPLE_SIZE);
+                detector.guess(None, false)
+            }
+        }
+    }
+
+    /// 每行数量一致且最多的分隔符
+    fn detect_delimiter(lines: &Vec<&str>) -> u8 {
+        let mut best = b',';
+        let mut best_score = 0;
+
+        for delimiter in DELIMITERS.iter() {
+            let counts: Vec<usize> = lines.iter().map(|line| Self::count_delimiter(line, *delimiter)).collect();
+            let first = match counts.first() {
+                Some(first) if *first > 0 => *first,
+                _ => continue,
+            };
+
+            let consistent = counts.iter().filter(|count| **count == first).count();
+            let score = consistent * 1000 + first;
+            if score > best_score {
+                best_score = score;
+                best = *delimiter;
+            }
+        }
+
+        best
+    }
+
+    /// 统计引号外的分隔符数量
+    fn count_delimiter(line: &str, delimiter: u8) -> usize {
+        let mut count = 0;
+        let mut in_quote = false;
+        for c in line.bytes() {
+            if c == b'"' {
+                in_quote = !in_quote;
+            } else if c == delimiter && !in_quote {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /// 字段以单引号包裹时使用单引号
+    fn detect_quote(lines: &Vec<&str>, delimiter: u8) -> u8 {
+        let delimiter = delimiter as char;
+        let count = |quote: char| {
+            lines
+                .iter()
+                .flat_map(|line| line.split(delimiter))
+                .map(|field| field.trim())
+                .filter(|field| field.len() >= 2 && field.starts_with(quote) && field.ends_with(quote))
+                .count()
+        };
+
+        if count('\'') > count('"') {
+            b'\''
+        } else {
+            b'"'
+        }
+    }
+
+    /// 第一行全为文本且后续行存在数字时认为有表头
+    fn detect_header(lines: &Vec<&str>, dialect: &Dialect) -> bool {
+        if lines.len() < 2 {
+            return false;
+        }
+
+        let sample = lines.join("\n");
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(dialect.delimiter)
+            .quote(dialect.quote)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(sample.as_bytes());
+        let records: Vec<csv::StringRecord> = reader.records().filter_map(|record| record.ok()).collect();
+        let (first, rest) = match records.split_first() {
+            Some(records) => records,
+            None => return false,
+        };
+
+        let is_number = |field: &str| !field.trim().is_empty() && field.trim().parse::<f64>().is_ok();
+        let header_is_text = first.iter().all(|field| !field.trim().is_empty() && !is_number(field));
+        let rest_has_number = rest.iter().any(|record| record.iter().any(|field| is_number(field)));
+        header_is_text && rest_has_number
+    }
+
+    /// 字段转换成单元格, 保留前导 0 的数字按文本处理
+    fn convert_field(field: &str) -> DataType {
+        let value = field.trim();
+        if value.is_empty() {
+            return DataType::Empty;
+        }
+
+        let leading_zero = value.len() > 1 && value.starts_with('0') && !value.starts_with("0.");
+        if !leading_zero {
+            if let Ok(number) = value.parse::<i64>() {
+                return DataType::Int(number);
+            }
+
+            if let Ok(number) = value.parse::<f64>() {
+                if number.is_finite() {
+                    return DataType::Float(number);
+                }
+            }
+        }
+
+        match value.to_lowercase().as_str() {
+            "true" => DataType::Bool(true),
+            "false" => DataType::Bool(false),
+            _ => DataType::String(field.to_string()),
+        }
+    }
+}
+
+impl<R: Read> DecodeReader<R> {
+    fn new(reader: R, encoding: &'static Encoding) -> Self {
+        Self {
+            reader,
+            decoder: encoding.new_decoder(),
+            input: vec![0; BUFFER_SIZE],
+            output: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+
+    /// 读取并解码下一段
+    fn fill(&mut self) -> std::io::Result<()> {
+        self.output.clear();
+        self.position = 0;
+
+        while self.output.is_empty() && !self.eof {
+            let size = self.reader.read(&mut self.input)?;
+            let last = size == 0;
+            let mut output = vec![0; self.decoder.max_utf8_buffer_length(size).unwrap_or(BUFFER_SIZE * 4).max(16)];
+            let (_, _, written, _) = self.decoder.decode_to_utf8(&self.input[..size], &mut output, last);
+            output.truncate(written);
+            self.output = output;
+            self.eof = last;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.output.len() {
+            self.fill()?;
+        }
+
+        let remain = &self.output[self.position..];
+        let size = remain.len().min(buf.len());
+        buf[..size].copy_from_slice(&remain[..size]);
+        self.position += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, WINDOWS_1252};
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(Delimited::detect_encoding("名称,数量\n苹果,1\n".as_bytes()), UTF_8);
+        assert_eq!(Delimited::detect_encoding(b"\xEF\xBB\xBFname,count\n"), UTF_8);
+
+        let (sample, _, _) = GBK.encode("名称,数量\n苹果,1\n香蕉,2\n橙子,3\n");
+        assert_eq!(Delimited::detect_encoding(&sample), GBK);
+
+        // 单字节编码不应按 GB18030 解码
+        let (sample, _, _) = WINDOWS_1252.encode("name,city\nJosé,Málaga\nFrançois,Besançon\nJürgen,München\n");
+        assert_eq!(Delimited::detect_encoding(&sample), WINDOWS_1252);
+    }
+}
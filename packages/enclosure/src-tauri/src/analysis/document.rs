@@ -1,19 +1,70 @@
-//! pdf、doc、ppt预览
+//! pdf、doc、ppt、epub、xps、cbz、fb2、odt、odp、rtf 预览
 
+use crate::analysis::odf::OpenDocument;
+use crate::analysis::pdf::Pdf;
 use crate::analysis::process::Process;
-use crate::config::{HttpResponse, SuffixProps, DOCUMENT_SUFFIXES};
+use crate::analysis::rtf::Rtf;
+use crate::config::{
+    DocumentOutline, DocumentThumbnail, HttpResponse, RequestOptions, SuffixProps, DOCUMENT_SUFFIXES, DOCUMENT_THUMBNAIL_EVENT, MUPDF_SUFFIXES,
+};
 use crate::error::Error;
 use crate::prepare::Prepare;
+use crate::scheduler::{Priority, Scheduler};
 use crate::utils::file::FileUtils;
 use crate::utils::Utils;
-use log::info;
+use log::{error, info};
 use mupdf::Matrix;
+use quick_xml::events::{BytesStart, Event};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
 
 pub struct Document;
 
+// 可重排文档默认页面宽度
+const DEFAULT_PAGE_WIDTH: f32 = 450.0;
+
+// 可重排文档默认页面高度
+const DEFAULT_PAGE_HEIGHT: f32 = 600.0;
+
+// 可重排文档默认字体大小
+const DEFAULT_FONT_SIZE: f32 = 12.0;
+
+// A4 宽度
+const DEFAULT_A4_WIDTH: f32 = 595.0;
+
+// A4 高度
+const DEFAULT_A4_HEIGHT: f32 = 842.0;
+
+// 缩略图宽度
+const THUMBNAIL_WIDTH: f32 = 160.0;
+
+// 缩略图缓存目录, 以 `.` 开头, 不会与文件的临时目录重名
+const THUMBNAIL_DIR: &str = ".thumbnails";
+
+// 导出页面缩放比例
+const EXPORT_SCALE: f32 = 2.0;
+
+// 导出格式
+const EXPORT_FORMATS: [&str; 4] = ["png", "jpeg", "webp", "pdf"];
+
+// 转换后的 html 目录
+const HTML_DIR: &str = "html";
+
+// 转换后的 html 文件
+const HTML_FILE: &str = "index.html";
+
+/// 文档结构
+#[derive(Debug, Clone)]
+pub enum DocumentBlock {
+    Heading(usize, String),
+    Paragraph(String),
+    ListItem(String),
+    Table(Vec<Vec<String>>),
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct PreviewProps {
     pub name: String,
@@ -22,20 +73,15 @@ pub struct PreviewProps {
 }
 
 impl Prepare<HttpResponse> for Document {
-    fn with_response(response: HttpResponse) -> Result<HttpResponse, String> {
+    fn with_response(response: HttpResponse) -> Result<HttpResponse, Error> {
         let file_path = &response.file_props.path;
         let suffix = response.file_props.suffix.clone();
 
-        // pdf
-        let pdf = DOCUMENT_SUFFIXES.get(0).unwrap();
-
-        // doc
-        let doc = DOCUMENT_SUFFIXES.get(1).unwrap();
-
         // docx
         let docx = DOCUMENT_SUFFIXES.get(2).unwrap();
 
-        if suffix.ends_with(pdf) {
+        // pdf、epub、xps、cbz、fb2
+        if MUPDF_SUFFIXES.contains(&suffix.as_str()) {
             return Self::prepare_pdf(file_path, response.clone());
         }
 
@@ -43,18 +89,52 @@ impl Prepare<HttpResponse> for Document {
             return Self::prepare_docx(file_path, response.clone());
         }
 
+        // odt
+        let odt = DOCUMENT_SUFFIXES.get(10).unwrap();
+
+        // odp
+        let odp = DOCUMENT_SUFFIXES.get(11).unwrap();
+
+        // rtf
+        let rtf = DOCUMENT_SUFFIXES.get(12).unwrap();
+
+        if &suffix == odt {
+            return Self::prepare_html(file_path, response.clone(), (DEFAULT_A4_WIDTH, DEFAULT_A4_HEIGHT), OpenDocument::to_html);
+        }
+
+        if &suffix == odp {
+            return Self::prepare_html(file_path, response.clone(), (DEFAULT_A4_HEIGHT, DEFAULT_A4_WIDTH), OpenDocument::to_html);
+        }
+
+        if &suffix == rtf {
+            return Self::prepare_html(file_path, response.clone(), (DEFAULT_A4_WIDTH, DEFAULT_A4_HEIGHT), Rtf::to_html);
+        }
+
         Ok(response)
     }
 }
 
 impl Document {
-    fn prepare<F>(file_path: &str, mut response: HttpResponse, func: F) -> Result<HttpResponse, String>
+    fn prepare<F>(file_path: &str, mut response: HttpResponse, func: F) -> Result<HttpResponse, Error>
     where
-        F: FnOnce(&str, &PathBuf, HttpResponse) -> Result<(), String>,
+        F: FnOnce(&str, &PathBuf, HttpResponse) -> Result<(), Error>,
     {
-        let temp_dir = FileUtils::create_temp_dir(&response.file_props.prefix, true)?;
+        // 重排的页面写入单独的目录, 不覆盖默认排版的缓存
+        let dir_name = match response.options.get_layout_key() {
+            Some(key) => format!("{}-{}", &response.file_props.prefix, key),
+            None => response.file_props.prefix.clone(),
+        };
+        let temp_dir = FileUtils::create_temp_dir(&dir_name, true)?;
+
+        // 取消时删除已生成的页面
+        if let Err(err) = func(file_path, &temp_dir, response.clone()) {
+            if response.options.is_cancelled() {
+                info!("prepare `{}` cancelled, remove temp dir", file_path);
+                let _ = fs::remove_dir_all(&temp_dir);
+            }
 
-        func(file_path, &temp_dir, response.clone())?;
+            return Err(err);
+        }
 
         info!("read pictures ...");
         let contents = Self::read_pictures(&temp_dir)?;
@@ -73,44 +153,502 @@ impl Document {
         Ok(response)
     }
 
-    /// pdf
-    fn prepare_pdf(file_path: &str, response: HttpResponse) -> Result<HttpResponse, String> {
+    /// pdf、epub、xps、cbz、fb2
+    fn prepare_pdf(file_path: &str, mut response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare pdf ...");
 
-        let res = Self::prepare(file_path, response, |file_path, temp_dir, _| {
-            let document = mupdf::document::Document::open(file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
-            let pages = document.pages().map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let mut document = mupdf::document::Document::open(file_path).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let needs_password = document.needs_password().map_err(|err| Error::Error(err.to_string()))?;
+        if needs_password {
+            return Self::prepare_encrypted_pdf(document, response);
+        }
 
-            for (i, page) in pages.enumerate() {
-                let page = page.map_err(|err| Error::Error(err.to_string()).to_string())?;
-                let matrix = Matrix::new_scale(1.0, 1.0);
-                let pixmap = page
-                    .to_pixmap(&matrix, &mupdf::Colorspace::device_rgb(), 0.0, true)
-                    .map_err(|err| Error::Error(err.to_string()).to_string())?;
+        Self::layout(&mut document, &response.options, (DEFAULT_PAGE_WIDTH, DEFAULT_PAGE_HEIGHT))?;
+        response.document_props.outlines = Self::read_outlines(&document)?;
+        Self::read_pdf_data(file_path, &mut response)?;
 
-                let output_path = temp_dir.clone().join(&format!("page-{}.png", i));
-                let output_dir = output_path.as_path().to_string_lossy().to_string();
+        let res = Self::prepare(file_path, response, move |_, temp_dir, response| {
+            Self::save_pages(&document, &response.options, temp_dir)
+        })?;
 
-                pixmap
-                    .save_as(&output_dir, mupdf::ImageFormat::PNG)
-                    .map_err(|err| Error::Error(err.to_string()).to_string())?;
-            }
+        info!("prepare pdf success !");
+        Ok(res)
+    }
 
-            Ok(())
+    /// odt、odp、rtf, 转换成 html 后使用 mupdf 渲染, `convert` 负责生成 html 及其引用的图片
+    fn prepare_html<C>(file_path: &str, response: HttpResponse, page_size: (f32, f32), convert: C) -> Result<HttpResponse, Error>
+    where
+        C: FnOnce(&str, &PathBuf) -> Result<String, Error>,
+    {
+        info!("prepare html document ...");
+
+        let options = response.options.clone();
+        let res = Self::prepare(file_path, response, move |file_path, temp_dir, _| {
+            let html_dir = temp_dir.join(HTML_DIR);
+            fs::create_dir_all(&html_dir).map_err(|err| Error::io(err, &html_dir.to_string_lossy()))?;
+
+            let html = convert(file_path, &html_dir)?;
+            let html_path = html_dir.join(HTML_FILE).as_path().to_string_lossy().to_string();
+            FileUtils::write_to_file_when_clear(&html_path, &html)?;
+
+            let mut document = mupdf::document::Document::open(&html_path).map_err(|err| Error::Error(err.to_string()))?;
+            Self::layout(&mut document, &options, page_size)?;
+            Self::save_pages(&document, &options, temp_dir)
         })?;
 
-        info!("prepare pdf success !");
+        info!("prepare html document success !");
         Ok(res)
     }
 
+    /// 加密 pdf, 页面只在内存中渲染, 不写入缓存目录
+    fn prepare_encrypted_pdf(mut document: mupdf::document::Document, mut response: HttpResponse) -> Result<HttpResponse, Error> {
+        info!("pdf `{}` is encrypted ...", &response.file_props.name);
+
+        let password = response.options.password.clone().unwrap_or_default();
+        if password.is_empty() {
+            let error = Error::PasswordRequired {
+                path: response.file_props.name.clone(),
+                incorrect: false,
+            };
+            return Ok(Self::password_required(response, error));
+        }
+
+        let authenticated = document
+            .authenticate(&password)
+            .map_err(|err| Error::Error(err.to_string()))?;
+        if !authenticated {
+            let error = Error::PasswordRequired {
+                path: response.file_props.name.clone(),
+                incorrect: true,
+            };
+            return Ok(Self::password_required(response, error));
+        }
+
+        response.document_props.outlines = Self::read_outlines(&document)?;
+        let file_path = response.file_props.path.clone();
+        Self::read_pdf_data(&file_path, &mut response)?;
+
+        let mut contents: Vec<PreviewProps> = Vec::new();
+        Self::render_pages(&document, &response.options, |i, pixmap| {
+            let mut buffer: Vec<u8> = Vec::new();
+            pixmap
+                .write_to(&mut buffer, mupdf::ImageFormat::PNG)
+                .map_err(|err| Error::Error(err.to_string()))?;
+
+            contents.push(PreviewProps {
+                name: format!("page-{}.png", i),
+                path: String::new(),
+                content: Utils::generate_image(buffer),
+            });
+            Ok(())
+        })?;
+
+        let suffix = response.file_props.suffix.clone();
+        response.code = 200;
+        response.body = serde_json::to_string(&contents).unwrap_or("".to_string());
+        response.suffix_props = SuffixProps {
+            name: suffix.clone(),
+            _type: String::from("preview"),
+            list: vec![suffix],
+        };
+
+        info!("prepare encrypted pdf success !");
+        Ok(response)
+    }
+
+    /// 打开文档, 加密文档使用密码验证, 可重排文档按默认大小排版, 返回文档和是否加密
+    pub fn open_document(file_path: &str, password: Option<&str>) -> Result<(mupdf::document::Document, bool), Error> {
+        let mut document = mupdf::document::Document::open(file_path).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let encrypted = document.needs_password().map_err(|err| Error::Error(err.to_string()))?;
+        if encrypted {
+            let authenticated = document
+                .authenticate(password.unwrap_or(""))
+                .map_err(|err| Error::Error(err.to_string()))?;
+            if !authenticated {
+                return Err(Error::PasswordRequired {
+                    path: file_path.to_string(),
+                    incorrect: password.is_some_and(|password| !password.is_empty()),
+                });
+            }
+        }
+
+        Self::layout(&mut document, &RequestOptions::default(), (DEFAULT_PAGE_WIDTH, DEFAULT_PAGE_HEIGHT))?;
+        Ok((document, encrypted))
+    }
+
+    /// 读取文档文字, 按块返回每行文字和字体大小
+    pub fn read_text_blocks(file_path: &str, password: Option<&str>) -> Result<Vec<Vec<(String, f32)>>, Error> {
+        let (document, _) = Self::open_document(file_path, password)?;
+        let pages = document.pages().map_err(|err| Error::Error(err.to_string()))?;
+
+        let mut blocks: Vec<Vec<(String, f32)>> = Vec::new();
+        for page in pages {
+            let page = page.map_err(|err| Error::Error(err.to_string()))?;
+            let text_page = page
+                .to_text_page(mupdf::TextPageOptions::empty())
+                .map_err(|err| Error::Error(err.to_string()))?;
+
+            for block in text_page.blocks() {
+                let mut lines: Vec<(String, f32)> = Vec::new();
+                for line in block.lines() {
+                    let mut text = String::new();
+                    let mut size: f32 = 0.0;
+                    for c in line.chars() {
+                        if let Some(ch) = c.char() {
+                            text.push(ch);
+                            size = size.max(c.size());
+                        }
+                    }
+
+                    let text = text.trim().to_string();
+                    if !text.is_empty() {
+                        lines.push((text, size));
+                    }
+                }
+
+                if !lines.is_empty() {
+                    blocks.push(lines);
+                }
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// 读取 docx 的标题、段落、列表和表格, 来自 `word/document.xml`
+    pub fn read_docx_blocks(file_path: &str) -> Result<Vec<DocumentBlock>, Error> {
+        let content = Self::read_zip_entry(file_path, "word/document.xml")?;
+        let mut reader = quick_xml::Reader::from_str(&content);
+
+        let mut blocks: Vec<DocumentBlock> = Vec::new();
+        let mut text = String::new();
+        let mut heading: usize = 0;
+        let mut is_list = false;
+        let mut table_depth: usize = 0;
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut cells: Vec<String> = Vec::new();
+        let mut cell = String::new();
+        let mut in_text = false;
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(file_path, err))? {
+                Event::Start(e) => match e.name().as_ref() {
+                    b"w:p" => {
+                        text.clear();
+                        heading = 0;
+                        is_list = false;
+                    }
+                    b"w:t" => in_text = true,
+                    b"w:tbl" => {
+                        table_depth += 1;
+                        if table_depth == 1 {
+                            rows.clear();
+                        }
+                    }
+                    b"w:tr" if table_depth == 1 => cells.clear(),
+                    b"w:tc" if table_depth == 1 => cell.clear(),
+                    b"w:numPr" => is_list = true,
+                    _ => {}
+                },
+                Event::Empty(e) => match e.name().as_ref() {
+                    b"w:pStyle" => heading = Self::get_heading_level(&e),
+                    b"w:numPr" => is_list = true,
+                    b"w:tab" => text.push('\t'),
+                    b"w:br" | b"w:cr" => text.push(' '),
+                    _ => {}
+                },
+                Event::Text(e) => {
+                    if in_text {
+                        let value = e.unescape().map_err(|err| Error::corrupt_file(file_path, err))?;
+                        text.push_str(&value);
+                    }
+                }
+                Event::End(e) => match e.name().as_ref() {
+                    b"w:t" => in_text = false,
+                    b"w:p" => {
+                        let value = text.trim().to_string();
+                        if table_depth > 0 {
+                            if !value.is_empty() {
+                                if !cell.is_empty() {
+                                    cell.push(' ');
+                                }
+                                cell.push_str(&value);
+                            }
+                        } else if !value.is_empty() {
+                            blocks.push(if heading > 0 {
+                                DocumentBlock::Heading(heading, value)
+                            } else if is_list {
+                                DocumentBlock::ListItem(value)
+                            } else {
+                                DocumentBlock::Paragraph(value)
+                            });
+                        }
+                    }
+                    b"w:tc" if table_depth == 1 => cells.push(cell.clone()),
+                    b"w:tr" if table_depth == 1 => rows.push(cells.clone()),
+                    b"w:tbl" => {
+                        table_depth = table_depth.saturating_sub(1);
+                        if table_depth == 0 && !rows.is_empty() {
+                            blocks.push(DocumentBlock::Table(rows.clone()));
+                        }
+                    }
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// `Heading1`、`Title` 等样式转换成标题级别
+    fn get_heading_level(e: &BytesStart) -> usize {
+        let value = match e.try_get_attribute(b"w:val").ok().flatten() {
+            Some(value) => String::from_utf8_lossy(&value.value).to_lowercase(),
+            None => return 0,
+        };
+
+        if value == "title" {
+            return 1;
+        }
+
+        match value.strip_prefix("heading") {
+            Some(level) => level.trim().parse::<usize>().unwrap_or(1).clamp(1, 6),
+            None => 0,
+        }
+    }
+
+    /// 读取 pptx 每页的文字, 按顺序读取 `ppt/slides/slideN.xml`
+    pub fn read_pptx_blocks(file_path: &str) -> Result<Vec<DocumentBlock>, Error> {
+        let reader = FileUtils::read_file_buffer(file_path)?;
+        let mut archive = zip::ZipArchive::new(reader).map_err(|err| Error::corrupt_file(file_path, err))?;
+
+        let mut slides: Vec<(usize, String)> = archive
+            .file_names()
+            .filter_map(|name| {
+                let number = name.strip_prefix("ppt/slides/slide")?.strip_suffix(".xml")?;
+                let number = number.parse::<usize>().ok()?;
+                Some((number, name.to_string()))
+            })
+            .collect();
+        slides.sort_by_key(|(number, _)| *number);
+
+        let mut blocks: Vec<DocumentBlock> = Vec::new();
+        for (number, name) in slides.iter() {
+            let mut content = String::new();
+            let mut file = archive.by_name(name).map_err(|err| Error::corrupt_file(file_path, err))?;
+            file.read_to_string(&mut content)
+                .map_err(|err| Error::corrupt_file(file_path, err))?;
+
+            blocks.push(DocumentBlock::Heading(1, format!("Slide {}", number)));
+            blocks.extend(Self::read_slide(file_path, &content)?);
+        }
+
+        Ok(blocks)
+    }
+
+    fn read_slide(file_path: &str, content: &str) -> Result<Vec<DocumentBlock>, Error> {
+        let mut reader = quick_xml::Reader::from_str(content);
+        let mut blocks: Vec<DocumentBlock> = Vec::new();
+        let mut text = String::new();
+        let mut is_title = false;
+        let mut is_list = false;
+        let mut in_text = false;
+        let mut in_table = false;
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut cells: Vec<String> = Vec::new();
+        let mut cell = String::new();
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(file_path, err))? {
+                Event::Start(e) => match e.name().as_ref() {
+                    b"p:sp" => is_title = false,
+                    b"a:p" => {
+                        text.clear();
+                        is_list = false;
+                    }
+                    b"a:t" => in_text = true,
+                    b"a:tbl" => {
+                        in_table = true;
+                        rows.clear();
+                    }
+                    b"a:tr" => cells.clear(),
+                    b"a:tc" => cell.clear(),
+                    b"a:buChar" | b"a:buAutoNum" => is_list = true,
+                    _ => {}
+                },
+                Event::Empty(e) => match e.name().as_ref() {
+                    b"p:ph" => {
+                        let kind = e
+                            .try_get_attribute(b"type")
+                            .ok()
+                            .flatten()
+                            .map(|kind| String::from_utf8_lossy(&kind.value).to_string());
+                        is_title = matches!(kind.as_deref(), Some("title") | Some("ctrTitle"));
+                    }
+                    b"a:buChar" | b"a:buAutoNum" => is_list = true,
+                    b"a:br" => text.push(' '),
+                    _ => {}
+                },
+                Event::Text(e) => {
+                    if in_text {
+                        let value = e.unescape().map_err(|err| Error::corrupt_file(file_path, err))?;
+                        text.push_str(&value);
+                    }
+                }
+                Event::End(e) => match e.name().as_ref() {
+                    b"a:t" => in_text = false,
+                    b"a:p" => {
+                        let value = text.trim().to_string();
+                        if value.is_empty() {
+                            continue;
+                        }
+
+                        if in_table {
+                            if !cell.is_empty() {
+                                cell.push(' ');
+                            }
+                            cell.push_str(&value);
+                        } else if is_title {
+                            blocks.push(DocumentBlock::Heading(2, value));
+                        } else if is_list {
+                            blocks.push(DocumentBlock::ListItem(value));
+                        } else {
+                            blocks.push(DocumentBlock::Paragraph(value));
+                        }
+                    }
+                    b"a:tc" => cells.push(cell.clone()),
+                    b"a:tr" => rows.push(cells.clone()),
+                    b"a:tbl" => {
+                        in_table = false;
+                        if !rows.is_empty() {
+                            blocks.push(DocumentBlock::Table(rows.clone()));
+                        }
+                    }
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    fn read_zip_entry(file_path: &str, name: &str) -> Result<String, Error> {
+        let reader = FileUtils::read_file_buffer(file_path)?;
+        let mut archive = zip::ZipArchive::new(reader).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let mut file = archive.by_name(name).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|err| Error::corrupt_file(file_path, err))?;
+        Ok(content)
+    }
+
+    /// 需要密码
+    fn password_required(mut response: HttpResponse, error: Error) -> HttpResponse {
+        response.set_error(error);
+        response.suffix_props = SuffixProps {
+            name: response.file_props.suffix.clone(),
+            _type: String::from("password"),
+            list: Vec::new(),
+        };
+        response
+    }
+
+    /// 可重排文档(epub、fb2、html 等)按页面宽高和字体大小排版
+    fn layout(document: &mut mupdf::document::Document, options: &RequestOptions, page_size: (f32, f32)) -> Result<(), Error> {
+        let reflowable = document.is_reflowable().map_err(|err| Error::Error(err.to_string()))?;
+        if !reflowable {
+            return Ok(());
+        }
+
+        let width = options.page_width.unwrap_or(page_size.0);
+        let height = options.page_height.unwrap_or(page_size.1);
+        let font_size = options.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+        info!("layout document, width: {}, height: {}, font size: {}", width, height, font_size);
+
+        document
+            .layout(width, height, font_size)
+            .map_err(|err| Error::Error(err.to_string()))?;
+        Ok(())
+    }
+
+    /// pdf 附件、表单、批注
+    fn read_pdf_data(file_path: &str, response: &mut HttpResponse) -> Result<(), Error> {
+        let pdf = DOCUMENT_SUFFIXES.get(0).unwrap();
+        if &response.file_props.suffix != pdf {
+            return Ok(());
+        }
+
+        // 附件、表单、批注只是附加信息, 读取失败时不影响预览
+        if let Err(err) = Pdf::read(file_path, response) {
+            error!("read pdf data of `{}` error: {}", file_path, err);
+        }
+
+        Ok(())
+    }
+
+    /// 读取目录
+    fn read_outlines(document: &mupdf::document::Document) -> Result<Vec<DocumentOutline>, Error> {
+        let outlines = document.outlines().map_err(|err| Error::Error(err.to_string()))?;
+        Ok(Self::convert_outlines(&outlines))
+    }
+
+    fn convert_outlines(outlines: &Vec<mupdf::Outline>) -> Vec<DocumentOutline> {
+        outlines
+            .iter()
+            .map(|outline| DocumentOutline {
+                title: outline.title.clone(),
+                page: outline.page,
+                uri: outline.uri.clone().unwrap_or_default(),
+                children: Self::convert_outlines(&outline.down),
+            })
+            .collect()
+    }
+
+    /// 渲染页面并保存到临时目录
+    fn save_pages(document: &mupdf::document::Document, options: &RequestOptions, temp_dir: &PathBuf) -> Result<(), Error> {
+        Self::render_pages(document, options, |i, pixmap| {
+            let output_path = temp_dir.clone().join(&format!("page-{}.png", i));
+            let output_dir = output_path.as_path().to_string_lossy().to_string();
+
+            pixmap
+                .save_as(&output_dir, mupdf::ImageFormat::PNG)
+                .map_err(|err| Error::Error(err.to_string()))
+        })
+    }
+    /// 逐页渲染, 每页之前检查任务是否已取消, 每页占用一个调度器许可, 首页优先, 需在阻塞线程中调用
+    /// 逐页渲染, 每页之前检查任务是否已取消, 每页占用一个调度器许可, 首页优先
+    fn render_pages<F>(document: &mupdf::document::Document, options: &RequestOptions, mut func: F) -> Result<(), Error>
+    where
+        F: FnMut(usize, mupdf::Pixmap) -> Result<(), Error>,
+    {
+        let pages = document.pages().map_err(|err| Error::Error(err.to_string()))?;
+        let job_id = options.job.as_ref().map(|job| job.id.clone()).unwrap_or_default();
+
+        for (i, page) in pages.enumerate() {
+            let priority = if i == 0 { Priority::High } else { Priority::Normal };
+            let permit = async_std::task::block_on(Scheduler::acquire(&job_id, priority));
+            options.check_cancelled()?;
+            let page = page.map_err(|err| Error::Error(err.to_string()))?;
+            let pixmap = Self::render_page(&page, 1.0, true)?;
+            drop(permit);
+            func(i, pixmap)?;
+        }
+
+        Ok(())
+    }
+
     /// doc
-    fn prepare_docx(file_path: &str, response: HttpResponse) -> Result<HttpResponse, String> {
+    fn prepare_docx(file_path: &str, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare docx ...");
 
         let res = Self::prepare(file_path, response, |file_path, temp_dir, _| {
             /*
             let file = FileUtils::read_file(file_path)?;
-            let docx = docx_rs::read_docx(&file.to_vec()).map_err(|err| Error::Error(err.to_string()).to_string())?;
+            let docx = docx_rs::read_docx(&file.to_vec()).map_err(|err| Error::Error(err.to_string()))?;
             let run = docx_rs::Run::new().add_break(docx_rs::BreakType::Page);
             let paragraph = Paragraph::new().add_run(run);
 
@@ -119,24 +657,24 @@ impl Document {
             println!("document_rels: {:?}", document_rels);
             let images = xml_docx.media;
             if images.len() == 0 {
-                return Err(Error::Error("failed to prepare docx".to_string()).to_string())
+                return Err(Error::Error("failed to prepare docx".to_string()))
             }
 
             for (i, (_, bytes)) in images.iter().enumerate() {
                 let output_path = temp_dir.clone().join(&format!("page-{}.png", i));
                 let output_dir = output_path.as_path().to_string_lossy().to_string();
-                fs::write(&output_dir, bytes).map_err(|err| Error::Error(err.to_string()).to_string())?;
+                fs::write(&output_dir, bytes).map_err(|err| Error::Error(err.to_string()))?;
             }
 
             */
 
             /*
-            let docx = docx::DocxFile::from_file(PathBuf::from(file_path)).map_err(|_| Error::Error("parse docx error !".to_string()).to_string())?;
-            let docx = docx.parse().map_err(|_| Error::Error("parse docx error !".to_string()).to_string())?;
+            let docx = docx::DocxFile::from_file(PathBuf::from(file_path)).map_err(|_| Error::Error("parse docx error !".to_string()))?;
+            let docx = docx.parse().map_err(|_| Error::Error("parse docx error !".to_string()))?;
             let document = docx.document;
-            let app = docx.app.ok_or(Error::Error("parse docx error !".to_string()).to_string())?;
-            let pages = app.pages.ok_or(Error::Error("get docx pages error !".to_string()).to_string())?;
-            let pages = pages.parse::<i32>().map_err(|_| Error::Error("parse docx pages error !".to_string()).to_string())?;
+            let app = docx.app.ok_or(Error::Error("parse docx error !".to_string()))?;
+            let pages = app.pages.ok_or(Error::Error("get docx pages error !".to_string()))?;
+            let pages = pages.parse::<i32>().map_err(|_| Error::Error("parse docx pages error !".to_string()))?;
             println!("pages: {:#?}", pages);
              */
 
@@ -169,10 +707,259 @@ impl Document {
         Ok(res)
     }
 
+    /// 渲染单页
+    fn render_page(page: &mupdf::Page, scale: f32, alpha: bool) -> Result<mupdf::Pixmap, Error> {
+        let matrix = Matrix::new_scale(scale, scale);
+        page.to_pixmap(&matrix, &mupdf::Colorspace::device_rgb(), 0.0, alpha)
+            .map_err(|err| Error::Error(err.to_string()))
+    }
+
+    /// 导出页面为 png、jpeg、webp 图片, 或合并为一个 pdf
+    pub fn export_pages(file_path: &str, pages: &str, format: &str, output_dir: &str, password: Option<&str>) -> Result<HttpResponse, Error> {
+        info!("export pages `{}` of `{}` to `{}` ...", pages, file_path, format);
+        let mut response = HttpResponse::default();
+        let output_path = Path::new(output_dir);
+        if !output_path.is_dir() {
+            response.set_error(Error::not_found("directory", output_dir));
+            return Ok(response);
+        }
+
+        let format = format.to_lowercase().replace("jpg", "jpeg");
+        if !EXPORT_FORMATS.contains(&format.as_str()) {
+            response.set_error(Error::unsupported_format("export", &format));
+            return Ok(response);
+        }
+
+        let (document, _) = Self::open_document(file_path, password)?;
+        let count = document.page_count().map_err(|err| Error::Error(err.to_string()))?;
+        let indexes = Self::parse_page_range(pages, count.max(0) as usize)?;
+        if indexes.is_empty() {
+            response.set_error(Error::not_found("page range", pages));
+            return Ok(response);
+        }
+
+        let prefix = Path::new(file_path)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or("document".to_string());
+
+        // 不覆盖已有文件, 重名时追加序号, 实际写入的文件名通过 body 返回
+        let mut files: Vec<String> = Vec::new();
+        if format == "pdf" {
+            let output_file = FileUtils::get_unique_file_path(output_path, &format!("{}-pages.pdf", prefix))
+                .as_path()
+                .to_string_lossy()
+                .to_string();
+            Self::export_pdf(&document, &indexes, &output_file)?;
+            files.push(output_file);
+        } else {
+            for index in indexes.iter() {
+                let page = document
+                    .load_page(*index as i32)
+                    .map_err(|err| Error::Error(err.to_string()))?;
+                let pixmap = Self::render_page(&page, EXPORT_SCALE, false)?;
+                let extension = if format == "jpeg" { "jpg" } else { format.as_str() };
+                let output_file = FileUtils::get_unique_file_path(output_path, &format!("{}-page-{}.{}", prefix, index + 1, extension))
+                    .as_path()
+                    .to_string_lossy()
+                    .to_string();
+
+                Self::save_pixmap(&pixmap, &format, &output_file)?;
+                files.push(output_file);
+            }
+        }
+
+        info!("export pages success !");
+        response.code = 200;
+        response.body = serde_json::to_string(&files).unwrap_or("".to_string());
+        Ok(response)
+    }
+
+    /// 保存图片, jpeg、webp 通过 `image` 编码
+    fn save_pixmap(pixmap: &mupdf::Pixmap, format: &str, output_file: &str) -> Result<(), Error> {
+        if format == "png" {
+            return pixmap
+                .save_as(output_file, mupdf::ImageFormat::PNG)
+                .map_err(|err| Error::Error(err.to_string()));
+        }
+
+        let image = image::RgbImage::from_raw(pixmap.width(), pixmap.height(), pixmap.samples().to_vec())
+            .ok_or(Error::Error("convert page to image error !".to_string()))?;
+
+        let image_format = if format == "webp" {
+            image::ImageFormat::WebP
+        } else {
+            image::ImageFormat::Jpeg
+        };
+        image
+            .save_with_format(output_file, image_format)
+            .map_err(|err| Error::Error(err.to_string()))
+    }
+
+    /// 合并页面为一个 pdf
+    fn export_pdf(document: &mupdf::document::Document, indexes: &Vec<usize>, output_file: &str) -> Result<(), Error> {
+        let mut writer = mupdf::DocumentWriter::new(output_file, "pdf", "").map_err(|err| Error::Error(err.to_string()))?;
+
+        for index in indexes.iter() {
+            let page = document
+                .load_page(*index as i32)
+                .map_err(|err| Error::Error(err.to_string()))?;
+            let bounds = page.bounds().map_err(|err| Error::Error(err.to_string()))?;
+            let device = writer.begin_page(bounds).map_err(|err| Error::Error(err.to_string()))?;
+            page.run(&device, &Matrix::IDENTITY)
+                .map_err(|err| Error::Error(err.to_string()))?;
+            writer.end_page(device).map_err(|err| Error::Error(err.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// 解析页码范围, 如 `1-3,5,8-`, 为空时返回所有页, 返回从 0 开始的索引
+    fn parse_page_range(pages: &str, count: usize) -> Result<Vec<usize>, Error> {
+        if pages.trim().is_empty() {
+            return Ok((0..count).collect());
+        }
+
+        let parse = |value: &str, default: usize| -> Result<usize, Error> {
+            let value = value.trim();
+            if value.is_empty() {
+                return Ok(default);
+            }
+
+            value
+                .parse::<usize>()
+                .map_err(|_| Error::invalid_argument("page range", pages, format!("`{}` is not a page number", value)))
+        };
+
+        let mut indexes: Vec<usize> = Vec::new();
+        for part in pages.split(',') {
+            let (start, end) = match part.split_once('-') {
+                Some((start, end)) => (parse(start, 1)?, parse(end, count)?),
+                None => {
+                    let page = parse(part, 0)?;
+                    (page, page)
+                }
+            };
+
+            for page in start.max(1)..=end.min(count) {
+                if !indexes.contains(&(page - 1)) {
+                    indexes.push(page - 1);
+                }
+            }
+        }
+
+        Ok(indexes)
+    }
+
+    /// 生成缩略图, 立即返回文件 hash, 缩略图在后台逐页渲染并通过 `DOCUMENT_THUMBNAIL_EVENT` 发送到前端
+    pub fn thumbnails(app: &AppHandle, file_path: &str, password: Option<String>) -> Result<HttpResponse, Error> {
+        info!("prepare thumbnails of `{}` ...", file_path);
+        let mut response = HttpResponse::default();
+        let hash = FileUtils::get_file_hash(file_path)?;
+        if hash.is_empty() {
+            response.set_error(Error::corrupt_file(file_path, "the file is empty"));
+            return Ok(response);
+        }
+
+        let app = app.clone();
+        let file_path = file_path.to_string();
+        let hash_cloned = hash.clone();
+        async_std::task::spawn_blocking(move || {
+            if let Err(err) = Self::render_thumbnails(&app, &file_path, password.as_deref(), &hash_cloned) {
+                error!("render thumbnails of `{}` error: {}", &file_path, &err);
+                Self::send_thumbnail(
+                    &app,
+                    DocumentThumbnail {
+                        hash: hash_cloned,
+                        finished: true,
+                        error: err.to_string(),
+                        ..Default::default()
+                    },
+                );
+            }
+        });
+
+        response.code = 200;
+        response.body = hash;
+        Ok(response)
+    }
+
+    /// 渲染缩略图, 按文件 hash 缓存, 加密文档不缓存
+    fn render_thumbnails(app: &AppHandle, file_path: &str, password: Option<&str>, hash: &str) -> Result<(), Error> {
+        let (document, encrypted) = Self::open_document(file_path, password)?;
+        let cache_dir = if encrypted {
+            None
+        } else {
+            let cache_dir = Utils::get_program_dir().join(THUMBNAIL_DIR).join(hash);
+            fs::create_dir_all(&cache_dir).map_err(|err| Error::io(err, &cache_dir.to_string_lossy()))?;
+            Some(cache_dir)
+        };
+
+        let count = document.page_count().map_err(|err| Error::Error(err.to_string()))?.max(0) as usize;
+        for index in 0..count {
+            let cache_file = cache_dir.as_ref().map(|dir| dir.join(format!("thumb-{}.png", index)));
+            let data = match &cache_file {
+                Some(cache_file) if cache_file.exists() => FileUtils::read_file(&cache_file.as_path().to_string_lossy().to_string())?,
+                _ => {
+                    let page = document
+                        .load_page(index as i32)
+                        .map_err(|err| Error::Error(err.to_string()))?;
+                    let bounds = page.bounds().map_err(|err| Error::Error(err.to_string()))?;
+                    let width = (bounds.x1 - bounds.x0).max(1.0);
+                    let permit = async_std::task::block_on(Scheduler::acquire(hash, Priority::Normal));
+                    let pixmap = Self::render_page(&page, THUMBNAIL_WIDTH / width, false)?;
+                    drop(permit);
+
+                    let mut buffer: Vec<u8> = Vec::new();
+                    pixmap
+                        .write_to(&mut buffer, mupdf::ImageFormat::PNG)
+                        .map_err(|err| Error::Error(err.to_string()))?;
+
+                    // 先写临时文件再重命名, 中断时不会留下不完整的缓存
+                    if let Some(cache_file) = &cache_file {
+                        FileUtils::write_to_file_atomic(&cache_file.as_path().to_string_lossy(), &buffer)?;
+                    }
+
+                    buffer
+                }
+            };
+
+            Self::send_thumbnail(
+                app,
+                DocumentThumbnail {
+                    hash: hash.to_string(),
+                    index,
+                    count,
+                    content: Utils::generate_image(data),
+                    ..Default::default()
+                },
+            );
+        }
+
+        Self::send_thumbnail(
+            app,
+            DocumentThumbnail {
+                hash: hash.to_string(),
+                count,
+                finished: true,
+                ..Default::default()
+            },
+        );
+
+        info!("render thumbnails of `{}` success !", file_path);
+        Ok(())
+    }
+
+    fn send_thumbnail(app: &AppHandle, thumbnail: DocumentThumbnail) {
+        if let Err(err) = app.emit(DOCUMENT_THUMBNAIL_EVENT, thumbnail) {
+            error!("send thumbnail to window error: {}", err.to_string());
+        }
+    }
+
     /// 读取图片转成 base64
-    fn read_pictures(file_path: &PathBuf) -> Result<Vec<PreviewProps>, String> {
+    fn read_pictures(file_path: &PathBuf) -> Result<Vec<PreviewProps>, Error> {
         let mut contents: Vec<PreviewProps> = Vec::new();
-        let entries = fs::read_dir(&file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let entries = fs::read_dir(&file_path).map_err(|err| Error::io(err, &file_path.to_string_lossy()))?;
 
         for entry in entries {
             let entry = entry.unwrap();
@@ -195,3 +982,20 @@ impl Document {
         return Ok(contents);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_page_range() {
+        assert_eq!(Document::parse_page_range("", 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(Document::parse_page_range("1-3,5,8-", 10).unwrap(), vec![0, 1, 2, 4, 7, 8, 9]);
+        assert_eq!(Document::parse_page_range("-2, 4", 5).unwrap(), vec![0, 1, 3]);
+        assert_eq!(Document::parse_page_range("3,1-3", 5).unwrap(), vec![2, 0, 1]);
+        assert_eq!(Document::parse_page_range("4-9", 5).unwrap(), vec![3, 4]);
+        assert!(Document::parse_page_range("0,6-", 5).unwrap().is_empty());
+        assert!(Document::parse_page_range("a-2", 5).is_err());
+        assert_eq!(Document::parse_page_range("1,,2", 5).unwrap(), vec![0, 1]);
+    }
+}
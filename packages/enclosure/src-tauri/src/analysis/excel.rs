@@ -1,50 +1,110 @@
 //! 读取 excel
 
 use std::env::temp_dir;
-use crate::config::{ExcelCell, ExcelRow, ExcelSheet, ExcelSheetMetadata, HttpResponse, MAX_ASYNC_TASK_COUNT, PREVIEW_FILE};
+use crate::analysis::delimited::Delimited;
+use crate::analysis::number_format::{
+    NumberFormat, DEFAULT_DATE_FORMAT, DEFAULT_DATE_TIME_FORMAT, DEFAULT_DURATION_FORMAT, DEFAULT_TIME_FORMAT,
+};
+use crate::analysis::process::Process;
+use crate::analysis::profile::Profiler;
+use crate::analysis::xlsx::{CellFormats, Xlsx};
+use crate::cache::manifest::Manifest;
+use crate::config::{
+    ExcelCell, ExcelRow, ExcelSheet, ExcelSheetEvent, ExcelSheetLayout, ExcelSheetMetadata, HttpResponse, WorkbookDefinedName, WorkbookProps, WorkbookSheet,
+    DELIMITED_SUFFIXES, EXCEL_SHEET_EVENT,
+};
 use crate::error::Error;
-use crate::prepare::Prepare;
-use crate::semaphore::Semaphore;
+use crate::prepare::{Prepare, Treat};
+use crate::scheduler::{Priority, Scheduler};
 use crate::utils::file::FileUtils;
 use async_std::sync::Arc;
-use calamine::{open_workbook_auto, DataType, Range, Reader};
+use calamine::{open_workbook_auto, DataType, Range, Reader, SheetType, SheetVisible, Sheets};
 use log::{error, info};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::time::Instant;
+use tauri::Manager;
 
 
 pub struct Excel;
 
-const TAKE_EXCEL_COUNT: usize = 100000;
+pub const TAKE_EXCEL_COUNT: usize = 100000;
+
+// 单次最多读取的行数
+const MAX_READ_ROWS: usize = 10000;
+
+// sheet 事件类型
+pub const SHEET_STARTED: &str = "started";
+pub const SHEET_PROGRESS: &str = "progress";
+pub const SHEET_CHUNK: &str = "chunk";
+pub const SHEET_FINISHED: &str = "finished";
+pub const SHEET_FAILED: &str = "failed";
+pub const SHEET_CANCELLED: &str = "cancelled";
+
+// 单元格类型
+pub const CELL_EMPTY: &str = "empty";
+pub const CELL_STRING: &str = "string";
+pub const CELL_NUMBER: &str = "number";
+pub const CELL_BOOL: &str = "bool";
+pub const CELL_DATE: &str = "date";
+pub const CELL_DURATION: &str = "duration";
+pub const CELL_ERROR: &str = "error";
 
 impl Prepare<HttpResponse> for Excel {
-    fn with_response(response: HttpResponse) -> Result<HttpResponse, String> {
+    fn with_response(response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare excel ...");
 
         // temp dir
         let temp_path = FileUtils::create_temp_dir(&response.file_props.prefix, true)?;
-        let mut workbook = open_workbook_auto(&response.file_props.path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let mut workbook = open_workbook_auto(&response.file_props.path).map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
 
         let mut res = response.clone();
         res.code = 200;
+        res.workbook_props = Self::read_workbook_props(&mut workbook, &res);
+
         // sheets
         let sheets = workbook.sheet_names().to_owned();
         if sheets.is_empty() {
             return Ok(res);
         }
 
+        let visibilities: HashMap<String, &'static str> = workbook
+            .sheets_metadata()
+            .iter()
+            .map(|sheet| (sheet.name.clone(), Self::get_visibility(&sheet.visible)))
+            .collect();
+
         let mut index: u32 = 1;
         for sheet_name in sheets {
+            if res.options.is_cancelled() {
+                break;
+            }
+
             info!("found sheet name `{}`", &sheet_name);
+            let visibility = visibilities.get(&sheet_name).copied().unwrap_or("visible");
             let start_time = Instant::now();
             let work_range = workbook
                 .worksheet_range(&sheet_name)
-                .map_err(|err| Error::Error(err.to_string()).to_string())?;
+                .map_err(|err| Error::corrupt_file(&res.file_props.path, err))?;
+            let formulas = Self::read_formulas(&mut workbook, &sheet_name);
             let temp_path_cloned = Arc::new(temp_path.clone());
             let res_cloned = Arc::new(res.clone());
             let index_cloned = Arc::new(index.clone());
             async_std::task::spawn_blocking(move || {
-                let _ = Self::handle_sheet(work_range.clone(), &sheet_name, start_time.clone(), &*temp_path_cloned, &*res_cloned, *index_cloned);
+                let (mut formats, layout) = Self::read_sheet_details(&*res_cloned, &sheet_name, &work_range, visibility);
+                formats.formulas = formulas;
+                formats.show_formulas = res_cloned.options.show_formulas;
+                let result = Self::handle_sheet(work_range.clone(), formats, layout, &sheet_name, start_time.clone(), &*temp_path_cloned, &*res_cloned, *index_cloned);
+                if let Err(err) = result {
+                    error!("handle sheet `{}` error: {}", &sheet_name, &err);
+                    let mut sheet = ExcelSheet::default();
+                    sheet.name = sheet_name.to_string();
+                    sheet.index = *index_cloned;
+                    Self::send_error(&*res_cloned, &*temp_path_cloned, &sheet, 0, &err);
+                }
             });
 
             index += 1;
@@ -58,12 +118,14 @@ impl Excel {
     /// 处理单张 sheet
     fn handle_sheet(
         range: Range<DataType>,
+        formats: CellFormats,
+        layout: ExcelSheetLayout,
         sheet_name: &str,
         start_time: Instant,
         temp_path: &PathBuf,
         response: &HttpResponse,
         index: u32
-    ) -> Result<(), String> {
+    ) -> Result<(), Error> {
         info!("handle sheet: {}", sheet_name);
         let sheet_index = index;
 
@@ -74,27 +136,44 @@ impl Excel {
         let elapsed_time = format!("{:.2?}", start_time.elapsed());
         info!("handle sheet: {}, row count: {}, cell count: {}, time: {}", sheet_name, row_size, cell_size, elapsed_time);
 
+        let mut sheet = ExcelSheet::default();
+        sheet.name = sheet_name.to_string();
+        sheet.index = sheet_index;
+        sheet.rows_count = row_size;
+        sheet.cells_count = cell_size;
+        sheet.layout = layout;
+        Self::send_event(response, SHEET_STARTED, &sheet, 0, "");
+
+        let formats = Arc::new(formats);
         let mut chunks = Vec::new();
         if row_size < TAKE_EXCEL_COUNT {
+            response.options.check_cancelled()?;
             for row in rows {
                 chunks.push(row.to_owned());
             }
 
-            let file_path = temp_path
-                .clone()
-                .join(&format!("{}-{}-{}", index, sheet_name, &response.file_props.prefix));
-            println!("file_path: {:#?}", &file_path);
-            let _ = Self::get_row(&chunks, 1, file_path)?;
+            let file_name = Self::get_chunk_file_name(sheet_index, 0);
+            let file_path = temp_path.clone().join(&file_name);
+            let rows = Self::get_row(&chunks, &formats, 1, file_path)?;
             chunks.clear();
 
+            let mut profiler = Profiler::new(Vec::new(), true);
+            profiler.add_rows(&rows);
+
             let mut metadata = ExcelSheetMetadata::default();
             metadata.name = sheet_name.to_string();
             metadata.row_start = 1;
             metadata.row_end = row_size;
             metadata.task_id = 0;
-            Self::write_result_to_file(temp_path.clone(), sheet_name.to_string(), sheet_index, row_size, cell_size, vec![metadata])?;
+            metadata.file = file_name;
+
+            sheet.metadata = vec![metadata];
+            Self::send_event(response, SHEET_CHUNK, &sheet, row_size, "");
+            sheet.profile = profiler.finish();
+            Self::write_result_to_file(temp_path.clone(), sheet.clone())?;
+            Self::send_event(response, SHEET_FINISHED, &sheet, row_size, "");
         } else {
-            let semaphore = Arc::new(Semaphore::new(MAX_ASYNC_TASK_COUNT));
+            let profiler = Arc::new(Mutex::new(Profiler::new(Vec::new(), true)));
             let mut index = 0;
             let mut task_index = 0;
             let mut tasks = Vec::new();
@@ -107,23 +186,50 @@ impl Excel {
                 chunks.push(row.to_owned());
 
                 if (chunks.len() == TAKE_EXCEL_COUNT && index != row_size) || (index == row_size) {
+                    // 取消时等待已启动的任务结束, 避免清理后继续写入
+                    if let Err(err) = response.options.check_cancelled() {
+                        async_std::task::block_on(futures::future::join_all(tasks));
+                        return Err(err);
+                    }
+
                     task_index += 1;
                     info!("exec task{}", task_index);
-                    let file_path = temp_path.clone().join(&format!("{}-{}-{}-{}-{}", index, task_index, sheet_name, &response.file_props.prefix, task_index));
-                    let semaphore_cloned = semaphore.clone();
-                    let chunks_clone = Arc::new(chunks.clone());
-                    let index_clone = Arc::new(task_index.clone());
-                    let temp_path_clone = Arc::new(file_path.clone());
-                    let res_clone = Arc::new(response.clone());
-                    let result = async_std::task::spawn(Self::handle_row(semaphore_cloned, chunks_clone, index_clone, temp_path_clone, res_clone));
-                    tasks.push(result);
-
+                    let file_name = Self::get_chunk_file_name(sheet_index, task_index);
+                    let file_path = temp_path.clone().join(&file_name);
 
                     let mut metadata = ExcelSheetMetadata::default();
                     metadata.name = sheet_name.to_string();
                     metadata.row_start = start_index;
                     metadata.row_end = index;
                     metadata.task_id = task_index;
+                    metadata.file = file_name;
+
+                    let mut chunk_sheet = sheet.clone();
+                    chunk_sheet.metadata = vec![metadata.clone()];
+
+                    // 第一个 sheet 和每个 sheet 的第一块优先显示
+                    let priority = if sheet_index == 1 || task_index == 1 {
+                        Priority::High
+                    } else {
+                        Priority::Normal
+                    };
+                    let chunks_clone = Arc::new(chunks.clone());
+                    let formats_clone = formats.clone();
+                    let sheet_clone = Arc::new(chunk_sheet);
+                    let temp_path_clone = Arc::new(file_path.clone());
+                    let res_clone = Arc::new(response.clone());
+                    let profiler_clone = profiler.clone();
+                    let result = async_std::task::spawn(Self::handle_row(
+                        priority,
+                        chunks_clone,
+                        formats_clone,
+                        sheet_clone,
+                        temp_path_clone,
+                        res_clone,
+                        profiler_clone,
+                    ));
+                    tasks.push(result);
+                    Self::send_event(response, SHEET_PROGRESS, &sheet, index, "");
 
                     if index == row_size {
                         start_index = 1
@@ -136,120 +242,583 @@ impl Excel {
                 }
             }
 
-            let sheet_name = sheet_name.to_string();
             let temp_path = temp_path.clone();
+            let response = response.clone();
            async_std::task::spawn(async move {
                match futures::future::try_join_all(tasks).await {
                     Ok(_) => {
                         info!("execute tasks success !");
                         // 完成所有任务后写入数据
-                        Self::write_result_to_file(
-                            temp_path,
-                            sheet_name.clone(),
-                            sheet_index,
-                            row_size,
-                            cell_size,
-                            metadatas
-                        ).ok();
+                        sheet.metadata = metadatas;
+                        let profiler = std::mem::take(&mut *profiler.lock().unwrap_or_else(|err| err.into_inner()));
+                        sheet.profile = profiler.finish();
+                        match Self::write_result_to_file(temp_path, sheet.clone()) {
+                            Ok(_) => Self::send_event(&response, SHEET_FINISHED, &sheet, row_size, ""),
+                            Err(err) => Self::send_event(&response, SHEET_FAILED, &sheet, row_size, &err.to_string()),
+                        }
                     }
                     Err(err) => {
                         error!("execute tasks error: {}", err);
+                        Self::send_error(&response, &temp_path, &sheet, 0, &err);
                     }
                 }
             });
         }
 
-        // 任务完成后发送消息到前端
         Ok(())
     }
 
-    /// 写入结果
-    fn write_result_to_file(temp_dir: PathBuf, sheet_name: String, index: u32, rows_count: usize, cells_count: usize, metadata: Vec<ExcelSheetMetadata>) -> Result<(), String> {
-        let mut sheet = ExcelSheet::default();
-        sheet.name = sheet_name;
-        sheet.rows_count = rows_count;
-        sheet.cells_count = cells_count;
-        sheet.metadata = metadata;
-        sheet.index = index;
-
-        // 写入到文件
-        info!("write sheet info into json ...");
-
-        // read file content
-        let json_file_path = temp_dir.join(PREVIEW_FILE);
-        let json_file_str = json_file_path.to_string_lossy().to_string();
+    /// 发送 sheet 进度到前端
+    pub fn send_event(response: &HttpResponse, kind: &str, sheet: &ExcelSheet, rows_processed: usize, error: &str) {
+        let app = match &response.options.app {
+            Some(app) => app,
+            None => return,
+        };
+
+        let event = ExcelSheetEvent {
+            kind: kind.to_string(),
+            path: response.file_props.path.clone(),
+            sheet: sheet.clone(),
+            rows_processed,
+            error: error.to_string(),
+        };
+
+        if let Err(err) = app.emit(EXCEL_SHEET_EVENT, event) {
+            error!("send sheet event error: {}", err);
+        }
+    }
 
-        let mut sheets: Vec<ExcelSheet> = Vec::new();
-        if json_file_path.exists() {
-            let content = FileUtils::read_file_string(&json_file_str)?;
+    /// 发送失败事件, 任务取消时删除该 sheet 已生成的块文件
+    pub fn send_error(response: &HttpResponse, temp_path: &PathBuf, sheet: &ExcelSheet, rows_processed: usize, error: &Error) {
+        if !response.options.is_cancelled() {
+            Self::send_event(response, SHEET_FAILED, sheet, rows_processed, &error.to_string());
+            return;
+        }
 
-            if !content.is_empty() {
-                sheets = serde_json::from_str(&content).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let prefix = format!("{}-", sheet.index);
+        if let Ok(entries) = std::fs::read_dir(temp_path) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&prefix) && name.ends_with(".json") {
+                    let _ = std::fs::remove_file(entry.path());
+                }
             }
         }
 
-        sheets.push(sheet);
-        let content = serde_json::to_string_pretty(&sheets).unwrap(); // 序列化为漂亮格式的 JSON 字符串
-        FileUtils::write_to_file_when_clear(&json_file_str, &content)?;
+        info!("sheet `{}` cancelled, remove chunk files", &sheet.name);
+        Self::send_event(response, SHEET_CANCELLED, sheet, rows_processed, "");
+    }
+
+    /// 写入结果, 多个 sheet 并行写入时由清单保证串行
+    pub fn write_result_to_file(temp_dir: PathBuf, sheet: ExcelSheet) -> Result<(), Error> {
+        // 写入到文件
+        info!("write sheet info into json ...");
+        Manifest::update(&temp_dir, |sheets: &mut Vec<ExcelSheet>| {
+            sheets.retain(|item| item.index != sheet.index);
+            sheets.push(sheet);
+            sheets.sort_by_key(|item| item.index);
+        })?;
         info!("write info json file success !");
         Ok(())
     }
 
-    /// 处理行
+    /// 处理行, 写入完成后发送块信息
     async fn handle_row(
-        semaphore: Arc<Semaphore>,
+        priority: Priority,
         chunks: Arc<Vec<Vec<DataType>>>,
-        index: Arc<usize>,
+        formats: Arc<CellFormats>,
+        sheet: Arc<ExcelSheet>,
         temp_path: Arc<PathBuf>,
         response: Arc<HttpResponse>,
-    ) -> Result<(), String> {
-        let index = *index;
-        info!("handle row, task {} ...", index);
-        semaphore.acquire().await;
+        profiler: Arc<Mutex<Profiler>>,
+    ) -> Result<(), Error> {
+        let sheet = &*sheet;
+        let row_start = sheet.metadata.first().map(|metadata| metadata.row_start).unwrap_or(1);
+        info!("handle row, start at {} ...", row_start);
+        let job_id = response.options.job.as_ref().map(|job| job.id.clone()).unwrap_or_default();
+        let permit = Scheduler::acquire(&job_id, priority).await;
 
         let chunks: &Vec<Vec<DataType>> = &*chunks;
 
         // file_path
         let file_path = &*temp_path;
-        Self::get_row(chunks, index, file_path.clone())?;
-        semaphore.release().await;
+        let result = response
+            .options
+            .check_cancelled()
+            .and_then(|_| Self::get_row(chunks, &*formats, row_start, file_path.clone()));
+        drop(permit);
+        let rows = result?;
+
+        // 先统计当前块再合并, 减少锁的占用
+        let mut chunk_profiler = Profiler::new(Vec::new(), true);
+        chunk_profiler.add_rows(&rows);
+        profiler.lock().unwrap_or_else(|err| err.into_inner()).merge(chunk_profiler);
+
+        let rows_processed = sheet.metadata.first().map(|metadata| metadata.row_end).unwrap_or(0);
+        Self::send_event(&*response, SHEET_CHUNK, sheet, rows_processed, "");
         Ok(())
     }
 
-    fn get_row(chunks: &Vec<Vec<DataType>>, index: usize, file_path: PathBuf) -> Result<(), String> {
+    /// 转换行并写入到文件, 返回转换后的行, `row_start` 为该块第一行的行号(从 1 开始)
+    pub fn get_row(chunks: &Vec<Vec<DataType>>, formats: &CellFormats, row_start: usize, file_path: PathBuf) -> Result<Vec<ExcelRow>, Error> {
+        let rows = Self::convert_rows(chunks.iter(), formats, row_start);
+
+        // 数据处理完成后写入到文件
+        let file_path = file_path.as_path().to_string_lossy().to_string();
+        let content = serde_json::to_string(&rows).unwrap(); // 序列化为漂亮格式的 JSON 字符串
+        FileUtils::write_to_file_atomic(&file_path, &content)?;
+        info!("generate file: {}", &file_path);
+
+        Ok(rows)
+    }
+
+    /// 转换行, `ExcelRow.index` 为整张 sheet 中的行索引(从 0 开始)
+    fn convert_rows<'a, I>(chunks: I, formats: &CellFormats, row_start: usize) -> Vec<ExcelRow>
+    where
+        I: Iterator<Item = &'a Vec<DataType>>,
+    {
         let mut rows: Vec<ExcelRow> = Vec::new();
 
-        for (x, chunk) in chunks.iter().enumerate() {
-            let mut cells: Vec<ExcelCell> = Vec::new();
-            for (y, c) in chunk.iter().enumerate() {
-                let value = match c {
-                    DataType::Empty => String::new(),
-                    DataType::String(ref s) | DataType::DateTimeIso(ref s) | DataType::DurationIso(ref s) => String::from(s),
-                    DataType::Float(ref f) | DataType::DateTime(ref f) | DataType::Duration(ref f) => f.to_string(),
-                    DataType::Int(ref i) => i.to_string(),
-                    DataType::Bool(ref b) => b.to_string(),
-                    DataType::Error(err) => {
-                        error!("read row: {} cell: {}, error: {:#?}", x, y, err);
-                        String::new()
-                    }
+        for (x, chunk) in chunks.enumerate() {
+            let row_index = row_start - 1 + x;
+            rows.push(Self::convert_row(chunk, formats, row_index));
+        }
+
+        rows
+    }
+
+    pub fn convert_row(chunk: &[DataType], formats: &CellFormats, row_index: usize) -> ExcelRow {
+        let mut cells: Vec<ExcelCell> = Vec::new();
+        for (y, c) in chunk.iter().enumerate() {
+            let (kind, mut value, raw) = Self::convert_cell(c, formats.get(row_index, y), formats.date1904);
+            let formula = formats.get_formula(row_index, y).unwrap_or("").to_string();
+            if formats.show_formulas && !formula.is_empty() {
+                value = format!("={}", formula);
+            }
+
+            cells.push(ExcelCell {
+                value,
+                kind: kind.to_string(),
+                raw,
+                formula,
+                row_index,
+                cell_index: y,
+            });
+        }
+
+        ExcelRow { index: row_index, cells }
+    }
+
+    /// 转换单元格, 返回 (类型, 显示值, 原始值)
+    fn convert_cell(cell: &DataType, code: Option<&str>, date1904: bool) -> (&'static str, String, String) {
+        match cell {
+            DataType::Empty => (CELL_EMPTY, String::new(), String::new()),
+            DataType::String(s) => (CELL_STRING, s.to_string(), s.to_string()),
+            DataType::DateTimeIso(s) => (CELL_DATE, s.to_string(), s.to_string()),
+            DataType::DurationIso(s) => (CELL_DURATION, s.to_string(), s.to_string()),
+            DataType::Float(f) => Self::convert_number(*f, code, date1904),
+            DataType::Int(i) => Self::convert_number(*i as f64, code, date1904),
+            DataType::DateTime(f) => {
+                let default_code = if *f < 1.0 {
+                    DEFAULT_TIME_FORMAT
+                } else if f.fract() == 0.0 {
+                    DEFAULT_DATE_FORMAT
+                } else {
+                    DEFAULT_DATE_TIME_FORMAT
                 };
+                let code = code.filter(|code| NumberFormat::is_date(code)).unwrap_or(default_code);
+                (CELL_DATE, NumberFormat::format(*f, code, date1904), f.to_string())
+            }
+            DataType::Duration(f) => {
+                let code = code.filter(|code| NumberFormat::is_date(code)).unwrap_or(DEFAULT_DURATION_FORMAT);
+                (CELL_DURATION, NumberFormat::format(*f, code, date1904), f.to_string())
+            }
+            DataType::Bool(b) => {
+                let value = if *b { "TRUE" } else { "FALSE" };
+                (CELL_BOOL, value.to_string(), b.to_string())
+            }
+            DataType::Error(err) => (CELL_ERROR, err.to_string(), err.to_string()),
+        }
+    }
 
-                cells.push(ExcelCell {
-                    value,
-                    row_index: x,
-                    cell_index: y,
-                });
+    /// 数字, 日期格式的数字按日期处理
+    fn convert_number(value: f64, code: Option<&str>, date1904: bool) -> (&'static str, String, String) {
+        let raw = NumberFormat::format_general(value);
+        match code {
+            Some(code) if NumberFormat::is_date(code) => (CELL_DATE, NumberFormat::format(value, code, date1904), raw),
+            Some(code) => (CELL_NUMBER, NumberFormat::format(value, code, date1904), raw),
+            None => (CELL_NUMBER, raw.clone(), raw),
+        }
+    }
+
+    /// 读取单元格格式和布局, 只支持 xlsx
+    fn read_sheet_details(response: &HttpResponse, sheet_name: &str, range: &Range<DataType>, visibility: &str) -> (CellFormats, ExcelSheetLayout) {
+        let origin = range.start().unwrap_or((0, 0));
+        let mut formats = CellFormats::default();
+        formats.origin = origin;
+        let mut layout = ExcelSheetLayout::default();
+        layout.row_offset = origin.0;
+        layout.col_offset = origin.1;
+
+        if Xlsx::is_xlsx(&response.file_props.suffix) {
+            match Xlsx::read_sheet(&response.file_props.path, sheet_name, origin) {
+                Ok((sheet_formats, sheet_layout)) => {
+                    formats = sheet_formats;
+                    layout = sheet_layout;
+                }
+                Err(err) => error!("read sheet `{}` formats and layout error: {}", sheet_name, err),
             }
+        }
 
-            rows.push(ExcelRow { index: x, cells })
+        if layout.visibility.is_empty() {
+            layout.visibility = visibility.to_string();
         }
 
-        // 数据处理完成后写入到文件
-        let file_path = file_path.as_path().to_string_lossy().to_string();
-        let content = serde_json::to_string(&rows).unwrap(); // 序列化为漂亮格式的 JSON 字符串
-        FileUtils::write_to_file_when_clear(&file_path, &content)?;
-        info!("generate file: {}", &file_path);
+        (formats, layout)
+    }
+
+    /// 读取公式, 不支持的格式返回 None
+    fn read_formulas(workbook: &mut Sheets<BufReader<File>>, sheet_name: &str) -> Option<Range<String>> {
+        match workbook.worksheet_formula(sheet_name) {
+            Ok(formulas) if !formulas.is_empty() => Some(formulas),
+            Ok(_) => None,
+            Err(err) => {
+                error!("read sheet `{}` formulas error: {}", sheet_name, err);
+                None
+            }
+        }
+    }
+
+    /// sheet 是否可见
+    fn get_visibility(visible: &SheetVisible) -> &'static str {
+        match visible {
+            SheetVisible::Visible => "visible",
+            SheetVisible::Hidden => "hidden",
+            SheetVisible::VeryHidden => "veryHidden",
+        }
+    }
+
+    /// sheet 类型
+    fn get_sheet_kind(kind: &SheetType) -> &'static str {
+        match kind {
+            SheetType::WorkSheet => "worksheet",
+            SheetType::ChartSheet => "chartsheet",
+            SheetType::DialogSheet => "dialogsheet",
+            SheetType::MacroSheet => "macrosheet",
+            SheetType::Vba => "vba",
+        }
+    }
+
+    /// workbook 结构, xlsx 额外读取表格、数据透视表和图表
+    fn read_workbook_props(workbook: &mut Sheets<BufReader<File>>, response: &HttpResponse) -> WorkbookProps {
+        let mut props = WorkbookProps::default();
+        if Xlsx::is_xlsx(&response.file_props.suffix) {
+            match Xlsx::read_workbook_props(&response.file_props.path) {
+                Ok(xlsx_props) => props = xlsx_props,
+                Err(err) => error!("read workbook props error: {}", err),
+            }
+        } else {
+            props.defined_names = workbook
+                .defined_names()
+                .iter()
+                .map(|(name, range)| WorkbookDefinedName {
+                    name: name.clone(),
+                    range: range.clone(),
+                    ..WorkbookDefinedName::default()
+                })
+                .collect();
+        }
+
+        // sheet index 从 1 开始, 与 sheet_names 顺序一致
+        props.sheets = workbook
+            .sheets_metadata()
+            .iter()
+            .enumerate()
+            .map(|(index, sheet)| WorkbookSheet {
+                name: sheet.name.clone(),
+                index: index as u32 + 1,
+                visibility: Self::get_visibility(&sheet.visible).to_string(),
+                kind: Self::get_sheet_kind(&sheet.typ).to_string(),
+            })
+            .collect();
+        props.has_vba = workbook.vba_project().map(|project| project.is_ok()).unwrap_or(false);
+        props
+    }
+
+    /// 块文件名
+    pub fn get_chunk_file_name(sheet_index: u32, task_id: usize) -> String {
+        format!("{}-{}.json", sheet_index, task_id)
+    }
+
+    /// 按行读取 sheet, 优先读取块文件, sheet 未处理完成时直接读取 workbook
+    pub fn read_sheet_rows(file_path: &str, sheet_index: u32, start: usize, count: usize, show_formulas: bool) -> Result<HttpResponse, Error> {
+        info!("read sheet {} rows, start: {}, count: {} ...", sheet_index, start, count);
+        let (mut response, temp_dir) = Self::get_temp_dir(file_path)?;
+        let count = count.min(MAX_READ_ROWS);
+        let mut rows = match Self::read_sheet_from_chunks(&temp_dir, sheet_index, start, count)? {
+            Some(rows) => rows,
+            None if DELIMITED_SUFFIXES.contains(&response.file_props.suffix.as_str()) => Delimited::read_rows(file_path, start, count)?,
+            None => Self::read_sheet_from_workbook(file_path, sheet_index, start, count, show_formulas)?,
+        };
+
+        // 块文件中保存的是解析时的显示值
+        if show_formulas {
+            rows.iter_mut()
+                .flat_map(|row| row.cells.iter_mut())
+                .filter(|cell| !cell.formula.is_empty())
+                .for_each(|cell| cell.value = format!("={}", cell.formula));
+        }
+
+        response.code = 200;
+        response.body = serde_json::to_string(&rows).unwrap_or("".to_string());
+        Ok(response)
+    }
+
+    /// 根据文件路径获取 response 和临时目录
+    pub fn get_temp_dir(file_path: &str) -> Result<(HttpResponse, PathBuf), Error> {
+        let file_name = Path::new(file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut response = Process::get_response(&file_name);
+        response.file_props.path = file_path.to_string();
+
+        let temp_dir = FileUtils::create_temp_dir(&response.file_props.prefix, false)?;
+        Ok((response, temp_dir))
+    }
+
+    /// 获取所有 sheet 名称, 顺序与 sheet index 一致
+    pub fn get_sheet_names(file_path: &str) -> Result<Vec<String>, Error> {
+        let suffix = FileUtils::get_file_suffix(file_path);
+        if DELIMITED_SUFFIXES.contains(&suffix.as_str()) {
+            let name = Path::new(file_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return Ok(vec![name]);
+        }
+
+        let workbook = open_workbook_auto(file_path).map_err(|err| Error::corrupt_file(file_path, err))?;
+        Ok(workbook.sheet_names().to_owned())
+    }
+
+    /// 逐行读取整张 sheet, 优先读取块文件, `f` 返回 false 时停止
+    pub fn scan_sheet<F>(file_path: &str, sheet_index: u32, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(ExcelRow) -> bool,
+    {
+        let (response, temp_dir) = Self::get_temp_dir(file_path)?;
+
+        // 块文件
+        let sheets: Vec<ExcelSheet> = Manifest::read(&temp_dir)?.unwrap_or_default();
+        if let Some(sheet) = sheets.iter().find(|sheet| sheet.index == sheet_index) {
+            let exists = sheet.metadata.iter().all(|metadata| !metadata.file.is_empty() && temp_dir.join(&metadata.file).exists());
+            if exists {
+                for metadata in sheet.metadata.iter() {
+                    let content = FileUtils::read_file_string(&temp_dir.join(&metadata.file).to_string_lossy().to_string())?;
+                    let rows: Vec<ExcelRow> = serde_json::from_str(&content).map_err(|err| Error::corrupt_file(&metadata.file, err))?;
+                    for row in rows {
+                        if !f(row) {
+                            return Ok(());
+                        }
+                    }
+                }
+
+                return Ok(());
+            }
+        }
+
+        // csv 等
+        if DELIMITED_SUFFIXES.contains(&response.file_props.suffix.as_str()) {
+            return Delimited::scan(file_path, f);
+        }
+
+        // workbook
+        let mut workbook = open_workbook_auto(file_path).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let sheets = workbook.sheet_names().to_owned();
+        let sheet_name = sheets
+            .get((sheet_index as usize).saturating_sub(1))
+            .ok_or_else(|| Error::not_found("sheet", &sheet_index.to_string()))?;
+        let range = workbook
+            .worksheet_range(sheet_name)
+            .map_err(|err| Error::corrupt_file(file_path, err))?;
+        let (formats, _) = Self::read_sheet_details(&response, sheet_name, &range, "");
+
+        for (index, row) in range.rows().enumerate() {
+            if !f(Self::convert_row(row, &formats, index)) {
+                break;
+            }
+        }
 
         Ok(())
     }
+
+    /// 按行索引读取, 返回顺序与 `indexes` 一致, 块文件完整时只读取包含这些行的块, 否则扫描整张 sheet
+    pub fn read_rows_by_indexes(file_path: &str, sheet_index: u32, indexes: &[usize]) -> Result<Vec<ExcelRow>, Error> {
+        if indexes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let positions: HashMap<usize, usize> = indexes.iter().enumerate().map(|(position, index)| (*index, position)).collect();
+        let mut rows: Vec<Option<ExcelRow>> = vec![None; indexes.len()];
+        let mut found = 0;
+        let mut read = |row: ExcelRow| {
+            if let Some(position) = positions.get(&row.index) {
+                if rows[*position].is_none() {
+                    found += 1;
+                }
+                rows[*position] = Some(row);
+            }
+
+            found < indexes.len()
+        };
+
+        let (_, temp_dir) = Self::get_temp_dir(file_path)?;
+        if !Self::scan_chunks(&temp_dir, sheet_index, indexes, &mut read)? {
+            Self::scan_sheet(file_path, sheet_index, read)?;
+        }
+
+        Ok(rows.into_iter().flatten().collect())
+    }
+
+    /// 只读取包含 `indexes` 中的行的块文件, `f` 返回 false 时停止, sheet 未处理完成时返回 false
+    fn scan_chunks<F>(temp_dir: &PathBuf, sheet_index: u32, indexes: &[usize], f: &mut F) -> Result<bool, Error>
+    where
+        F: FnMut(ExcelRow) -> bool,
+    {
+        let sheets: Vec<ExcelSheet> = Manifest::read(temp_dir)?.unwrap_or_default();
+        let sheet = match sheets.iter().find(|sheet| sheet.index == sheet_index) {
+            Some(sheet) => sheet,
+            None => return Ok(false),
+        };
+
+        let exists = sheet.metadata.iter().all(|metadata| !metadata.file.is_empty() && temp_dir.join(&metadata.file).exists());
+        if !exists {
+            return Ok(false);
+        }
+
+        // metadata 中的行号从 1 开始
+        for metadata in sheet.metadata.iter() {
+            if !indexes.iter().any(|index| metadata.row_start <= index + 1 && *index < metadata.row_end) {
+                continue;
+            }
+
+            let chunk_path = temp_dir.join(&metadata.file).to_string_lossy().to_string();
+            let content = FileUtils::read_file_string(&chunk_path)?;
+            let rows: Vec<ExcelRow> = serde_json::from_str(&content).map_err(|err| Error::corrupt_file(&chunk_path, err))?;
+            for row in rows {
+                if !f(row) {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// 从块文件读取, sheet 未处理完成时返回 None
+    fn read_sheet_from_chunks(temp_dir: &PathBuf, sheet_index: u32, start: usize, count: usize) -> Result<Option<Vec<ExcelRow>>, Error> {
+        let sheets: Vec<ExcelSheet> = match Manifest::read(temp_dir)? {
+            Some(sheets) => sheets,
+            None => return Ok(None),
+        };
+
+        let sheet = match sheets.iter().find(|sheet| sheet.index == sheet_index) {
+            Some(sheet) => sheet,
+            None => return Ok(None),
+        };
+
+        // metadata 中的行号从 1 开始
+        let end = start.saturating_add(count);
+        let mut rows: Vec<ExcelRow> = Vec::new();
+        for metadata in sheet.metadata.iter() {
+            if metadata.row_end <= start || metadata.row_start > end || metadata.file.is_empty() {
+                continue;
+            }
+
+            let chunk_path = temp_dir.join(&metadata.file);
+            if !chunk_path.exists() {
+                return Ok(None);
+            }
+
+            let content = FileUtils::read_file_string(&chunk_path.to_string_lossy().to_string())?;
+            let chunk_rows: Vec<ExcelRow> = serde_json::from_str(&content).map_err(|err| Error::corrupt_file(&chunk_path.to_string_lossy(), err))?;
+            rows.extend(chunk_rows.into_iter().filter(|row| row.index >= start && row.index < end));
+        }
+
+        Ok(Some(rows))
+    }
+
+    /// 直接从 workbook 读取
+    fn read_sheet_from_workbook(file_path: &str, sheet_index: u32, start: usize, count: usize, show_formulas: bool) -> Result<Vec<ExcelRow>, Error> {
+        let mut workbook = open_workbook_auto(file_path).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let sheets = workbook.sheet_names().to_owned();
+
+        // sheet index 从 1 开始
+        let sheet_name = sheets
+            .get((sheet_index as usize).saturating_sub(1))
+            .ok_or_else(|| Error::not_found("sheet", &sheet_index.to_string()))?;
+        let range = workbook
+            .worksheet_range(sheet_name)
+            .map_err(|err| Error::corrupt_file(file_path, err))?;
+
+        let mut response = HttpResponse::default();
+        response.file_props.path = file_path.to_string();
+        response.file_props.suffix = FileUtils::get_file_suffix(file_path);
+        let (mut formats, _) = Self::read_sheet_details(&response, sheet_name, &range, "");
+        formats.formulas = Self::read_formulas(&mut workbook, sheet_name);
+        formats.show_formulas = show_formulas;
+
+        let rows = range
+            .rows()
+            .enumerate()
+            .skip(start)
+            .take(count)
+            .map(|(index, row)| Self::convert_row(row, &formats, index))
+            .collect();
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_scan_chunks() {
+        let temp_dir = std::env::temp_dir().join(format!("excel-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // 第一块不是合法的 json, 读取时会报错
+        let rows: Vec<ExcelRow> = (2..4).map(|index| ExcelRow { index, cells: Vec::new() }).collect();
+        fs::write(temp_dir.join("chunk-1.json"), "[").unwrap();
+        fs::write(temp_dir.join("chunk-2.json"), serde_json::to_string(&rows).unwrap()).unwrap();
+
+        let metadata = |row_start: usize, row_end: usize, file: &str| ExcelSheetMetadata {
+            row_start,
+            row_end,
+            file: file.to_string(),
+            ..Default::default()
+        };
+        let sheet = ExcelSheet {
+            index: 1,
+            metadata: vec![metadata(1, 2, "chunk-1.json"), metadata(3, 4, "chunk-2.json")],
+            ..Default::default()
+        };
+        Manifest::write(&temp_dir, &vec![sheet]).unwrap();
+
+        let mut indexes: Vec<usize> = Vec::new();
+        let scanned = Excel::scan_chunks(&temp_dir, 1, &[3], &mut |row| {
+            indexes.push(row.index);
+            true
+        });
+        assert!(scanned.unwrap());
+        assert_eq!(indexes, vec![2, 3]);
+
+        assert!(Excel::scan_chunks(&temp_dir, 1, &[1], &mut |_| true).is_err());
+        assert!(!Excel::scan_chunks(&temp_dir, 2, &[3], &mut |_| true).unwrap());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
 }
@@ -0,0 +1,449 @@
+//! 导出 sheet 为 csv、json lines、parquet
+
+use crate::analysis::delimited::Delimited;
+use crate::analysis::excel::{
+    Excel, CELL_BOOL, CELL_EMPTY, CELL_NUMBER, SHEET_CANCELLED, SHEET_FAILED, SHEET_FINISHED, SHEET_PROGRESS, SHEET_STARTED,
+};
+use crate::analysis::sql::Sql;
+use crate::analysis::xlsx::Xlsx;
+use crate::config::{ExcelCell, ExportEvent, HttpResponse, SheetRange, DELIMITED_SUFFIXES, EXPORT_SHEET_EVENT};
+use crate::error::Error;
+use crate::job::Job;
+use crate::utils::file::FileUtils;
+use log::{error, info};
+use parquet::basic::{Compression, ConvertedType, Repetition, Type as PhysicalType};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use serde_json::{Number, Value};
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+pub struct Export;
+
+/// 导出任务
+struct ExportTask {
+    app: AppHandle,
+    file_path: String,
+    sheet_index: u32,
+    output_path: String,
+    // 先写入临时文件, 成功后重命名为 output_path
+    temp_path: String,
+    range: SheetRange,
+    has_header: bool,
+    job: Arc<Job>,
+}
+
+/// 读取到的表头或行
+enum ScanRow {
+    Header(Vec<String>),
+    Cells(Vec<ExcelCell>),
+}
+
+/// parquet 列类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    Bool,
+    Double,
+    Text,
+}
+
+// 支持的导出格式
+const EXPORT_FORMATS: [&str; 3] = ["csv", "jsonl", "parquet"];
+
+// 每导出多少行发送一次进度
+const PROGRESS_ROWS: usize = 10000;
+
+// parquet 每个 row group 的行数
+const ROW_GROUP_SIZE: usize = 50000;
+
+impl Export {
+    /// 导出 sheet 或选定范围, 立即返回导出路径, 进度通过 `EXPORT_SHEET_EVENT` 发送, 使用 `cancel_job` 取消
+    pub fn export_sheet(
+        app: &AppHandle,
+        file_path: &str,
+        sheet_index: u32,
+        format: &str,
+        output_path: &str,
+        range: SheetRange,
+        has_header: bool,
+    ) -> Result<HttpResponse, Error> {
+        info!("export sheet {} of `{}` to `{}` ...", sheet_index, file_path, format);
+        let mut response = HttpResponse::default();
+
+        let format = match format.to_lowercase().as_str() {
+            "json" | "ndjson" => "jsonl".to_string(),
+            format => format.to_string(),
+        };
+        if !EXPORT_FORMATS.contains(&format.as_str()) {
+            response.set_error(Error::unsupported_format("export", &format));
+            return Ok(response);
+        }
+
+        let output_dir = Path::new(output_path).parent();
+        if output_path.is_empty() || output_dir.map(|dir| !dir.as_os_str().is_empty() && !dir.is_dir()).unwrap_or(true) {
+            response.set_error(Error::not_found("directory", output_path));
+            return Ok(response);
+        }
+
+        if FileUtils::is_same_file(file_path, output_path) {
+            response.set_error(Error::invalid_argument("output path", output_path, "the same as the source file"));
+            return Ok(response);
+        }
+
+        let job = Job::create(None, "export", file_path);
+        response.job_id = job.id.clone();
+        let task = ExportTask {
+            app: app.clone(),
+            file_path: file_path.to_string(),
+            sheet_index,
+            output_path: output_path.to_string(),
+            temp_path: FileUtils::get_temp_file_path(output_path).to_string_lossy().to_string(),
+            range,
+            has_header,
+            job,
+        };
+
+        async_std::task::spawn_blocking(move || {
+            task.send_event(SHEET_STARTED, 0, "");
+            let result = match format.as_str() {
+                "csv" => task.export_csv(),
+                "jsonl" => task.export_jsonl(),
+                _ => task.export_parquet(),
+            }
+            .and_then(|rows_written| {
+                fs::rename(&task.temp_path, &task.output_path).map_err(|err| Error::io(err, &task.output_path))?;
+                Ok(rows_written)
+            });
+
+            match result {
+                Ok(rows_written) => {
+                    info!("export sheet to `{}` success, rows: {}", &task.output_path, rows_written);
+                    task.send_event(SHEET_FINISHED, rows_written, "");
+                }
+                Err(err) => {
+                    error!("export sheet to `{}` error: {}", &task.output_path, &err);
+                    // 删除未完成的文件, 已存在的目标文件保持不变
+                    let _ = fs::remove_file(&task.temp_path);
+                    if task.job.is_cancelled() {
+                        task.send_event(SHEET_CANCELLED, 0, "");
+                    } else {
+                        task.send_event(SHEET_FAILED, 0, &err.to_string());
+                    }
+                }
+            }
+        });
+
+        response.code = 200;
+        response.body = output_path.to_string();
+        Ok(response)
+    }
+}
+
+impl ExportTask {
+    /// 逐行读取选定范围内的单元格, 表头先于数据行传入
+    fn scan<F>(&self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(ScanRow) -> Result<(), Error>,
+    {
+        let col_start = self.range.col_start.unwrap_or(0);
+        let col_end = self.range.col_end.unwrap_or(usize::MAX);
+        let row_start = self.range.row_start.unwrap_or(0);
+        let row_end = self.range.row_end.unwrap_or(usize::MAX);
+
+        // csv 等的表头不在行中
+        let suffix = FileUtils::get_file_suffix(&self.file_path);
+        let mut need_header = self.has_header;
+        if DELIMITED_SUFFIXES.contains(&suffix.as_str()) {
+            let header = Delimited::read_header(&self.file_path)?;
+            if !header.is_empty() {
+                let count = col_end.saturating_sub(col_start).saturating_add(1);
+                f(ScanRow::Header(header.into_iter().skip(col_start).take(count).collect()))?;
+                need_header = false;
+            }
+        }
+
+        let mut result: Result<(), Error> = Ok(());
+        Excel::scan_sheet(&self.file_path, self.sheet_index, |row| {
+            if row.index > row_end {
+                return false;
+            }
+
+            if row.index < row_start {
+                return true;
+            }
+
+            if let Err(err) = self.job.check() {
+                result = Err(err);
+                return false;
+            }
+
+            let cells: Vec<ExcelCell> = row
+                .cells
+                .into_iter()
+                .filter(|cell| cell.cell_index >= col_start && cell.cell_index <= col_end)
+                .collect();
+
+            result = if need_header {
+                need_header = false;
+                f(ScanRow::Header(cells.into_iter().map(|cell| cell.value.trim().to_string()).collect()))
+            } else {
+                f(ScanRow::Cells(cells))
+            };
+            result.is_ok()
+        })?;
+
+        result
+    }
+
+    fn export_csv(&self) -> Result<usize, Error> {
+        let mut writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_path(&self.temp_path)
+            .map_err(|err| Error::io(err.into(), &self.output_path))?;
+
+        let mut rows_written: usize = 0;
+        self.scan(|row| {
+            match row {
+                ScanRow::Header(header) => writer.write_record(&header),
+                ScanRow::Cells(cells) => {
+                    rows_written += 1;
+                    self.send_progress(rows_written);
+                    writer.write_record(cells.iter().map(|cell| cell.value.as_str()))
+                }
+            }
+            .map_err(|err| Error::io(err.into(), &self.output_path))
+        })?;
+
+        writer.flush().map_err(|err| Error::io(err, &self.output_path))?;
+        Ok(rows_written)
+    }
+
+    /// 每行一个 json 对象, 键为表头或列名
+    fn export_jsonl(&self) -> Result<usize, Error> {
+        let file = File::create(&self.temp_path).map_err(|err| Error::io(err, &self.output_path))?;
+        let mut writer = BufWriter::new(file);
+
+        let col_start = self.range.col_start.unwrap_or(0);
+        let mut header: Vec<String> = Vec::new();
+        let mut columns: Vec<String> = Vec::new();
+        let mut rows_written: usize = 0;
+        self.scan(|row| {
+            let cells = match row {
+                ScanRow::Header(names) => {
+                    header = names;
+                    return Ok(());
+                }
+                ScanRow::Cells(cells) => cells,
+            };
+
+            if columns.len() < cells.len() {
+                columns = Self::get_columns(&header, cells.len(), col_start);
+            }
+
+            // 按列顺序输出, serde_json 的 Map 会按键排序
+            let fields: Vec<String> = cells
+                .iter()
+                .zip(columns.iter())
+                .map(|(cell, name)| format!("{}:{}", Value::String(name.clone()), Self::get_json_value(cell)))
+                .collect();
+            let line = format!("{{{}}}", fields.join(","));
+            writeln!(writer, "{}", line).map_err(|err| Error::io(err, &self.output_path))?;
+            rows_written += 1;
+            self.send_progress(rows_written);
+            Ok(())
+        })?;
+
+        writer.flush().map_err(|err| Error::io(err, &self.output_path))?;
+        Ok(rows_written)
+    }
+
+    /// 先扫描一遍推断列类型, 再按 row group 写入
+    fn export_parquet(&self) -> Result<usize, Error> {
+        let col_start = self.range.col_start.unwrap_or(0);
+        let mut header: Vec<String> = Vec::new();
+        let mut types: Vec<Option<ColumnType>> = Vec::new();
+        self.scan(|row| {
+            match row {
+                ScanRow::Header(names) => header = names,
+                ScanRow::Cells(cells) => {
+                    if types.len() < cells.len() {
+                        types.resize(cells.len(), None);
+                    }
+
+                    for (cell, column_type) in cells.iter().zip(types.iter_mut()) {
+                        *column_type = Self::merge_type(*column_type, cell);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+
+        let columns_count = types.len().max(header.len());
+        types.resize(columns_count, None);
+        let types: Vec<ColumnType> = types.into_iter().map(|kind| kind.unwrap_or(ColumnType::Text)).collect();
+        let columns = Self::get_columns(&header, columns_count, col_start);
+
+        let mut fields: Vec<Arc<Type>> = Vec::new();
+        for (name, column_type) in columns.iter().zip(types.iter()) {
+            let builder = match column_type {
+                ColumnType::Bool => Type::primitive_type_builder(name, PhysicalType::BOOLEAN),
+                ColumnType::Double => Type::primitive_type_builder(name, PhysicalType::DOUBLE),
+                ColumnType::Text => Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY).with_converted_type(ConvertedType::UTF8),
+            };
+            let field = builder
+                .with_repetition(Repetition::OPTIONAL)
+                .build()
+                .map_err(|err| Error::Error(err.to_string()))?;
+            fields.push(Arc::new(field));
+        }
+
+        let schema = Type::group_type_builder("schema")
+            .with_fields(fields)
+            .build()
+            .map_err(|err| Error::Error(err.to_string()))?;
+        let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let file = File::create(&self.temp_path).map_err(|err| Error::io(err, &self.output_path))?;
+        let mut writer =
+            SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties)).map_err(|err| Error::Error(err.to_string()))?;
+
+        let mut rows: Vec<Vec<ExcelCell>> = Vec::new();
+        let mut rows_written: usize = 0;
+        self.scan(|row| {
+            if let ScanRow::Cells(cells) = row {
+                rows.push(cells);
+                if rows.len() == ROW_GROUP_SIZE {
+                    Self::write_row_group(&mut writer, &types, &rows)?;
+                    rows_written += rows.len();
+                    rows.clear();
+                    self.send_progress(rows_written);
+                }
+            }
+            Ok(())
+        })?;
+
+        if !rows.is_empty() {
+            Self::write_row_group(&mut writer, &types, &rows)?;
+            rows_written += rows.len();
+        }
+
+        writer.close().map_err(|err| Error::Error(err.to_string()))?;
+        Ok(rows_written)
+    }
+
+    fn write_row_group(writer: &mut SerializedFileWriter<File>, types: &[ColumnType], rows: &[Vec<ExcelCell>]) -> Result<(), Error> {
+        let mut row_group = writer.next_row_group().map_err(|err| Error::Error(err.to_string()))?;
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column().map_err(|err| Error::Error(err.to_string()))? {
+            let cells: Vec<Option<&ExcelCell>> = rows.iter().map(|row| row.get(index).filter(|cell| !Self::is_empty(cell))).collect();
+            let levels: Vec<i16> = cells.iter().map(|cell| if cell.is_some() { 1 } else { 0 }).collect();
+
+            let result = match types[index] {
+                ColumnType::Bool => {
+                    let values: Vec<bool> = cells.iter().flatten().map(|cell| cell.raw == "true").collect();
+                    column.typed::<BoolType>().write_batch(&values, Some(&levels), None)
+                }
+                ColumnType::Double => {
+                    let values: Vec<f64> = cells.iter().flatten().map(|cell| cell.raw.parse::<f64>().unwrap_or(0.0)).collect();
+                    column.typed::<DoubleType>().write_batch(&values, Some(&levels), None)
+                }
+                ColumnType::Text => {
+                    let values: Vec<ByteArray> = cells.iter().flatten().map(|cell| ByteArray::from(cell.value.as_str())).collect();
+                    column.typed::<ByteArrayType>().write_batch(&values, Some(&levels), None)
+                }
+            };
+
+            result.map_err(|err| Error::Error(err.to_string()))?;
+            column.close().map_err(|err| Error::Error(err.to_string()))?;
+            index += 1;
+        }
+
+        row_group.close().map_err(|err| Error::Error(err.to_string()))?;
+        Ok(())
+    }
+
+    /// 全部为布尔值时为 Bool, 全部为数值时为 Double, 否则为 Text
+    fn merge_type(current: Option<ColumnType>, cell: &ExcelCell) -> Option<ColumnType> {
+        if Self::is_empty(cell) {
+            return current;
+        }
+
+        let cell_type = match cell.kind.as_str() {
+            CELL_BOOL => ColumnType::Bool,
+            CELL_NUMBER if cell.raw.parse::<f64>().is_ok() => ColumnType::Double,
+            _ => ColumnType::Text,
+        };
+
+        match current {
+            None => Some(cell_type),
+            Some(current) if current == cell_type => Some(current),
+            Some(_) => Some(ColumnType::Text),
+        }
+    }
+
+    fn is_empty(cell: &ExcelCell) -> bool {
+        cell.kind == CELL_EMPTY || cell.value.is_empty()
+    }
+
+    /// 数值、布尔值保留类型, 日期等使用显示值
+    fn get_json_value(cell: &ExcelCell) -> Value {
+        if Self::is_empty(cell) {
+            return Value::Null;
+        }
+
+        match cell.kind.as_str() {
+            CELL_BOOL => Value::Bool(cell.raw == "true"),
+            CELL_NUMBER => {
+                if let Ok(number) = cell.raw.parse::<i64>() {
+                    return Value::Number(number.into());
+                }
+
+                cell.raw
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+                    .unwrap_or(Value::String(cell.value.clone()))
+            }
+            _ => Value::String(cell.value.clone()),
+        }
+    }
+
+    /// 列名, 没有表头时为原始列名 A、B、C ...
+    fn get_columns(header: &[String], columns_count: usize, col_start: usize) -> Vec<String> {
+        let mut names: Vec<String> = header.to_vec();
+        names.resize(columns_count.max(header.len()), String::new());
+        for (index, name) in names.iter_mut().enumerate() {
+            if name.is_empty() {
+                *name = Xlsx::get_column_name(col_start + index);
+            }
+        }
+
+        Sql::get_columns(&names, names.len())
+    }
+
+    fn send_progress(&self, rows_written: usize) {
+        if rows_written % PROGRESS_ROWS == 0 {
+            self.send_event(SHEET_PROGRESS, rows_written, "");
+        }
+    }
+
+    fn send_event(&self, kind: &str, rows_written: usize, error: &str) {
+        let event = ExportEvent {
+            kind: kind.to_string(),
+            path: self.file_path.clone(),
+            output_path: self.output_path.clone(),
+            rows_written,
+            error: error.to_string(),
+        };
+
+        if let Err(err) = self.app.emit(EXPORT_SHEET_EVENT, event) {
+            error!("send export event error: {}", err);
+        }
+    }
+}
@@ -1,21 +1,151 @@
 mod archive;
+mod convert;
+mod delimited;
 mod document;
 mod excel;
+mod export;
+mod number_format;
+mod odf;
+mod pdf;
 pub mod process;
+mod profile;
+mod query;
+mod rtf;
+mod search;
+mod sql;
+mod xlsx;
 
 use crate::analysis::archive::Archive;
+use crate::analysis::convert::Convert;
+use crate::analysis::document::Document;
+use crate::analysis::excel::Excel;
+use crate::analysis::export::Export;
+use crate::analysis::pdf::Pdf;
 use crate::analysis::process::Process;
-use crate::config::HttpResponse;
+use crate::analysis::query::Query;
+use crate::analysis::search::Search;
+use crate::analysis::sql::Sql;
+use crate::config::{HttpResponse, SheetQuery, SheetRange};
+use crate::error::Error;
+use crate::job::Job;
 use tauri::ipc::Request;
 
 /// 通过文件流或文件路径读取文件
 #[tauri::command]
-pub async fn process<'a>(app: tauri::AppHandle, request: Request<'a>) -> Result<HttpResponse, String> {
+pub async fn process<'a>(app: tauri::AppHandle, request: Request<'a>) -> Result<HttpResponse, Error> {
     Process::handle(&app, request).await
 }
 
 /// 解压压缩包
 #[tauri::command]
-pub fn unarchive(file_path: &str, full_path: &str) -> Result<HttpResponse, String> {
+pub fn unarchive(file_path: &str, full_path: &str) -> Result<HttpResponse, Error> {
     Archive::unarchive(file_path, full_path)
 }
+
+/// 导出 pdf 附件
+#[tauri::command]
+pub async fn extract_attachment(file_path: String, name: String, output_dir: String, password: Option<String>) -> Result<HttpResponse, Error> {
+    Pdf::extract_attachment(&file_path, &name, &output_dir, password.as_deref())
+}
+
+/// 导出文档页面
+#[tauri::command]
+pub async fn export_pages(
+    file_path: String,
+    pages: String,
+    format: String,
+    output_dir: String,
+    password: Option<String>,
+) -> Result<HttpResponse, Error> {
+    Document::export_pages(&file_path, &pages, &format, &output_dir, password.as_deref())
+}
+
+/// 生成文档缩略图
+#[tauri::command]
+pub async fn thumbnails(app: tauri::AppHandle, file_path: String, password: Option<String>) -> Result<HttpResponse, Error> {
+    Document::thumbnails(&app, &file_path, password)
+}
+
+/// 文档转换成纯文本或 markdown
+#[tauri::command]
+pub async fn convert_document(
+    file_path: String,
+    format: String,
+    output_path: Option<String>,
+    password: Option<String>,
+) -> Result<HttpResponse, Error> {
+    Convert::convert_document(&file_path, &format, output_path.as_deref(), password.as_deref())
+}
+
+/// 按行读取 sheet
+#[tauri::command]
+pub async fn read_sheet_rows(
+    file_path: String,
+    sheet_index: u32,
+    start: usize,
+    count: usize,
+    show_formulas: Option<bool>,
+) -> Result<HttpResponse, Error> {
+    Excel::read_sheet_rows(&file_path, sheet_index, start, count, show_formulas.unwrap_or(false))
+}
+
+/// 排序、过滤 sheet
+#[tauri::command]
+pub async fn query_sheet(file_path: String, sheet_index: u32, query: SheetQuery) -> Result<HttpResponse, Error> {
+    Query::query_sheet(&file_path, sheet_index, &query)
+}
+
+/// 对 sheet、csv 执行 sql 查询
+#[tauri::command]
+pub async fn query_sql(file_path: String, sql: String) -> Result<HttpResponse, Error> {
+    Sql::query(&file_path, &sql)
+}
+
+/// 导出 sheet 为 csv、jsonl、parquet
+#[tauri::command]
+pub async fn export_sheet(
+    app: tauri::AppHandle,
+    file_path: String,
+    sheet_index: u32,
+    format: String,
+    output_path: String,
+    range: Option<SheetRange>,
+    has_header: Option<bool>,
+) -> Result<HttpResponse, Error> {
+    Export::export_sheet(&app, &file_path, sheet_index, &format, &output_path, range.unwrap_or_default(), has_header.unwrap_or(false))
+}
+
+/// 搜索 workbook
+#[tauri::command]
+pub async fn search_workbook(
+    app: tauri::AppHandle,
+    file_path: String,
+    query: String,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+) -> Result<HttpResponse, Error> {
+    Search::search_workbook(&app, &file_path, &query, regex.unwrap_or(false), case_sensitive.unwrap_or(false))
+}
+
+/// 取消任务
+#[tauri::command]
+pub fn cancel_job(id: &str) -> Result<HttpResponse, Error> {
+    let mut response = HttpResponse::default();
+    if Job::cancel(id) {
+        response.code = 200;
+        response.body = id.to_string();
+    } else {
+        response.set_error(Error::not_found("job", id));
+    }
+
+    Ok(response)
+}
+
+/// 正在执行或等待中的任务
+#[tauri::command]
+pub fn list_jobs() -> Result<HttpResponse, Error> {
+    let mut response = HttpResponse::default();
+    response.code = 200;
+    response.body = serde_json::to_string(&Job::list()).unwrap_or("".to_string());
+    Ok(response)
+}
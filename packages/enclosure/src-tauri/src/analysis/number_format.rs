@@ -0,0 +1,759 @@
+//! excel 数字格式, 按单元格格式代码显示日期、百分比、货币等
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+
+pub struct NumberFormat;
+
+/// 格式代码拆分后的片段
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    // 原样输出的文本
+    Literal(String),
+    // 格式字符
+    Char(char),
+    // 经过时间 [h]、[mm]、[ss]
+    Elapsed(char, usize),
+}
+
+/// 日期片段
+#[derive(Debug, Clone, PartialEq)]
+enum DatePart {
+    Literal(String),
+    Year(usize),
+    Month(usize),
+    Day(usize),
+    Hour(usize),
+    Minute(usize),
+    Second(usize),
+    // 秒的小数位
+    Fraction(usize),
+    Elapsed(char, usize),
+    AmPm(bool),
+}
+
+// 默认日期格式
+pub const DEFAULT_DATE_FORMAT: &str = "yyyy-mm-dd";
+pub const DEFAULT_TIME_FORMAT: &str = "hh:mm:ss";
+pub const DEFAULT_DATE_TIME_FORMAT: &str = "yyyy-mm-dd hh:mm:ss";
+pub const DEFAULT_DURATION_FORMAT: &str = "[h]:mm:ss";
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December",
+];
+
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+impl NumberFormat {
+    /// 内置格式
+    pub fn get_builtin(id: u32) -> Option<&'static str> {
+        let code = match id {
+            0 => "General",
+            1 => "0",
+            2 => "0.00",
+            3 => "#,##0",
+            4 => "#,##0.00",
+            5 => "$#,##0_);($#,##0)",
+            6 => "$#,##0_);[Red]($#,##0)",
+            7 => "$#,##0.00_);($#,##0.00)",
+            8 => "$#,##0.00_);[Red]($#,##0.00)",
+            9 => "0%",
+            10 => "0.00%",
+            11 => "0.00E+00",
+            12 => "# ?/?",
+            13 => "# ??/??",
+            14 => DEFAULT_DATE_FORMAT,
+            15 => "d-mmm-yy",
+            16 => "d-mmm",
+            17 => "mmm-yy",
+            18 => "h:mm AM/PM",
+            19 => "h:mm:ss AM/PM",
+            20 => "h:mm",
+            21 => "h:mm:ss",
+            22 => "yyyy-mm-dd h:mm",
+            27..=36 | 50..=58 => DEFAULT_DATE_FORMAT,
+            37 => "#,##0 ;(#,##0)",
+            38 => "#,##0 ;[Red](#,##0)",
+            39 => "#,##0.00;(#,##0.00)",
+            40 => "#,##0.00;[Red](#,##0.00)",
+            45 => "mm:ss",
+            46 => DEFAULT_DURATION_FORMAT,
+            47 => "mm:ss.0",
+            48 => "##0.0E+0",
+            49 => "@",
+            _ => return None,
+        };
+
+        Some(code)
+    }
+
+    /// 是否为常规格式
+    pub fn is_general(code: &str) -> bool {
+        code.is_empty() || code.eq_ignore_ascii_case("general") || code == "@"
+    }
+
+    /// 是否为日期格式
+    pub fn is_date(code: &str) -> bool {
+        if Self::is_general(code) {
+            return false;
+        }
+
+        let section = Self::get_sections(code).into_iter().next().unwrap_or_default();
+        Self::is_date_section(&Self::parse(&section))
+    }
+
+    /// 按格式代码格式化数字
+    pub fn format(value: f64, code: &str, date1904: bool) -> String {
+        if Self::is_general(code) {
+            return Self::format_general(value);
+        }
+
+        let sections = Self::get_sections(code);
+        let (section, value, signed) = Self::choose_section(&sections, value);
+        let parts = Self::parse(&section);
+
+        if Self::is_date_section(&parts) {
+            return Self::format_date(value, &parts, date1904).unwrap_or(Self::format_general(value));
+        }
+
+        let text = Self::format_number(value.abs(), &parts);
+        if signed && value < 0.0 && text.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            return format!("-{}", text);
+        }
+
+        text
+    }
+
+    /// 常规格式, 最多显示 10 位有效小数
+    pub fn format_general(value: f64) -> String {
+        if value.fract() == 0.0 && value.abs() < 1e15 {
+            return format!("{}", value as i64);
+        }
+
+        if value.abs() >= 1e11 || (value != 0.0 && value.abs() < 1e-9) {
+            let text = format!("{:.5E}", value);
+            return Self::trim_exponent(&text);
+        }
+
+        let text = format!("{:.10}", value);
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        text.to_string()
+    }
+
+    /// 序列号转换成日期, 支持 1900 和 1904 日期系统
+    pub fn to_datetime(value: f64, date1904: bool) -> Option<NaiveDateTime> {
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+
+        let days = value.floor() as i64;
+        let millis = ((value - value.floor()) * 86_400_000.0).round() as i64;
+
+        // 1900 日期系统中 1900-02-29 并不存在, 60 之前的序列号需要少算一天
+        let base = if date1904 {
+            NaiveDate::from_ymd_opt(1904, 1, 1)?
+        } else if days < 60 {
+            NaiveDate::from_ymd_opt(1899, 12, 31)?
+        } else {
+            NaiveDate::from_ymd_opt(1899, 12, 30)?
+        };
+
+        let datetime = base.and_hms_opt(0, 0, 0)?;
+        datetime
+            .checked_add_signed(Duration::days(days))?
+            .checked_add_signed(Duration::milliseconds(millis))
+    }
+
+    /// 按 `;` 拆分为正数、负数、零、文本四段
+    fn get_sections(code: &str) -> Vec<String> {
+        let mut sections: Vec<String> = Vec::new();
+        let mut current = String::new();
+        let mut in_quote = false;
+        let mut in_bracket = false;
+        let mut escaped = false;
+
+        for c in code.chars() {
+            if escaped {
+                current.push(c);
+                escaped = false;
+                continue;
+            }
+
+            match c {
+                '\\' if !in_quote => {
+                    current.push(c);
+                    escaped = true;
+                }
+                '"' => {
+                    in_quote = !in_quote;
+                    current.push(c);
+                }
+                '[' if !in_quote => {
+                    in_bracket = true;
+                    current.push(c);
+                }
+                ']' if !in_quote => {
+                    in_bracket = false;
+                    current.push(c);
+                }
+                ';' if !in_quote && !in_bracket => {
+                    sections.push(current.clone());
+                    current.clear();
+                }
+                _ => current.push(c),
+            }
+        }
+
+        sections.push(current);
+        sections
+    }
+
+    /// 选择格式段, 返回值为 (格式, 数值, 是否需要添加负号)
+    fn choose_section(sections: &Vec<String>, value: f64) -> (String, f64, bool) {
+        let first = sections.first().cloned().unwrap_or_default();
+        if value < 0.0 && sections.len() >= 2 && !sections[1].is_empty() {
+            return (sections[1].clone(), value.abs(), false);
+        }
+
+        if value == 0.0 && sections.len() >= 3 && !sections[2].is_empty() {
+            return (sections[2].clone(), value, false);
+        }
+
+        (first, value, true)
+    }
+
+    /// 解析格式段
+    fn parse(section: &str) -> Vec<Part> {
+        let mut parts: Vec<Part> = Vec::new();
+        let chars: Vec<char> = section.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '"' => {
+                    let mut text = String::new();
+                    i += 1;
+                    while i < chars.len() && chars[i] != '"' {
+                        text.push(chars[i]);
+                        i += 1;
+                    }
+                    parts.push(Part::Literal(text));
+                }
+                '\\' => {
+                    if i + 1 < chars.len() {
+                        parts.push(Part::Literal(chars[i + 1].to_string()));
+                        i += 1;
+                    }
+                }
+                // `_x` 占用一个字符宽度, `*x` 重复填充
+                '_' => {
+                    if i + 1 < chars.len() {
+                        parts.push(Part::Literal(" ".to_string()));
+                        i += 1;
+                    }
+                }
+                '*' => {
+                    i += 1;
+                }
+                '[' => {
+                    let mut text = String::new();
+                    i += 1;
+                    while i < chars.len() && chars[i] != ']' {
+                        text.push(chars[i]);
+                        i += 1;
+                    }
+                    if let Some(part) = Self::parse_bracket(&text) {
+                        parts.push(part);
+                    }
+                }
+                _ => parts.push(Part::Char(c)),
+            }
+
+            i += 1;
+        }
+
+        parts
+    }
+
+    /// `[h]` 经过时间, `[$¥-804]` 货币符号, 颜色和条件忽略
+    fn parse_bracket(text: &str) -> Option<Part> {
+        let lower = text.to_lowercase();
+        if !lower.is_empty() && lower.chars().all(|c| c == 'h' || c == 'm' || c == 's') {
+            let c = lower.chars().next()?;
+            if lower.chars().all(|item| item == c) {
+                return Some(Part::Elapsed(c, lower.len()));
+            }
+        }
+
+        if let Some(currency) = text.strip_prefix('$') {
+            let symbol = currency.split('-').next().unwrap_or("");
+            return Some(Part::Literal(symbol.to_string()));
+        }
+
+        None
+    }
+
+    fn is_date_section(parts: &Vec<Part>) -> bool {
+        parts.iter().any(|part| match part {
+            Part::Char(c) => matches!(c.to_ascii_lowercase(), 'y' | 'm' | 'd' | 'h' | 's'),
+            Part::Elapsed(_, _) => true,
+            _ => false,
+        })
+    }
+
+    /// 日期格式化
+    fn format_date(value: f64, parts: &Vec<Part>, date1904: bool) -> Option<String> {
+        let datetime = Self::to_datetime(value, date1904)?;
+        let date_parts = Self::parse_date(parts);
+        let twelve_hour = date_parts.iter().any(|part| matches!(part, DatePart::AmPm(_)));
+
+        let mut text = String::new();
+        for part in date_parts.iter() {
+            match part {
+                DatePart::Literal(literal) => text.push_str(literal),
+                DatePart::Year(len) => {
+                    if *len <= 2 {
+                        text.push_str(&format!("{:02}", datetime.year() % 100));
+                    } else {
+                        text.push_str(&format!("{:04}", datetime.year()));
+                    }
+                }
+                DatePart::Month(len) => {
+                    let month = datetime.month() as usize;
+                    match len {
+                        1 => text.push_str(&month.to_string()),
+                        2 => text.push_str(&format!("{:02}", month)),
+                        3 => text.push_str(&MONTHS[month - 1][..3]),
+                        5 => text.push_str(&MONTHS[month - 1][..1]),
+                        _ => text.push_str(MONTHS[month - 1]),
+                    }
+                }
+                DatePart::Day(len) => {
+                    let weekday = datetime.weekday().num_days_from_monday() as usize;
+                    match len {
+                        1 => text.push_str(&datetime.day().to_string()),
+                        2 => text.push_str(&format!("{:02}", datetime.day())),
+                        3 => text.push_str(&WEEKDAYS[weekday][..3]),
+                        _ => text.push_str(WEEKDAYS[weekday]),
+                    }
+                }
+                DatePart::Hour(len) => {
+                    let mut hour = datetime.hour();
+                    if twelve_hour {
+                        hour = match hour % 12 {
+                            0 => 12,
+                            h => h,
+                        };
+                    }
+                    text.push_str(&Self::pad(hour as i64, *len));
+                }
+                DatePart::Minute(len) => text.push_str(&Self::pad(datetime.minute() as i64, *len)),
+                DatePart::Second(len) => text.push_str(&Self::pad(datetime.second() as i64, *len)),
+                DatePart::Fraction(len) => {
+                    let millis = datetime.nanosecond() / 1_000_000;
+                    let fraction = format!("{:03}", millis);
+                    text.push('.');
+                    text.push_str(&fraction[..(*len).min(3)]);
+                }
+                DatePart::Elapsed(unit, len) => {
+                    let total = match unit {
+                        'h' => value * 24.0,
+                        'm' => value * 24.0 * 60.0,
+                        _ => value * 24.0 * 60.0 * 60.0,
+                    };
+                    text.push_str(&Self::pad(total.floor() as i64, *len));
+                }
+                DatePart::AmPm(short) => {
+                    let am = datetime.hour() < 12;
+                    let label = match (am, short) {
+                        (true, false) => "AM",
+                        (false, false) => "PM",
+                        (true, true) => "A",
+                        (false, true) => "P",
+                    };
+                    text.push_str(label);
+                }
+            }
+        }
+
+        Some(text)
+    }
+
+    /// 合并相同的日期字符, 区分月份和分钟
+    fn parse_date(parts: &Vec<Part>) -> Vec<DatePart> {
+        let mut date_parts: Vec<DatePart> = Vec::new();
+        let mut i = 0;
+
+        while i < parts.len() {
+            match &parts[i] {
+                Part::Literal(text) => date_parts.push(DatePart::Literal(text.clone())),
+                Part::Elapsed(unit, len) => date_parts.push(DatePart::Elapsed(*unit, *len)),
+                Part::Char(c) => {
+                    let lower = c.to_ascii_lowercase();
+
+                    // AM/PM、A/P
+                    if lower == 'a' {
+                        let rest: String = parts[i..]
+                            .iter()
+                            .take(5)
+                            .map(|part| match part {
+                                Part::Char(c) => *c,
+                                _ => '\0',
+                            })
+                            .collect::<String>()
+                            .to_uppercase();
+                        if rest.starts_with("AM/PM") {
+                            date_parts.push(DatePart::AmPm(false));
+                            i += 5;
+                            continue;
+                        }
+
+                        if rest.starts_with("A/P") {
+                            date_parts.push(DatePart::AmPm(true));
+                            i += 3;
+                            continue;
+                        }
+                    }
+
+                    if !matches!(lower, 'y' | 'm' | 'd' | 'h' | 's') {
+                        // 秒后面的 `.0` 为小数秒
+                        if *c == '.' && matches!(date_parts.last(), Some(DatePart::Second(_))) {
+                            let mut len = 0;
+                            while i + 1 + len < parts.len() && parts[i + 1 + len] == Part::Char('0') {
+                                len += 1;
+                            }
+                            if len > 0 {
+                                date_parts.push(DatePart::Fraction(len));
+                                i += 1 + len;
+                                continue;
+                            }
+                        }
+
+                        date_parts.push(DatePart::Literal(c.to_string()));
+                        i += 1;
+                        continue;
+                    }
+
+                    let mut len = 1;
+                    while i + len < parts.len() && matches!(&parts[i + len], Part::Char(next) if next.to_ascii_lowercase() == lower) {
+                        len += 1;
+                    }
+
+                    let part = match lower {
+                        'y' => DatePart::Year(len),
+                        'd' => DatePart::Day(len),
+                        'h' => DatePart::Hour(len),
+                        's' => DatePart::Second(len),
+                        _ => {
+                            if len <= 2 && (Self::after_hour(&date_parts) || Self::before_second(parts, i + len)) {
+                                DatePart::Minute(len)
+                            } else {
+                                DatePart::Month(len)
+                            }
+                        }
+                    };
+
+                    date_parts.push(part);
+                    i += len;
+                    continue;
+                }
+            }
+
+            i += 1;
+        }
+
+        date_parts
+    }
+
+    /// 前一个日期片段是否为小时
+    fn after_hour(date_parts: &Vec<DatePart>) -> bool {
+        for part in date_parts.iter().rev() {
+            match part {
+                DatePart::Literal(_) => continue,
+                DatePart::Hour(_) | DatePart::Elapsed('h', _) => return true,
+                _ => return false,
+            }
+        }
+
+        false
+    }
+
+    /// 后一个日期字符是否为秒
+    fn before_second(parts: &Vec<Part>, start: usize) -> bool {
+        for part in parts[start..].iter() {
+            match part {
+                Part::Char(c) if matches!(c.to_ascii_lowercase(), 'y' | 'm' | 'd' | 'h') => return false,
+                Part::Char(c) if c.to_ascii_lowercase() == 's' => return true,
+                Part::Elapsed('s', _) => return true,
+                _ => continue,
+            }
+        }
+
+        false
+    }
+
+    /// 数字格式化
+    fn format_number(value: f64, parts: &Vec<Part>) -> String {
+        let is_placeholder = |part: &Part| matches!(part, Part::Char('0') | Part::Char('#') | Part::Char('?'));
+        let first = parts.iter().position(|part| is_placeholder(part));
+        let last = parts.iter().rposition(|part| is_placeholder(part));
+
+        // 没有数字占位符, 只输出文本
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                let text = Self::render_literals(&parts[..]);
+                return if parts.iter().any(|part| matches!(part, Part::Char('@'))) {
+                    text.replace('@', &Self::format_general(value))
+                } else {
+                    text
+                };
+            }
+        };
+
+        // 百分比
+        let percent = parts.iter().filter(|part| matches!(part, Part::Char('%'))).count();
+        let mut value = value * 100f64.powi(percent as i32);
+
+        // 占位符后紧跟的 `,` 表示除以 1000
+        let mut scale = 0;
+        let mut j = last + 1;
+        while j < parts.len() && parts[j] == Part::Char(',') {
+            scale += 1;
+            j += 1;
+        }
+        value /= 1000f64.powi(scale);
+
+        let middle = &parts[first..=last];
+        let exponent = middle.iter().position(|part| matches!(part, Part::Char('E') | Part::Char('e')));
+
+        let number = match exponent {
+            Some(index) => Self::format_scientific(value, &middle[..index], &middle[index + 1..]),
+            None => Self::format_decimal(value, middle),
+        };
+
+        let prefix = Self::render_literals(&parts[..first]);
+        let suffix = Self::render_literals(&parts[j..]);
+        format!("{}{}{}", prefix, number, suffix)
+    }
+
+    /// 普通小数
+    fn format_decimal(value: f64, parts: &[Part]) -> String {
+        let dot = parts.iter().position(|part| *part == Part::Char('.'));
+        let (integer_parts, decimal_parts) = match dot {
+            Some(dot) => (&parts[..dot], &parts[dot + 1..]),
+            None => (parts, &parts[parts.len()..]),
+        };
+
+        let grouping = integer_parts.iter().any(|part| *part == Part::Char(','));
+        let min_integer = integer_parts.iter().filter(|part| matches!(part, Part::Char('0'))).count();
+        let decimals = decimal_parts
+            .iter()
+            .filter(|part| matches!(part, Part::Char('0') | Part::Char('#') | Part::Char('?')))
+            .count();
+        let min_decimals = decimal_parts
+            .iter()
+            .filter(|part| matches!(part, Part::Char('0') | Part::Char('?')))
+            .count();
+
+        let text = format!("{:.*}", decimals, value);
+        let (integer, decimal) = match text.split_once('.') {
+            Some((integer, decimal)) => (integer.to_string(), decimal.to_string()),
+            None => (text.clone(), String::new()),
+        };
+
+        let mut integer = if integer == "0" && min_integer == 0 { String::new() } else { integer };
+        while integer.len() < min_integer {
+            integer.insert(0, '0');
+        }
+
+        if grouping {
+            integer = Self::group(&integer);
+        }
+
+        let mut decimal = decimal;
+        while decimal.len() > min_decimals && decimal.ends_with('0') {
+            decimal.pop();
+        }
+
+        if decimal.is_empty() {
+            return integer;
+        }
+
+        format!("{}.{}", integer, decimal)
+    }
+
+    /// 科学计数法
+    fn format_scientific(value: f64, mantissa: &[Part], exponent: &[Part]) -> String {
+        let dot = mantissa.iter().position(|part| *part == Part::Char('.'));
+        let decimals = match dot {
+            Some(dot) => mantissa[dot + 1..].iter().filter(|part| matches!(part, Part::Char('0') | Part::Char('#'))).count(),
+            None => 0,
+        };
+
+        let always_sign = exponent.first() == Some(&Part::Char('+'));
+        let digits = exponent.iter().filter(|part| matches!(part, Part::Char('0') | Part::Char('#'))).count().max(1);
+
+        let text = format!("{:.*e}", decimals, value);
+        let (base, power) = text.split_once('e').unwrap_or((&text, "0"));
+        let power: i32 = power.parse().unwrap_or(0);
+        let sign = if power < 0 {
+            "-"
+        } else if always_sign {
+            "+"
+        } else {
+            ""
+        };
+
+        format!("{}E{}{}", base, sign, Self::pad(power.abs() as i64, digits))
+    }
+
+    /// 千分位
+    fn group(integer: &str) -> String {
+        let chars: Vec<char> = integer.chars().collect();
+        let mut text = String::new();
+        for (i, c) in chars.iter().enumerate() {
+            if i > 0 && (chars.len() - i) % 3 == 0 {
+                text.push(',');
+            }
+            text.push(*c);
+        }
+
+        text
+    }
+
+    /// 输出文本片段, 忽略 `%` 以外的格式字符位置
+    fn render_literals(parts: &[Part]) -> String {
+        let mut text = String::new();
+        for part in parts.iter() {
+            match part {
+                Part::Literal(literal) => text.push_str(literal),
+                Part::Char(',') => {}
+                Part::Char(c) => text.push(*c),
+                Part::Elapsed(_, _) => {}
+            }
+        }
+
+        text
+    }
+
+    fn pad(value: i64, len: usize) -> String {
+        format!("{:0width$}", value, width = len.max(1))
+    }
+
+    /// `1.23450E5` => `1.2345E+05`
+    fn trim_exponent(text: &str) -> String {
+        let (base, power) = text.split_once('E').unwrap_or((text, "0"));
+        let base = if base.contains('.') {
+            base.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            base
+        };
+        let power: i32 = power.parse().unwrap_or(0);
+        let sign = if power < 0 { "-" } else { "+" };
+        format!("{}E{}{:02}", base, sign, power.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(value: f64, code: &str) -> String {
+        NumberFormat::format(value, code, false)
+    }
+
+    #[test]
+    fn test_date_serial() {
+        assert_eq!(format(1.0, DEFAULT_DATE_FORMAT), "1900-01-01");
+        assert_eq!(format(59.0, DEFAULT_DATE_FORMAT), "1900-02-28");
+        assert_eq!(format(61.0, DEFAULT_DATE_FORMAT), "1900-03-01");
+        assert_eq!(format(44927.0, DEFAULT_DATE_FORMAT), "2023-01-01");
+        assert_eq!(format(44927.5, DEFAULT_DATE_TIME_FORMAT), "2023-01-01 12:00:00");
+        assert_eq!(format(44927.0, "d-mmm-yy"), "1-Jan-23");
+        assert_eq!(format(44927.0, "dddd, mmmm d"), "Sunday, January 1");
+        assert_eq!(format(0.75, "h:mm AM/PM"), "6:00 PM");
+        assert_eq!(format(0.5 + 1.5 / 86400.0, "mm:ss.0"), "00:01.5");
+    }
+
+    #[test]
+    fn test_leap_year_bug() {
+        // 1900-02-29 并不存在, 60 显示为前一天
+        assert_eq!(format(60.0, DEFAULT_DATE_FORMAT), "1900-02-28");
+        assert_eq!(NumberFormat::to_datetime(60.0, false), NumberFormat::to_datetime(59.0, false));
+        assert!(NumberFormat::to_datetime(-1.0, false).is_none());
+    }
+
+    #[test]
+    fn test_date1904() {
+        assert_eq!(NumberFormat::format(0.0, DEFAULT_DATE_FORMAT, true), "1904-01-01");
+        assert_eq!(NumberFormat::format(43465.0, DEFAULT_DATE_FORMAT, true), "2023-01-01");
+        assert_eq!(NumberFormat::format(43465.25, DEFAULT_DATE_TIME_FORMAT, true), "2023-01-01 06:00:00");
+    }
+
+    #[test]
+    fn test_elapsed_time() {
+        assert_eq!(format(1.5, "[h]:mm"), "36:00");
+        assert_eq!(format(2.0 + 1.0 / 1440.0, DEFAULT_DURATION_FORMAT), "48:01:00");
+        assert_eq!(format(0.5 / 24.0, "[mm]:ss"), "30:00");
+        assert_eq!(format(1.0 / 1440.0, "[ss]"), "60");
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(format(0.256, "0%"), "26%");
+        assert_eq!(format(0.1234, "0.00%"), "12.34%");
+        assert_eq!(format(-0.5, "0%"), "-50%");
+    }
+
+    #[test]
+    fn test_currency() {
+        let code = NumberFormat::get_builtin(7).unwrap();
+        assert_eq!(format(1234.5, code), "$1,234.50 ");
+        assert_eq!(format(-1234.5, code), "($1,234.50)");
+        assert_eq!(format(1234.5, "[$¥-804]#,##0.00"), "¥1,234.50");
+        assert_eq!(format(1234.5, "#,##0.00\" EUR\""), "1,234.50 EUR");
+    }
+
+    #[test]
+    fn test_sections() {
+        let code = "0.00;[Red]-0.00;\"zero\";@";
+        assert_eq!(format(1.5, code), "1.50");
+        assert_eq!(format(-1.5, code), "-1.50");
+        assert_eq!(format(0.0, code), "zero");
+        // 只有一段时负数自动添加负号
+        assert_eq!(format(-1.5, "0.00"), "-1.50");
+        assert_eq!(format(-0.001, "0.00"), "0.00");
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(format(1234567.0, "#,##0"), "1,234,567");
+        assert_eq!(format(1234567.0, "#,##0,"), "1,235");
+        assert_eq!(format(3.14159, "0.00"), "3.14");
+        assert_eq!(format(0.5, "#.##"), ".5");
+        assert_eq!(format(12345.0, "0.00E+00"), "1.23E+04");
+        assert_eq!(format(0.00012, "0.00E+00"), "1.20E-04");
+        assert_eq!(format(7.0, "000"), "007");
+    }
+
+    #[test]
+    fn test_general() {
+        assert!(NumberFormat::is_general("General"));
+        assert!(NumberFormat::is_general(""));
+        assert_eq!(format(1234567.0, "General"), "1234567");
+        assert_eq!(format(0.1 + 0.2, "General"), "0.3");
+        assert_eq!(format(-2.5, "General"), "-2.5");
+        assert_eq!(format(1e12 + 0.5, "General"), "1E+12");
+        assert_eq!(format(1.5e-10, "General"), "1.5E-10");
+    }
+
+    #[test]
+    fn test_is_date() {
+        assert!(NumberFormat::is_date("yyyy-mm-dd"));
+        assert!(NumberFormat::is_date("[h]:mm"));
+        assert!(!NumberFormat::is_date("0.00%"));
+        assert!(!NumberFormat::is_date("General"));
+        assert!(!NumberFormat::is_date("\"day\" 0"));
+    }
+}
@@ -0,0 +1,363 @@
+//! OpenDocument(odt、odp) 转换成 html
+
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use crate::utils::Utils;
+use log::info;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, PathBuf};
+
+pub struct OpenDocument;
+
+type OdfArchive = zip::ZipArchive<BufReader<File>>;
+
+/// 文字样式
+#[derive(Default, Debug, Clone)]
+struct TextStyle {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+const CONTENT_FILE: &str = "content.xml";
+
+// 批注、演讲者备注、修订中删除的文字, 不显示
+const SKIPPED_ELEMENTS: [&[u8]; 3] = [b"office:annotation", b"presentation:notes", b"text:tracked-changes"];
+
+// 允许的链接协议
+const LINK_SCHEMES: [&str; 3] = ["http:", "https:", "mailto:"];
+
+const HTML_STYLE: &str = "body { margin: 0; } \
+    .slide { page-break-before: always; } \
+    .slide:first-child { page-break-before: auto; } \
+    table { border-collapse: collapse; margin: 4px 0; } \
+    td { border: 1px solid #ccc; padding: 2px 4px; vertical-align: top; } \
+    img { max-width: 100%; }";
+
+impl OpenDocument {
+    /// 转换 odt、odp 为 html, 图片解压到 `output_dir`
+    pub fn to_html(file_path: &str, output_dir: &PathBuf) -> Result<String, Error> {
+        info!("convert open document `{}` to html ...", file_path);
+        let reader = FileUtils::read_file_buffer(file_path)?;
+        let mut archive = zip::ZipArchive::new(reader).map_err(|err| Error::corrupt_file(file_path, err))?;
+
+        let content = Self::read_entry(file_path, &mut archive, CONTENT_FILE)?;
+        let styles = Self::read_styles(file_path, &content)?;
+        let body = Self::convert(file_path, &content, &styles, &mut archive, output_dir)?;
+
+        Ok(format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"/><style>{}</style></head><body>{}</body></html>",
+            HTML_STYLE, body
+        ))
+    }
+
+    /// 读取压缩包中的文件
+    fn read_entry(file_path: &str, archive: &mut OdfArchive, name: &str) -> Result<String, Error> {
+        let mut file = archive.by_name(name).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content).map_err(|err| Error::corrupt_file(file_path, err))?;
+        Ok(content)
+    }
+
+    /// 读取自动样式(粗体、斜体、下划线)
+    fn read_styles(file_path: &str, content: &str) -> Result<HashMap<String, TextStyle>, Error> {
+        let mut styles: HashMap<String, TextStyle> = HashMap::new();
+        let mut reader = quick_xml::Reader::from_str(content);
+        let mut current: Option<String> = None;
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(file_path, err))? {
+                Event::Start(e) if e.name().as_ref() == b"style:style" => {
+                    current = Self::get_attribute(&e, b"style:name");
+                }
+                Event::End(e) if e.name().as_ref() == b"style:style" => {
+                    current = None;
+                }
+                Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"style:text-properties" => {
+                    if let Some(name) = &current {
+                        let underline = Self::get_attribute(&e, b"style:text-underline-style").unwrap_or("none".to_string());
+                        styles.insert(
+                            name.clone(),
+                            TextStyle {
+                                bold: Self::get_attribute(&e, b"fo:font-weight").as_deref() == Some("bold"),
+                                italic: Self::get_attribute(&e, b"fo:font-style").as_deref() == Some("italic"),
+                                underline: underline != "none",
+                            },
+                        );
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(styles)
+    }
+
+    /// 转换 `office:body` 为 html
+    fn convert(
+        file_path: &str,
+        content: &str,
+        styles: &HashMap<String, TextStyle>,
+        archive: &mut OdfArchive,
+        output_dir: &PathBuf,
+    ) -> Result<String, Error> {
+        let mut html = String::new();
+        let mut reader = quick_xml::Reader::from_str(content);
+        let mut stack: Vec<String> = Vec::new();
+        let mut in_body = false;
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(file_path, err))? {
+                Event::Start(e) => {
+                    if e.name().as_ref() == b"office:body" {
+                        in_body = true;
+                        continue;
+                    }
+
+                    if !in_body {
+                        continue;
+                    }
+
+                    if SKIPPED_ELEMENTS.contains(&e.name().as_ref()) {
+                        reader.read_to_end(e.name()).map_err(|err| Error::corrupt_file(file_path, err))?;
+                        continue;
+                    }
+
+                    let (open, close) = Self::convert_element(file_path, &e, styles, archive, output_dir)?;
+                    html.push_str(&open);
+                    stack.push(close);
+                }
+                Event::Empty(e) => {
+                    if !in_body {
+                        continue;
+                    }
+
+                    let (open, close) = Self::convert_element(file_path, &e, styles, archive, output_dir)?;
+                    html.push_str(&open);
+                    html.push_str(&close);
+                }
+                Event::End(e) => {
+                    if e.name().as_ref() == b"office:body" {
+                        break;
+                    }
+
+                    if let Some(close) = stack.pop() {
+                        html.push_str(&close);
+                    }
+                }
+                Event::Text(e) => {
+                    if in_body {
+                        let text = e.unescape().map_err(|err| Error::corrupt_file(file_path, err))?;
+                        html.push_str(&Utils::escape_html(&text));
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(html)
+    }
+
+    /// 元素转换成 html 开始和结束标签
+    fn convert_element(
+        file_path: &str,
+        e: &BytesStart,
+        styles: &HashMap<String, TextStyle>,
+        archive: &mut OdfArchive,
+        output_dir: &PathBuf,
+    ) -> Result<(String, String), Error> {
+        let style = Self::get_style(e, styles);
+        let tags = match e.name().as_ref() {
+            b"text:h" => {
+                let level = Self::get_attribute(e, b"text:outline-level")
+                    .and_then(|level| level.parse::<usize>().ok())
+                    .unwrap_or(1)
+                    .clamp(1, 6);
+                (format!("<h{}{}>", level, style), format!("</h{}>", level))
+            }
+            b"text:p" => (format!("<p{}>", style), "</p>".to_string()),
+            b"text:span" => (format!("<span{}>", style), "</span>".to_string()),
+            b"text:a" => {
+                let href = Self::get_attribute(e, b"xlink:href").unwrap_or_default();
+                let allowed = LINK_SCHEMES.iter().any(|scheme| href.trim().to_lowercase().starts_with(scheme));
+                if allowed {
+                    (format!("<a href=\"{}\">", Utils::escape_html(href.trim())), "</a>".to_string())
+                } else {
+                    ("<a>".to_string(), "</a>".to_string())
+                }
+            }
+            b"text:list" => ("<ul>".to_string(), "</ul>".to_string()),
+            b"text:list-item" => ("<li>".to_string(), "</li>".to_string()),
+            b"text:line-break" => ("<br/>".to_string(), String::new()),
+            b"text:tab" => ("&#160;&#160;&#160;&#160;".to_string(), String::new()),
+            b"text:s" => {
+                let count = Self::get_attribute(e, b"text:c").and_then(|c| c.parse::<usize>().ok()).unwrap_or(1);
+                ("&#160;".repeat(count), String::new())
+            }
+            b"table:table" => ("<table>".to_string(), "</table>".to_string()),
+            b"table:table-row" => ("<tr>".to_string(), "</tr>".to_string()),
+            b"table:table-cell" => {
+                let mut attributes = String::new();
+                let get_span = |name: &[u8]| Self::get_attribute(e, name).and_then(|span| span.trim().parse::<usize>().ok());
+                if let Some(colspan) = get_span(b"table:number-columns-spanned") {
+                    attributes.push_str(&format!(" colspan=\"{}\"", colspan));
+                }
+
+                if let Some(rowspan) = get_span(b"table:number-rows-spanned") {
+                    attributes.push_str(&format!(" rowspan=\"{}\"", rowspan));
+                }
+
+                (format!("<td{}>", attributes), "</td>".to_string())
+            }
+            b"draw:page" => ("<div class=\"slide\">".to_string(), "</div>".to_string()),
+            b"draw:image" => {
+                let href = Self::get_attribute(e, b"xlink:href").unwrap_or_default();
+                match Self::extract_image(file_path, &href, archive, output_dir)? {
+                    Some(src) => (format!("<img src=\"{}\"/>", Utils::escape_html(&src)), String::new()),
+                    None => (String::new(), String::new()),
+                }
+            }
+            _ => (String::new(), String::new()),
+        };
+
+        Ok(tags)
+    }
+
+    /// 解压图片到输出目录
+    fn extract_image(file_path: &str, href: &str, archive: &mut OdfArchive, output_dir: &PathBuf) -> Result<Option<String>, Error> {
+        // 忽略外部链接
+        if href.is_empty() || href.contains("://") || href.starts_with("..") {
+            return Ok(None);
+        }
+
+        let mut file = match archive.by_name(href) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+
+        // 只解压到输出目录内, 忽略绝对路径和包含 `..` 的路径
+        let relative_path = match file.enclosed_name() {
+            Some(path) if path.components().all(|component| matches!(component, Component::Normal(_))) => path.to_path_buf(),
+            _ => return Ok(None),
+        };
+
+        let mut buffer: Vec<u8> = Vec::new();
+        file.read_to_end(&mut buffer).map_err(|err| Error::corrupt_file(file_path, err))?;
+
+        let output_path = output_dir.join(relative_path);
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent).map_err(|err| Error::io(err, &parent.to_string_lossy()))?;
+        }
+
+        fs::write(&output_path, buffer).map_err(|err| Error::io(err, &output_path.to_string_lossy()))?;
+        Ok(Some(href.to_string()))
+    }
+
+    /// 获取样式
+    fn get_style(e: &BytesStart, styles: &HashMap<String, TextStyle>) -> String {
+        let style = Self::get_attribute(e, b"text:style-name").and_then(|name| styles.get(&name).cloned());
+        let style = match style {
+            Some(style) => style,
+            None => return String::new(),
+        };
+
+        let mut css = String::new();
+        if style.bold {
+            css.push_str("font-weight: bold;");
+        }
+
+        if style.italic {
+            css.push_str("font-style: italic;");
+        }
+
+        if style.underline {
+            css.push_str("text-decoration: underline;");
+        }
+
+        if css.is_empty() {
+            return css;
+        }
+
+        format!(" style=\"{}\"", css)
+    }
+
+    /// 获取属性
+    fn get_attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+        let attribute = e.try_get_attribute(name).ok().flatten()?;
+        let value = attribute.unescape_value().ok()?;
+        Some(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// 写入只有 content.xml 的 odt
+    fn create_odt(body: &str) -> (PathBuf, PathBuf) {
+        let temp_dir = std::env::temp_dir().join(format!("odf-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        let file_path = temp_dir.join("test.odt");
+        let mut writer = zip::ZipWriter::new(File::create(&file_path).unwrap());
+        writer.start_file(CONTENT_FILE, zip::write::FileOptions::default()).unwrap();
+        let content = format!(
+            "<?xml version=\"1.0\"?><office:document-content><office:body><office:text>{}</office:text></office:body></office:document-content>",
+            body
+        );
+        writer.write_all(content.as_bytes()).unwrap();
+        writer.finish().unwrap();
+        (temp_dir, file_path)
+    }
+
+    fn to_html(body: &str) -> String {
+        let (temp_dir, file_path) = create_odt(body);
+        let html = OpenDocument::to_html(&file_path.to_string_lossy(), &temp_dir).unwrap();
+        fs::remove_dir_all(&temp_dir).unwrap();
+        html
+    }
+
+    #[test]
+    fn test_table_span() {
+        let html = to_html(
+            "<table:table><table:table-row>\
+            <table:table-cell table:number-columns-spanned=\"2\" table:number-rows-spanned='1\"&gt;&lt;script&gt;alert(1)&lt;/script&gt;'>\
+            <text:p>a</text:p></table:table-cell></table:table-row></table:table>",
+        );
+        assert!(html.contains("<td colspan=\"2\"><p>a</p></td>"));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn test_link() {
+        let html = to_html("<text:p><text:a xlink:href=\"https://example.com/?a=1&amp;b=2\">ok</text:a></text:p>");
+        assert!(html.contains("<a href=\"https://example.com/?a=1&amp;b=2\">ok</a>"));
+
+        let html = to_html("<text:p><text:a xlink:href=\" JavaScript:alert(1)\">bad</text:a></text:p>");
+        assert!(html.contains("<a>bad</a>"));
+        assert!(!html.to_lowercase().contains("javascript"));
+
+        let html = to_html("<text:p><text:a xlink:href=\"mailto:a@example.com\">mail</text:a></text:p>");
+        assert!(html.contains("href=\"mailto:a@example.com\""));
+    }
+
+    #[test]
+    fn test_skipped_elements() {
+        let html = to_html(
+            "<text:tracked-changes><text:changed-region><text:deletion>\
+            <text:p>deleted</text:p></text:deletion></text:changed-region></text:tracked-changes>\
+            <text:p>visible<office:annotation><dc:creator>author</dc:creator><text:p>comment</text:p></office:annotation></text:p>\
+            <presentation:notes><text:p>notes</text:p></presentation:notes>",
+        );
+        assert!(html.contains("<p>visible</p>"));
+        for text in ["deleted", "comment", "author", "notes"] {
+            assert!(!html.contains(text), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! pdf 附件、表单、批注
+
+use crate::config::{DocumentAnnotation, DocumentAttachment, DocumentField, HttpResponse};
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use log::{error, info};
+use mupdf::pdf::{PdfDocument, PdfObject};
+use std::path::Path;
+
+pub struct Pdf;
+
+// 名称树、表单最大递归深度, 防止循环引用
+const MAX_DEPTH: usize = 32;
+
+// 不作为批注返回的类型
+const IGNORE_ANNOTATIONS: [&str; 3] = ["Link", "Widget", "Popup"];
+
+impl Pdf {
+    /// 打开 pdf, 加密文档需要密码
+    pub fn open(file_path: &str, password: Option<&str>) -> Result<PdfDocument, Error> {
+        let mut document = PdfDocument::open(file_path).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let needs_password = document.needs_password().map_err(|err| Error::Error(err.to_string()))?;
+        if needs_password {
+            let authenticated = document
+                .authenticate(password.unwrap_or(""))
+                .map_err(|err| Error::Error(err.to_string()))?;
+            if !authenticated {
+                return Err(Error::PasswordRequired {
+                    path: file_path.to_string(),
+                    incorrect: password.is_some_and(|password| !password.is_empty()),
+                });
+            }
+        }
+
+        Ok(document)
+    }
+
+    /// 读取附件、表单、批注
+    pub fn read(file_path: &str, response: &mut HttpResponse) -> Result<(), Error> {
+        info!("read pdf attachments, fields and annotations ...");
+        let document = Self::open(file_path, response.options.password.as_deref())?;
+        let catalog = Self::get_catalog(file_path, &document)?;
+
+        // 单项读取失败时保留为空, 不影响其它项
+        match Self::read_attachments(&catalog) {
+            Ok(attachments) => response.document_props.attachments = attachments.into_iter().map(|(attachment, _)| attachment).collect(),
+            Err(err) => error!("read pdf attachments of `{}` error: {}", file_path, err),
+        }
+
+        match Self::read_fields(&catalog) {
+            Ok(fields) => response.document_props.fields = fields,
+            Err(err) => error!("read pdf fields of `{}` error: {}", file_path, err),
+        }
+
+        match Self::read_annotations(&document) {
+            Ok(annotations) => response.document_props.annotations = annotations,
+            Err(err) => error!("read pdf annotations of `{}` error: {}", file_path, err),
+        }
+
+        Ok(())
+    }
+
+    /// 导出附件到 `output_dir`
+    pub fn extract_attachment(file_path: &str, name: &str, output_dir: &str, password: Option<&str>) -> Result<HttpResponse, Error> {
+        let mut response = HttpResponse::default();
+        let output_path = Path::new(output_dir);
+        if !output_path.is_dir() {
+            response.set_error(Error::not_found("directory", output_dir));
+            return Ok(response);
+        }
+
+        let document = Self::open(file_path, password)?;
+        let catalog = Self::get_catalog(file_path, &document)?;
+        let attachments = Self::read_attachments(&catalog)?;
+        let attachment = attachments.iter().find(|(attachment, _)| attachment.name == name);
+        let (attachment, spec) = match attachment {
+            Some(attachment) => attachment,
+            None => {
+                response.set_error(Error::not_found("attachment", name));
+                return Ok(response);
+            }
+        };
+
+        let stream = Self::get_path(spec, &["EF", "F"])?;
+        let stream = match stream {
+            Some(stream) => stream,
+            None => {
+                response.set_error(Error::corrupt_file(file_path, format!("the attachment `{}` has no content", name)));
+                return Ok(response);
+            }
+        };
+
+        let data = stream.read_stream().map_err(|err| Error::Error(err.to_string()))?;
+
+        // 只保留文件名, 防止写到输出目录之外
+        let file_name = Path::new(&attachment.name)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or("attachment".to_string());
+        // 不覆盖已有文件, 重名时追加序号
+        let file_path = FileUtils::get_unique_file_path(output_path, &file_name);
+        let file_str = file_path.as_path().to_string_lossy().to_string();
+        FileUtils::write_to_file_atomic(&file_str, data)?;
+
+        info!("extract attachment `{}` to `{}` success !", name, &file_str);
+        response.code = 200;
+        response.body = file_str;
+        Ok(response)
+    }
+
+    fn get_catalog(file_path: &str, document: &PdfDocument) -> Result<PdfObject, Error> {
+        let trailer = document.trailer().map_err(|err| Error::Error(err.to_string()))?;
+        let catalog = Self::get_path(&trailer, &["Root"])?;
+        catalog.ok_or(Error::corrupt_file(file_path, "the pdf catalog is not found"))
+    }
+
+    /// 附件, 读取 `/Names/EmbeddedFiles` 名称树
+    fn read_attachments(catalog: &PdfObject) -> Result<Vec<(DocumentAttachment, PdfObject)>, Error> {
+        let mut attachments = Vec::new();
+        if let Some(tree) = Self::get_path(catalog, &["Names", "EmbeddedFiles"])? {
+            Self::read_name_tree(&tree, &mut attachments, 0)?;
+        }
+
+        Ok(attachments)
+    }
+
+    fn read_name_tree(node: &PdfObject, attachments: &mut Vec<(DocumentAttachment, PdfObject)>, depth: usize) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return Ok(());
+        }
+
+        if let Some(names) = Self::get_path(node, &["Names"])? {
+            let len = names.len().map_err(|err| Error::Error(err.to_string()))?;
+            let mut i = 0;
+            while i + 1 < len {
+                let key = Self::get_index(&names, i)?.map(|key| Self::as_text(&key)).unwrap_or_default();
+                if let Some(spec) = Self::get_index(&names, i + 1)? {
+                    let name = Self::get_text(&spec, "UF")?.or(Self::get_text(&spec, "F")?).unwrap_or(key);
+                    let description = Self::get_text(&spec, "Desc")?.unwrap_or_default();
+                    let size = match Self::get_path(&spec, &["EF", "F", "Params", "Size"])? {
+                        Some(size) => size.as_int().unwrap_or(0).max(0) as u64,
+                        None => 0,
+                    };
+
+                    attachments.push((
+                        DocumentAttachment {
+                            name,
+                            description,
+                            size: FileUtils::convert_size(size),
+                        },
+                        spec,
+                    ));
+                }
+
+                i += 2;
+            }
+        }
+
+        if let Some(kids) = Self::get_path(node, &["Kids"])? {
+            let len = kids.len().map_err(|err| Error::Error(err.to_string()))?;
+            for i in 0..len {
+                if let Some(kid) = Self::get_index(&kids, i)? {
+                    Self::read_name_tree(&kid, attachments, depth + 1)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 表单, 读取 `/AcroForm/Fields`
+    fn read_fields(catalog: &PdfObject) -> Result<Vec<DocumentField>, Error> {
+        let mut fields: Vec<DocumentField> = Vec::new();
+        if let Some(list) = Self::get_path(catalog, &["AcroForm", "Fields"])? {
+            Self::read_field_list(&list, "", &mut fields, 0)?;
+        }
+
+        Ok(fields)
+    }
+
+    fn read_field_list(list: &PdfObject, parent: &str, fields: &mut Vec<DocumentField>, depth: usize) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return Ok(());
+        }
+
+        let len = list.len().map_err(|err| Error::Error(err.to_string()))?;
+        for i in 0..len {
+            let field = match Self::get_index(list, i)? {
+                Some(field) => field,
+                None => continue,
+            };
+
+            // 全名为各级 `/T` 以 `.` 连接
+            let name = match Self::get_text(&field, "T")? {
+                Some(name) if parent.is_empty() => name,
+                Some(name) => format!("{}.{}", parent, name),
+                None => parent.to_string(),
+            };
+
+            if let Some(kids) = Self::get_path(&field, &["Kids"])? {
+                let has_named_kids = Self::get_index(&kids, 0)?.map(|kid| Self::get_text(&kid, "T")).transpose()?.flatten().is_some();
+                if has_named_kids {
+                    Self::read_field_list(&kids, &name, fields, depth + 1)?;
+                    continue;
+                }
+            }
+
+            let value = match Self::get_path(&field, &["V"])? {
+                Some(value) => Self::as_text(&value),
+                None => String::new(),
+            };
+
+            let kind = match Self::get_path(&field, &["FT"])? {
+                Some(kind) => Self::as_text(&kind),
+                None => String::new(),
+            };
+
+            fields.push(DocumentField { name, kind, value });
+        }
+
+        Ok(())
+    }
+
+    /// 批注, 读取每页的 `/Annots`
+    fn read_annotations(document: &PdfDocument) -> Result<Vec<DocumentAnnotation>, Error> {
+        let mut annotations: Vec<DocumentAnnotation> = Vec::new();
+        let count = document.page_count().map_err(|err| Error::Error(err.to_string()))?;
+
+        for page in 0..count {
+            let page_object = document.find_page(page).map_err(|err| Error::Error(err.to_string()))?;
+            let annots = match Self::get_path(&page_object, &["Annots"])? {
+                Some(annots) => annots,
+                None => continue,
+            };
+
+            let len = annots.len().map_err(|err| Error::Error(err.to_string()))?;
+            for i in 0..len {
+                let annot = match Self::get_index(&annots, i)? {
+                    Some(annot) => annot,
+                    None => continue,
+                };
+
+                let kind = Self::get_path(&annot, &["Subtype"])?.map(|kind| Self::as_text(&kind)).unwrap_or_default();
+                if IGNORE_ANNOTATIONS.contains(&kind.as_str()) {
+                    continue;
+                }
+
+                let mut rect: Vec<f32> = Vec::new();
+                if let Some(array) = Self::get_path(&annot, &["Rect"])? {
+                    for j in 0..4 {
+                        let value = Self::get_index(&array, j)?.map(|value| value.as_float().unwrap_or(0.0)).unwrap_or(0.0);
+                        rect.push(value);
+                    }
+                }
+
+                annotations.push(DocumentAnnotation {
+                    kind,
+                    author: Self::get_text(&annot, "T")?.unwrap_or_default(),
+                    content: Self::get_text(&annot, "Contents")?.unwrap_or_default(),
+                    modified: Self::get_text(&annot, "M")?.unwrap_or_default(),
+                    page: page as u32 + 1,
+                    rect,
+                });
+            }
+        }
+
+        Ok(annotations)
+    }
+
+    /// 按路径读取字典
+    fn get_path(object: &PdfObject, keys: &[&str]) -> Result<Option<PdfObject>, Error> {
+        let mut current = object.clone();
+        for key in keys.iter() {
+            let value = current.get_dict(*key).map_err(|err| Error::Error(err.to_string()))?;
+            match value {
+                Some(value) => current = value,
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(current))
+    }
+
+    fn get_index(array: &PdfObject, index: usize) -> Result<Option<PdfObject>, Error> {
+        array.get_array(index as i32).map_err(|err| Error::Error(err.to_string()))
+    }
+
+    fn get_text(object: &PdfObject, key: &str) -> Result<Option<String>, Error> {
+        let value = Self::get_path(object, &[key])?;
+        Ok(value.map(|value| Self::as_text(&value)).filter(|value| !value.is_empty()))
+    }
+
+    /// 字符串、名称、数字转换成文本
+    fn as_text(object: &PdfObject) -> String {
+        if object.is_string().unwrap_or(false) {
+            return object.as_string().map(|value| value.to_string()).unwrap_or_default();
+        }
+
+        if object.is_name().unwrap_or(false) {
+            return object.as_name().map(|value| String::from_utf8_lossy(value).to_string()).unwrap_or_default();
+        }
+
+        if object.is_int().unwrap_or(false) {
+            return object.as_int().map(|value| value.to_string()).unwrap_or_default();
+        }
+
+        if object.is_number().unwrap_or(false) {
+            return object.as_float().map(|value| value.to_string()).unwrap_or_default();
+        }
+
+        if object.is_bool().unwrap_or(false) {
+            return object.as_bool().map(|value| value.to_string()).unwrap_or_default();
+        }
+
+        String::new()
+    }
+}
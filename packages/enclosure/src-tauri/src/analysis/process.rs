@@ -1,14 +1,19 @@
 //! 处理文件
 
 use crate::analysis::archive::Archive;
+use crate::analysis::delimited::Delimited;
 use crate::analysis::document::Document;
 use crate::analysis::excel::Excel;
+use crate::analysis::query::Query;
 use crate::cache::Cache;
-use crate::config::{FileProps, EXCEL_SUFFIXES};
+use crate::cache::manifest::Manifest;
+use crate::config::{FileProps, RequestOptions, DELIMITED_SUFFIXES, EXCEL_SUFFIXES};
 use crate::config::{HttpResponse, SuffixProps, ARCHIVE_SUFFIXES, DOCUMENT_SUFFIXES, IMAGE_SUFFIXES, PREVIEW_FILE};
 use crate::error::Error;
+use crate::job::Job;
 use crate::prepare::{Prepare, Treat};
 use crate::utils::file::FileUtils;
+use crate::utils::sniff::{Sniff, Sniffed};
 use crate::utils::Utils;
 use chrono::TimeZone;
 use log::{error, info};
@@ -24,24 +29,37 @@ use tauri::AppHandle;
 
 pub struct Process;
 
+/// 请求参数
+const PARAM_KEYS: [&str; 9] = [
+    "fileType",
+    "filePath",
+    "password",
+    "pageWidth",
+    "pageHeight",
+    "fontSize",
+    "showFormulas",
+    "hasHeader",
+    "jobId",
+];
+
 impl Treat<HttpResponse> for Process {
     /// 从 `headers` 头中获取文件名, 中文名是 encode 的, 需要 decode
-    fn get_filename(headers: &tauri::http::HeaderMap) -> Result<String, String> {
+    fn get_filename(headers: &tauri::http::HeaderMap) -> Result<String, Error> {
         info!("request headers: {:#?}", headers);
         let filename = headers.get("fileName");
         info!("filename in header: {:#?}", filename);
         if filename.is_none() {
-            return Err(Error::Error("`fileName` not in headers !".to_string()).to_string());
+            return Err(Error::Error("`fileName` not in headers !".to_string()));
         }
 
         let mut file_name = String::new();
         if let Some(filename) = filename {
-            let name = filename.to_str().map_err(|err| Error::Error(err.to_string()).to_string())?;
+            let name = filename.to_str().map_err(|err| Error::Error(err.to_string()))?;
             file_name = name.to_string();
         }
 
         // decode filename
-        let file_name = urlencoding::decode(&file_name).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let file_name = urlencoding::decode(&file_name).map_err(|err| Error::Error(err.to_string()))?;
         let file_name = file_name.to_string();
         info!("filename: {:#?}", &file_name);
         return Ok(file_name);
@@ -67,7 +85,7 @@ impl Treat<HttpResponse> for Process {
         response
     }
 
-    fn prepare(_: &AppHandle, body: &InvokeBody, response: &HttpResponse) -> Result<HttpResponse, String> {
+    fn prepare(app: &AppHandle, body: &InvokeBody, response: &HttpResponse) -> Result<HttpResponse, Error> {
         // blob
         if let InvokeBody::Raw(data) = body {
             let res = Self::prepare_blob(data, response.clone())?;
@@ -84,13 +102,20 @@ impl Treat<HttpResponse> for Process {
 
             return if let Some(param_path) = params.get("filePath") {
                 if param_path.is_empty() {
-                    return Err(Error::Error("`fileName` not in headers !".to_string()).to_string());
+                    return Err(Error::Error("`fileName` not in headers !".to_string()));
                 }
 
-                let suffix = &response.file_props.suffix;
-                let res = Self::prepare_json(param_path, response.clone())?;
+                let mut response = response.clone();
+                let job = Job::create(params.get("jobId").map(|id| id.as_str()), "process", param_path);
+                response.options = Self::get_options(&params);
+                response.options.app = Some(app.clone());
+                response.options.job = Some(job.clone());
+                let mut res = Self::prepare_json(param_path, response)?;
+                // 缓存中的 id 可能是之前的任务
+                res.job_id = job.id.clone();
                 // excel 采用异步并行任务
-                if file_type.is_empty() && !EXCEL_SUFFIXES.contains(&suffix.as_str()) {
+                let suffix = &res.file_props.suffix;
+                if file_type.is_empty() && !EXCEL_SUFFIXES.contains(&suffix.as_str()) && !DELIMITED_SUFFIXES.contains(&suffix.as_str()) {
                     Cache::save_history(&res.file_props)?;
                     // 更新菜单 some errors ?
                     // Menu::update_history_submenus(app);
@@ -98,14 +123,21 @@ impl Treat<HttpResponse> for Process {
 
                 Ok(res)
             } else {
-                Err(Error::Error("`fileName` not in headers !".to_string()).to_string())
+                Err(Error::Error("`fileName` not in headers !".to_string()))
             };
         }
 
         return Ok(response.clone());
     }
 
-    fn prepare_blob(data: &Vec<u8>, response: HttpResponse) -> Result<HttpResponse, String> {
+    fn prepare_blob(data: &Vec<u8>, mut response: HttpResponse) -> Result<HttpResponse, Error> {
+        let sniffed = Sniff::detect(data, &response.file_props.suffix);
+        if sniffed.executable {
+            response.set_error(Error::unsupported_format("executable", &sniffed.mime));
+            return Ok(response);
+        }
+
+        Self::set_sniffed(&mut response, sniffed);
         let suffix = response.file_props.suffix.clone();
         let suffix = suffix.as_str();
         // image suffix
@@ -114,7 +146,7 @@ impl Treat<HttpResponse> for Process {
             let svg = IMAGE_SUFFIXES.get(10).unwrap();
             let content: String;
             if svg == &suffix {
-                content = String::from_utf8(data.clone()).map_err(|err| Error::Error(err.to_string()).to_string())?;
+                content = String::from_utf8(data.clone()).map_err(|err| Error::corrupt_file(&response.file_props.path, err))?;
             } else {
                 content = Utils::generate_image(data.clone());
             }
@@ -136,20 +168,12 @@ impl Treat<HttpResponse> for Process {
         return Ok(res);
     }
 
-    fn prepare_json(file_path: &str, response: HttpResponse) -> Result<HttpResponse, String> {
+    fn prepare_json(file_path: &str, response: HttpResponse) -> Result<HttpResponse, Error> {
         let mut res = response.clone();
-        let suffix = res.file_props.suffix.clone();
-        let suffix = &suffix.as_str();
-        info!("file path: {}, suffix: {}", file_path, suffix);
+        info!("file path: {}, suffix: {}", file_path, &res.file_props.suffix);
 
-        // 判断文件是否是可执行文件
         info!("prepare to get file `{}` props", file_path);
         let mut file_props = Self::prepare_file_props(file_path)?;
-        if file_props.executable {
-            res.error = format!("the `{}` file is an executable file !", file_path);
-            return Ok(res);
-        }
-
         file_props.name = res.file_props.name.clone();
         file_props.suffix = res.file_props.suffix.clone();
         file_props.prefix = res.file_props.prefix.clone();
@@ -161,19 +185,30 @@ impl Treat<HttpResponse> for Process {
             return Self::prepare_directory(&path, res);
         }
 
-        // cache
-        if DOCUMENT_SUFFIXES.contains(suffix) || ARCHIVE_SUFFIXES.contains(suffix) {
+        // 根据文件头识别类型, 判断文件是否是可执行文件
+        let sniffed = Sniff::detect_file(file_path, &res.file_props.suffix)?;
+        if res.file_props.executable || sniffed.executable {
+            res.set_error(Error::unsupported_format("executable", &sniffed.mime));
+            return Ok(res);
+        }
+
+        Self::set_sniffed(&mut res, sniffed);
+        let suffix = res.file_props.suffix.clone();
+        let suffix = &suffix.as_str();
+
+        // cache, 设置了重排参数时需要重新渲染
+        if (DOCUMENT_SUFFIXES.contains(suffix) && !res.options.has_layout()) || ARCHIVE_SUFFIXES.contains(suffix) {
             return Self::compare_file(file_path, res);
         }
 
         Self::prepare_file(file_path, res)
     }
 
-    fn prepare_directory(path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, String> {
+    fn prepare_directory(path: &PathBuf, response: HttpResponse) -> Result<HttpResponse, Error> {
         let file_path = path.as_path().to_string_lossy().to_string();
         if !path.exists() {
             error!("file path `{}` not exists, read directory error !", &file_path);
-            return Err(Error::Error(format!("file path `{}` not exists, read directory error !", &file_path)).to_string());
+            return Err(Error::not_found("directory", &file_path));
         }
 
         // 按目录归纳文件
@@ -188,7 +223,7 @@ impl Treat<HttpResponse> for Process {
         Ok(res)
     }
 
-    fn prepare_file(file_path: &str, response: HttpResponse) -> Result<HttpResponse, String> {
+    fn prepare_file(file_path: &str, response: HttpResponse) -> Result<HttpResponse, Error> {
         let suffix = &response.file_props.suffix;
         // archive
         if ARCHIVE_SUFFIXES.contains(&suffix.as_str()) {
@@ -198,15 +233,22 @@ impl Treat<HttpResponse> for Process {
 
         // excel
         if EXCEL_SUFFIXES.contains(&suffix.as_str()) {
+            Query::clear_cache(file_path);
             return Excel::with_response(response);
         }
 
+        // csv、tsv、psv
+        if DELIMITED_SUFFIXES.contains(&suffix.as_str()) {
+            Query::clear_cache(file_path);
+            return Delimited::with_response(response);
+        }
+
         let content = FileUtils::read_file(file_path)?;
         Self::prepare_blob(&content, response)
     }
 
     /// 比较临时文件是不是和文件一致
-    fn compare_file(file_path: &str, response: HttpResponse) -> Result<HttpResponse, String> {
+    fn compare_file(file_path: &str, response: HttpResponse) -> Result<HttpResponse, Error> {
         info!("prepare to get cache ...");
         let name = &response.file_props.name;
         let temp_dir = FileUtils::create_temp_dir(&response.file_props.prefix, false)?;
@@ -234,29 +276,22 @@ impl Treat<HttpResponse> for Process {
         info!("`{}` hash is same ...", &response.file_props.name);
 
         // 相等则直接读取原来文件
-        let json_file_path = temp_dir.clone().join(PREVIEW_FILE);
-        let json_file_str = json_file_path.as_path().to_string_lossy().to_string();
-        if !json_file_path.exists() {
-            info!("json path not exists, no cache found ...");
-            return Self::prepare_file(file_path, response);
-        }
-
-        info!("read `{}` in path `{}`", PREVIEW_FILE, &json_file_str);
-        let content = FileUtils::read_file_string(&json_file_str)?;
-        if content.is_empty() {
-            info!("`{}` content is empty !", PREVIEW_FILE);
-            return Self::prepare_file(file_path, response);
-        }
+        info!("read `{}` in path `{:#?}`", PREVIEW_FILE, Manifest::get_path(&temp_dir));
+        let response = match Manifest::read::<HttpResponse>(&temp_dir)? {
+            Some(response) => response,
+            None => {
+                info!("`{}` not exists or empty, no cache found ...", PREVIEW_FILE);
+                return Self::prepare_file(file_path, response);
+            }
+        };
 
-        info!("convert `{}` to response !", PREVIEW_FILE);
-        let response = serde_json::from_str(&content).map_err(|err| Error::Error(err.to_string()).to_string())?;
         info!("get file `{}` by cache success !", &file_path);
         Ok(response)
     }
 
     /// 获取文件属性
-    fn prepare_file_props(file_path: &str) -> Result<FileProps, String> {
-        let metadata: Metadata = fs::metadata(file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+    fn prepare_file_props(file_path: &str) -> Result<FileProps, Error> {
+        let metadata: Metadata = fs::metadata(file_path).map_err(|err| Error::io(err, file_path))?;
         let mut file_props = FileProps::default();
         file_props.path = file_path.to_string();
 
@@ -321,30 +356,43 @@ impl Treat<HttpResponse> for Process {
 }
 
 impl Process {
+    /// 使用识别出的后缀, 后缀与内容不一致时按内容处理
+    fn set_sniffed(response: &mut HttpResponse, sniffed: Sniffed) {
+        if sniffed.suffix != response.file_props.suffix {
+            info!(
+                "`{}` is detected as `{}` ({}), not `{}`",
+                &response.file_props.name, &sniffed.suffix, &sniffed.mime, &response.file_props.suffix
+            );
+        }
+
+        response.file_props.suffix = sniffed.suffix;
+        response.file_props.mime = sniffed.mime;
+    }
+
     /// 处理
-    pub async fn handle<'a>(app: &AppHandle, request: Request<'a>) -> Result<HttpResponse, String> {
+    pub async fn handle<'a>(app: &AppHandle, request: Request<'a>) -> Result<HttpResponse, Error> {
         let file_name = Self::get_filename(request.headers())?; // get filename in headers
         let response = Self::get_response(&file_name);
         // Self::prepare(app, request.body(), response)
         Self::task(app, request.body(), response).await
     }
 
-    /// 使用异步任务, 保证不阻塞主线程
-    async fn task(app: &AppHandle, body: &InvokeBody, response: HttpResponse) -> Result<HttpResponse, String> {
+    /// 在阻塞线程池中执行, 保证不阻塞主线程, 也不占用异步执行器的线程
+    async fn task(app: &AppHandle, body: &InvokeBody, response: HttpResponse) -> Result<HttpResponse, Error> {
         let app_cloned = Arc::new(app.clone());
         let body_cloned = Arc::new(body.clone());
         let response_cloned = Arc::new(response.clone());
-        let result = async_std::task::spawn(async move {
+        let result = async_std::task::spawn_blocking(move || {
             info!("async task");
             Self::prepare(&*app_cloned, &*body_cloned, &*response_cloned)
         });
 
-        let res = result.await.map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let res = result.await.map_err(|err| Error::Error(err.to_string()))?;
         Ok(res)
     }
 
     /// 根据路径执行
-    pub fn exec_by_file_path(filename: &str, file_path: &str) -> Result<HttpResponse, String> {
+    pub fn exec_by_file_path(filename: &str, file_path: &str) -> Result<HttpResponse, Error> {
         let response = Self::get_response(&filename);
         Self::prepare_json(file_path, response)
     }
@@ -352,23 +400,40 @@ impl Process {
     fn get_params_by_header(data: &serde_json::Value) -> HashMap<String, String> {
         let map = serde_json::Map::new();
         let obj: &serde_json::Map<String, serde_json::Value> = data.as_object().unwrap_or(&map);
-        let file_type = obj.get("fileType");
-        let file_path = obj.get("filePath");
 
         let mut params: HashMap<String, String> = HashMap::new();
-        if let Some(file_type) = file_type {
-            params.insert(String::from("fileType"), file_type.as_str().unwrap().to_string());
-        }
+        for key in PARAM_KEYS.iter() {
+            let value = match obj.get(*key) {
+                Some(serde_json::Value::String(value)) => value.to_string(),
+                Some(serde_json::Value::Null) | None => continue,
+                Some(value) => value.to_string(),
+            };
 
-        if let Some(file_path) = file_path {
-            params.insert(String::from("filePath"), file_path.as_str().unwrap().to_string());
+            params.insert(key.to_string(), value);
         }
 
         params
     }
 
+    /// 获取请求参数
+    fn get_options(params: &HashMap<String, String>) -> RequestOptions {
+        let get_number = |key: &str| params.get(key).and_then(|value| value.parse::<f32>().ok()).filter(|value| *value > 0.0);
+        let get_bool = |key: &str| params.get(key).map(|value| value == "true" || value == "1");
+
+        RequestOptions {
+            password: params.get("password").cloned(),
+            page_width: get_number("pageWidth"),
+            page_height: get_number("pageHeight"),
+            font_size: get_number("fontSize"),
+            app: None,
+            show_formulas: get_bool("showFormulas").unwrap_or(false),
+            has_header: get_bool("hasHeader"),
+            job: None,
+        }
+    }
+
     /// 读取目录
-    pub fn read_directory(path: &PathBuf, prefix: &str) -> Result<(Vec<FileProps>, u64), String> {
+    pub fn read_directory(path: &PathBuf, prefix: &str) -> Result<(Vec<FileProps>, u64), Error> {
         // 读取目录下的所有文件
         let mut files: Vec<FileProps> = Vec::new();
         let mut size: u64 = 0;
@@ -398,8 +463,8 @@ impl Process {
     }
 
     /// 读取文件夹下的所有文件
-    pub fn read_files(path: &Path, unzip_path_str: &str, size: &mut u64, files: &mut Vec<FileProps>) -> Result<(), String> {
-        let entries = fs::read_dir(path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+    pub fn read_files(path: &Path, unzip_path_str: &str, size: &mut u64, files: &mut Vec<FileProps>) -> Result<(), Error> {
+        let entries = fs::read_dir(path).map_err(|err| Error::io(err, &path.to_string_lossy()))?;
         for entry in entries {
             let entry = entry.unwrap();
             let path = entry.path();
@@ -419,6 +484,7 @@ impl Process {
                 key: path_str.clone(),
                 name: suffix.clone(),
                 suffix: suffix.clone(),
+                mime: String::new(),
                 prefix: "".to_string(),
                 path: relative_path.clone(),
                 full_path: path_str.clone(),
@@ -487,27 +553,23 @@ impl Process {
         root
     }
 
-    pub fn copy_file(temp_dir: &PathBuf, response: &HttpResponse) -> Result<(), String> {
+    pub fn copy_file(temp_dir: &PathBuf, response: &HttpResponse) -> Result<(), Error> {
         info!("copy origin file to temp dir ...");
         let path = &response.file_props.path;
         let temp_dir_str = temp_dir.as_path().to_string_lossy().to_string();
         let mut copy_options = fs_extra::dir::CopyOptions::new();
         copy_options.overwrite = true;
-        fs_extra::copy_items(&[path], &temp_dir_str, &copy_options).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        fs_extra::copy_items(&[path], &temp_dir_str, &copy_options).map_err(|err| Error::Error(err.to_string()))?;
         Ok(())
     }
 
-    pub fn write_to_file(temp_dir: &PathBuf, response: &HttpResponse) -> Result<(), String> {
+    pub fn write_to_file(temp_dir: &PathBuf, response: &HttpResponse) -> Result<(), Error> {
         info!("write response into json ...");
-        let json_file_path = temp_dir.with_extension(PREVIEW_FILE);
-        let file_path = json_file_path.as_path().to_string_lossy().to_string();
-        let content = serde_json::to_string_pretty(&response).unwrap(); // 序列化为漂亮格式的 JSON 字符串
-        FileUtils::write_to_file_when_clear(&file_path, &content)?;
-        Ok(())
+        Manifest::write(temp_dir, response)
     }
 
     /// 拷贝文件到临时目录, 并把结果写入到文件
-    pub fn copy_write_to_file(temp_dir: &PathBuf, response: &HttpResponse) -> Result<(), String> {
+    pub fn copy_write_to_file(temp_dir: &PathBuf, response: &HttpResponse) -> Result<(), Error> {
         Self::copy_file(temp_dir, response)?;
         Self::write_to_file(temp_dir, response)?;
         Ok(())
@@ -0,0 +1,321 @@
+//! sheet 列统计, 可按块统计后合并
+
+use crate::analysis::excel::{CELL_DATE, CELL_DURATION, CELL_EMPTY, CELL_NUMBER, CELL_STRING};
+use crate::analysis::xlsx::Xlsx;
+use crate::config::{ExcelCell, ExcelColumnProfile, ExcelHistogramBin, ExcelRow, ExcelValueCount};
+use std::collections::{HashMap, HashSet};
+
+/// sheet 统计
+#[derive(Default, Debug, Clone)]
+pub struct Profiler {
+    header: Vec<String>,
+    // 没有表头时, 是否检测第一行为表头
+    detect_header: bool,
+    // 待检测的第一行, 不计入统计
+    first_row: Option<ExcelRow>,
+    rows_count: usize,
+    columns: Vec<ColumnStats>,
+}
+
+/// 单列统计
+#[derive(Default, Debug, Clone)]
+struct ColumnStats {
+    count: usize,
+    kinds: HashMap<String, usize>,
+    values: HashMap<String, usize>,
+    approximate: bool,
+    // (数值, 显示值)
+    min_number: Option<(f64, String)>,
+    max_number: Option<(f64, String)>,
+    min_text: Option<String>,
+    max_text: Option<String>,
+    sum: f64,
+    numbers_count: usize,
+    numbers: Vec<f64>,
+}
+
+// 每列最多记录的不同值
+const MAX_DISTINCT_VALUES: usize = 100000;
+
+// 每列最多记录的数值, 用于直方图
+const MAX_HISTOGRAM_VALUES: usize = 1000000;
+
+const TOP_VALUES_COUNT: usize = 10;
+
+const HISTOGRAM_BINS: usize = 10;
+
+const KIND_MIXED: &str = "mixed";
+
+impl Profiler {
+    /// `header` 为 csv 等已知的表头, `detect_header` 为 true 时根据第一行判断
+    pub fn new(header: Vec<String>, detect_header: bool) -> Self {
+        Self {
+            header,
+            detect_header,
+            ..Self::default()
+        }
+    }
+
+    pub fn add_rows(&mut self, rows: &[ExcelRow]) {
+        for row in rows {
+            if self.detect_header && row.index == 0 {
+                self.first_row = Some(row.clone());
+                continue;
+            }
+
+            self.add_row(row);
+        }
+    }
+
+    /// 合并其它块的统计
+    pub fn merge(&mut self, other: Profiler) {
+        self.rows_count += other.rows_count;
+        if other.first_row.is_some() {
+            self.first_row = other.first_row;
+        }
+
+        if self.columns.len() < other.columns.len() {
+            self.columns.resize_with(other.columns.len(), ColumnStats::default);
+        }
+
+        for (stats, other) in self.columns.iter_mut().zip(other.columns) {
+            stats.merge(other);
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<ExcelColumnProfile> {
+        let mut header = self.header.clone();
+        if let Some(first_row) = self.first_row.take() {
+            if self.is_header(&first_row) {
+                header = first_row.cells.iter().map(|cell| cell.value.trim().to_string()).collect();
+            } else {
+                self.add_row(&first_row);
+            }
+        }
+
+        let rows_count = self.rows_count;
+        let columns_count = self.columns.len().max(header.len());
+        self.columns.resize_with(columns_count, ColumnStats::default);
+
+        self.columns
+            .into_iter()
+            .enumerate()
+            .map(|(index, stats)| {
+                let name = header
+                    .get(index)
+                    .filter(|name| !name.is_empty())
+                    .cloned()
+                    .unwrap_or(Xlsx::get_column_name(index));
+                stats.finish(index, name, rows_count)
+            })
+            .collect()
+    }
+
+    fn add_row(&mut self, row: &ExcelRow) {
+        self.rows_count += 1;
+        if self.columns.len() < row.cells.len() {
+            self.columns.resize_with(row.cells.len(), ColumnStats::default);
+        }
+
+        for (stats, cell) in self.columns.iter_mut().zip(row.cells.iter()) {
+            stats.add(cell);
+        }
+    }
+
+    /// 第一行全部为不重复的文本, 且有列的其它值不全为文本时作为表头
+    fn is_header(&self, first_row: &ExcelRow) -> bool {
+        if self.rows_count == 0 || first_row.cells.iter().all(|cell| cell.value.trim().is_empty()) {
+            return false;
+        }
+
+        let mut names: HashSet<String> = HashSet::new();
+        let all_text = first_row
+            .cells
+            .iter()
+            .filter(|cell| !cell.value.trim().is_empty())
+            .all(|cell| cell.kind == CELL_STRING && names.insert(cell.value.trim().to_lowercase()));
+
+        all_text && self.columns.iter().any(|stats| stats.kinds.keys().any(|kind| kind != CELL_STRING))
+    }
+}
+
+impl ColumnStats {
+    fn add(&mut self, cell: &ExcelCell) {
+        if cell.kind == CELL_EMPTY || cell.value.trim().is_empty() {
+            return;
+        }
+
+        self.count += 1;
+        *self.kinds.entry(cell.kind.clone()).or_insert(0) += 1;
+
+        if let Some(count) = self.values.get_mut(&cell.value) {
+            *count += 1;
+        } else if self.values.len() < MAX_DISTINCT_VALUES {
+            self.values.insert(cell.value.clone(), 1);
+        } else {
+            self.approximate = true;
+        }
+
+        let is_number = [CELL_NUMBER, CELL_DATE, CELL_DURATION].contains(&cell.kind.as_str());
+        match cell.raw.parse::<f64>() {
+            Ok(number) if is_number && number.is_finite() => self.add_number(number, &cell.value),
+            _ => self.add_text(&cell.value),
+        }
+    }
+
+    fn add_number(&mut self, number: f64, display: &str) {
+        if self.min_number.as_ref().map(|(min, _)| number < *min).unwrap_or(true) {
+            self.min_number = Some((number, display.to_string()));
+        }
+
+        if self.max_number.as_ref().map(|(max, _)| number > *max).unwrap_or(true) {
+            self.max_number = Some((number, display.to_string()));
+        }
+
+        self.sum += number;
+        self.numbers_count += 1;
+        if self.numbers.len() < MAX_HISTOGRAM_VALUES {
+            self.numbers.push(number);
+        }
+    }
+
+    fn add_text(&mut self, value: &str) {
+        if self.min_text.as_deref().map(|min| value < min).unwrap_or(true) {
+            self.min_text = Some(value.to_string());
+        }
+
+        if self.max_text.as_deref().map(|max| value > max).unwrap_or(true) {
+            self.max_text = Some(value.to_string());
+        }
+    }
+
+    fn merge(&mut self, other: ColumnStats) {
+        self.count += other.count;
+        for (kind, count) in other.kinds {
+            *self.kinds.entry(kind).or_insert(0) += count;
+        }
+
+        self.approximate |= other.approximate;
+        for (value, count) in other.values {
+            if let Some(existing) = self.values.get_mut(&value) {
+                *existing += count;
+            } else if self.values.len() < MAX_DISTINCT_VALUES {
+                self.values.insert(value, count);
+            } else {
+                self.approximate = true;
+            }
+        }
+
+        if let Some((number, display)) = other.min_number {
+            if self.min_number.as_ref().map(|(min, _)| number < *min).unwrap_or(true) {
+                self.min_number = Some((number, display));
+            }
+        }
+
+        if let Some((number, display)) = other.max_number {
+            if self.max_number.as_ref().map(|(max, _)| number > *max).unwrap_or(true) {
+                self.max_number = Some((number, display));
+            }
+        }
+
+        if let Some(text) = other.min_text {
+            self.add_text(&text);
+        }
+
+        if let Some(text) = other.max_text {
+            self.add_text(&text);
+        }
+
+        self.sum += other.sum;
+        self.numbers_count += other.numbers_count;
+        let remaining = MAX_HISTOGRAM_VALUES.saturating_sub(self.numbers.len());
+        self.numbers.extend(other.numbers.into_iter().take(remaining));
+    }
+
+    fn finish(self, index: usize, name: String, rows_count: usize) -> ExcelColumnProfile {
+        let kind = match self.kinds.len() {
+            0 => CELL_EMPTY.to_string(),
+            1 => self.kinds.keys().next().cloned().unwrap_or_default(),
+            _ => KIND_MIXED.to_string(),
+        };
+
+        // 数值为主时使用数值的最值
+        let numeric = self.numbers_count * 2 > self.count;
+        let (min, max) = if numeric {
+            (
+                self.min_number.map(|(_, display)| display).unwrap_or_default(),
+                self.max_number.map(|(_, display)| display).unwrap_or_default(),
+            )
+        } else {
+            (self.min_text.unwrap_or_default(), self.max_text.unwrap_or_default())
+        };
+
+        let mean = if self.numbers_count > 0 && numeric {
+            Some(self.sum / self.numbers_count as f64)
+        } else {
+            None
+        };
+
+        let mut top_values: Vec<ExcelValueCount> = self
+            .values
+            .iter()
+            .map(|(value, count)| ExcelValueCount {
+                value: value.clone(),
+                count: *count,
+            })
+            .collect();
+        top_values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        top_values.truncate(TOP_VALUES_COUNT);
+
+        let histogram = if numeric { Self::get_histogram(&self.numbers) } else { Vec::new() };
+
+        ExcelColumnProfile {
+            index,
+            name,
+            kind,
+            count: self.count,
+            empty_count: rows_count.saturating_sub(self.count),
+            distinct_count: self.values.len(),
+            approximate: self.approximate,
+            min,
+            max,
+            mean,
+            top_values,
+            histogram,
+        }
+    }
+
+    /// 等宽直方图
+    fn get_histogram(numbers: &[f64]) -> Vec<ExcelHistogramBin> {
+        let min = numbers.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = numbers.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        if numbers.is_empty() || !min.is_finite() || !max.is_finite() {
+            return Vec::new();
+        }
+
+        // 所有值相同时只有一个区间
+        if min == max {
+            return vec![ExcelHistogramBin {
+                start: min,
+                end: max,
+                count: numbers.len(),
+            }];
+        }
+
+        let width = (max - min) / HISTOGRAM_BINS as f64;
+        let mut histogram: Vec<ExcelHistogramBin> = (0..HISTOGRAM_BINS)
+            .map(|i| ExcelHistogramBin {
+                start: min + width * i as f64,
+                end: min + width * (i + 1) as f64,
+                count: 0,
+            })
+            .collect();
+
+        for number in numbers {
+            let bin = (((number - min) / width) as usize).min(HISTOGRAM_BINS - 1);
+            histogram[bin].count += 1;
+        }
+
+        histogram
+    }
+}
@@ -0,0 +1,237 @@
+//! sheet 排序、过滤
+
+use crate::analysis::excel::Excel;
+use crate::config::{ExcelCell, ExcelRow, HttpResponse, SheetFilter, SheetQuery, SheetQueryResult, SheetSort};
+use crate::error::Error;
+use log::info;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+pub struct Query;
+
+// 单次最多返回的行数
+const MAX_QUERY_ROWS: usize = 10000;
+
+// 最多缓存的查询数
+const MAX_CACHED_QUERIES: usize = 8;
+
+// 过滤、排序后的行索引, 翻页时复用
+static ORDERS: OnceLock<Mutex<Orders>> = OnceLock::new();
+
+type Orders = VecDeque<(OrderKey, Arc<Vec<usize>>)>;
+
+/// 文件、修改时间、sheet 和条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OrderKey {
+    file_path: String,
+    modified: String,
+    sheet_index: u32,
+    condition: String,
+}
+
+const FILTER_EQUALS: &str = "equals";
+const FILTER_CONTAINS: &str = "contains";
+const FILTER_RANGE: &str = "range";
+const FILTER_EMPTY: &str = "empty";
+const FILTER_NOT_EMPTY: &str = "not_empty";
+
+impl Query {
+    /// 按条件查询 sheet, 返回过滤、排序后的行窗口及总行数
+    pub fn query_sheet(file_path: &str, sheet_index: u32, query: &SheetQuery) -> Result<HttpResponse, Error> {
+        info!("query sheet {}, query: {:?} ...", sheet_index, query);
+        let start_time = Instant::now();
+        let mut response = HttpResponse::default();
+
+        let operators = [FILTER_EQUALS, FILTER_CONTAINS, FILTER_RANGE, FILTER_EMPTY, FILTER_NOT_EMPTY];
+        if let Some(filter) = query.filters.iter().find(|filter| !operators.contains(&filter.operator.as_str())) {
+            response.set_error(Error::unsupported_format("filter", &filter.operator));
+            return Ok(response);
+        }
+
+        // 条件中的文本不区分大小写, 提前转小写
+        let filters: Vec<SheetFilter> = query
+            .filters
+            .iter()
+            .map(|filter| SheetFilter {
+                value: filter.value.to_lowercase(),
+                ..filter.clone()
+            })
+            .collect();
+
+        let order = Self::get_order(file_path, sheet_index, query, &filters)?;
+        let total = order.len();
+        let count = if query.count == 0 {
+            MAX_QUERY_ROWS
+        } else {
+            query.count.min(MAX_QUERY_ROWS)
+        };
+        let indexes: Vec<usize> = order.iter().skip(query.start).take(count).copied().collect();
+        let rows = Excel::read_rows_by_indexes(file_path, sheet_index, &indexes)?;
+
+        info!("query sheet {} total: {}, cost: {:?}", sheet_index, total, start_time.elapsed());
+        let result = SheetQueryResult { total, rows };
+        response.code = 200;
+        response.body = serde_json::to_string(&result).unwrap_or("".to_string());
+        Ok(response)
+    }
+
+    /// 清除文件的查询缓存, 重新解析文件时调用
+    pub fn clear_cache(file_path: &str) {
+        let mut orders = Self::get_orders().lock().unwrap_or_else(|err| err.into_inner());
+        orders.retain(|(key, _)| key.file_path != file_path);
+    }
+
+    fn get_orders() -> &'static Mutex<Orders> {
+        ORDERS.get_or_init(|| Mutex::new(VecDeque::new()))
+    }
+
+    /// 过滤、排序后的行索引, 优先使用缓存, 只保留排序列的单元格
+    fn get_order(file_path: &str, sheet_index: u32, query: &SheetQuery, filters: &[SheetFilter]) -> Result<Arc<Vec<usize>>, Error> {
+        let modified = fs::metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .map(|modified| format!("{:?}", modified))
+            .unwrap_or_default();
+        let condition = serde_json::to_string(&(&query.sorts, &query.filters)).map_err(|err| Error::Error(err.to_string()))?;
+        let key = OrderKey {
+            file_path: file_path.to_string(),
+            modified,
+            sheet_index,
+            condition,
+        };
+
+        {
+            let orders = Self::get_orders().lock().unwrap_or_else(|err| err.into_inner());
+            if let Some((_, order)) = orders.iter().find(|(item, _)| *item == key) {
+                info!("query sheet {} hit cache", sheet_index);
+                return Ok(order.clone());
+            }
+        }
+
+        let mut entries: Vec<(usize, Vec<Option<ExcelCell>>)> = Vec::new();
+        Excel::scan_sheet(file_path, sheet_index, |row| {
+            if filters.iter().all(|filter| Self::is_match(&row, filter)) {
+                let keys = query.sorts.iter().map(|sort| Self::get_cell(&row, sort.column).cloned()).collect();
+                entries.push((row.index, keys));
+            }
+
+            true
+        })?;
+
+        if !query.sorts.is_empty() {
+            // 稳定排序, 相等时保持原始行顺序
+            entries.sort_by(|(_, a), (_, b)| Self::compare_keys(a, b, &query.sorts));
+        }
+
+        let order = Arc::new(entries.into_iter().map(|(index, _)| index).collect::<Vec<usize>>());
+        let mut orders = Self::get_orders().lock().unwrap_or_else(|err| err.into_inner());
+        orders.retain(|(item, _)| *item != key);
+        if orders.len() >= MAX_CACHED_QUERIES {
+            orders.pop_front();
+        }
+        orders.push_back((key, order.clone()));
+        Ok(order)
+    }
+
+    fn get_cell(row: &ExcelRow, column: usize) -> Option<&ExcelCell> {
+        row.cells.get(column).filter(|cell| cell.cell_index == column)
+    }
+
+    fn is_empty(cell: Option<&ExcelCell>) -> bool {
+        cell.map(|cell| cell.value.trim().is_empty()).unwrap_or(true)
+    }
+
+    /// 单元格的数值, 日期、时长为序列号
+    fn get_number(cell: &ExcelCell) -> Option<f64> {
+        if Self::is_empty(Some(cell)) {
+            return None;
+        }
+
+        cell.raw.trim().parse::<f64>().ok().filter(|value| !value.is_nan())
+    }
+
+    fn is_match(row: &ExcelRow, filter: &SheetFilter) -> bool {
+        let cell = Self::get_cell(row, filter.column);
+        match filter.operator.as_str() {
+            FILTER_EMPTY => Self::is_empty(cell),
+            FILTER_NOT_EMPTY => !Self::is_empty(cell),
+            FILTER_EQUALS => cell
+                .map(|cell| cell.value.to_lowercase() == filter.value)
+                .unwrap_or(filter.value.is_empty()),
+            FILTER_CONTAINS => cell.map(|cell| cell.value.to_lowercase().contains(&filter.value)).unwrap_or(false),
+            FILTER_RANGE => match cell.and_then(Self::get_number) {
+                Some(value) => filter.min.map(|min| value >= min).unwrap_or(true) && filter.max.map(|max| value <= max).unwrap_or(true),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// 按排序列的单元格比较, `a`、`b` 与 `sorts` 一一对应
+    fn compare_keys(a: &[Option<ExcelCell>], b: &[Option<ExcelCell>], sorts: &[SheetSort]) -> Ordering {
+        for ((sort, a), b) in sorts.iter().zip(a.iter()).zip(b.iter()) {
+            let (a, b) = (a.as_ref(), b.as_ref());
+
+            // 空单元格始终排在最后
+            let ordering = match (Self::is_empty(a), Self::is_empty(b)) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    let ordering = Self::compare_cells(a.unwrap(), b.unwrap());
+                    if sort.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                }
+            };
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        Ordering::Equal
+    }
+
+    /// 数值按大小比较且排在文本之前, 文本不区分大小写比较
+    fn compare_cells(a: &ExcelCell, b: &ExcelCell) -> Ordering {
+        match (Self::get_number(a), Self::get_number(b)) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.value.to_lowercase().cmp(&b.value.to_lowercase()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: &str) -> Vec<Option<ExcelCell>> {
+        vec![Some(ExcelCell {
+            value: value.to_string(),
+            raw: value.to_string(),
+            ..Default::default()
+        })]
+    }
+
+    #[test]
+    fn test_compare_keys() {
+        let sorts = vec![SheetSort { column: 0, descending: false }];
+        let mut keys = vec![key("b"), vec![None], key("10"), key("A"), key("2")];
+        keys.sort_by(|a, b| Query::compare_keys(a, b, &sorts));
+        let values: Vec<String> = keys.iter().map(|key| key[0].as_ref().map(|cell| cell.value.clone()).unwrap_or_default()).collect();
+        assert_eq!(values, vec!["2", "10", "A", "b", ""]);
+
+        // 倒序时空单元格仍在最后
+        let sorts = vec![SheetSort { column: 0, descending: true }];
+        keys.sort_by(|a, b| Query::compare_keys(a, b, &sorts));
+        let values: Vec<String> = keys.iter().map(|key| key[0].as_ref().map(|cell| cell.value.clone()).unwrap_or_default()).collect();
+        assert_eq!(values, vec!["b", "A", "10", "2", ""]);
+    }
+}
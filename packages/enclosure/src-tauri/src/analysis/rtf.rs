@@ -0,0 +1,559 @@
+//! rtf 转换成 html
+
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use crate::utils::Utils;
+use encoding_rs::Encoding;
+use log::info;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+pub struct Rtf;
+
+/// 当前组的输出目标
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Destination {
+    Normal,
+    Skip,
+    FontTable,
+    Picture,
+}
+
+/// 字符格式
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+/// 组状态, `{` 时入栈, `}` 时出栈
+#[derive(Debug, Clone)]
+struct State {
+    destination: Destination,
+    style: Style,
+    encoding: &'static Encoding,
+    // \ucN, \uN 之后需要跳过的字符数
+    uc: usize,
+    // 剩余需要跳过的字符数, 组结束时恢复
+    skip_chars: usize,
+    in_table: bool,
+}
+
+/// 图片
+#[derive(Default, Debug, Clone)]
+struct Picture {
+    extension: String,
+    hex: String,
+    data: Vec<u8>,
+}
+
+/// 转换器
+struct Converter<'a> {
+    data: &'a [u8],
+    pos: usize,
+    output_dir: &'a PathBuf,
+    state: State,
+    stack: Vec<State>,
+    // 字体编号对应的编码
+    fonts: HashMap<i32, &'static Encoding>,
+    default_encoding: &'static Encoding,
+    font_index: i32,
+    ignorable: bool,
+    // 等待解码的字节
+    pending: Vec<u8>,
+    run: String,
+    run_style: Style,
+    paragraph: String,
+    cells: Vec<String>,
+    rows: Vec<String>,
+    picture: Picture,
+    picture_count: usize,
+    html: String,
+}
+
+const HTML_STYLE: &str = "table { border-collapse: collapse; margin: 4px 0; } \
+    td { border: 1px solid #ccc; padding: 2px 4px; vertical-align: top; } \
+    p { margin: 0 0 4px 0; } \
+    img { max-width: 100%; }";
+
+impl Rtf {
+    /// 转换 rtf 为 html, 图片保存到 `output_dir`
+    pub fn to_html(file_path: &str, output_dir: &PathBuf) -> Result<String, Error> {
+        info!("convert rtf `{}` to html ...", file_path);
+        let data = FileUtils::read_file(file_path)?;
+        if !data.starts_with(b"{\\rtf") {
+            return Err(Error::corrupt_file(file_path, "not a valid rtf file"));
+        }
+
+        let mut converter = Converter::new(&data, output_dir);
+        let body = converter.convert()?;
+
+        Ok(format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"/><style>{}</style></head><body>{}</body></html>",
+            HTML_STYLE, body
+        ))
+    }
+
+    /// codepage 转换成编码
+    fn get_encoding(codepage: i32) -> Option<&'static Encoding> {
+        let label = match codepage {
+            874 | 1250..=1258 => format!("windows-{}", codepage),
+            932 => "shift_jis".to_string(),
+            936 => "gbk".to_string(),
+            949 => "euc-kr".to_string(),
+            950 => "big5".to_string(),
+            10000 => "macintosh".to_string(),
+            65001 => "utf-8".to_string(),
+            _ => return None,
+        };
+
+        Encoding::for_label(label.as_bytes())
+    }
+
+    /// `\fcharset` 转换成 codepage
+    fn get_charset_codepage(charset: i32) -> Option<i32> {
+        let codepage = match charset {
+            0 => 1252,
+            77 => 10000,
+            128 => 932,
+            129 => 949,
+            134 => 936,
+            136 => 950,
+            161 => 1253,
+            162 => 1254,
+            163 => 1258,
+            177 => 1255,
+            178 => 1256,
+            186 => 1257,
+            204 => 1251,
+            222 => 874,
+            238 => 1250,
+            _ => return None,
+        };
+
+        Some(codepage)
+    }
+}
+
+impl<'a> Converter<'a> {
+    fn new(data: &'a [u8], output_dir: &'a PathBuf) -> Self {
+        let encoding = encoding_rs::WINDOWS_1252;
+        Self {
+            data,
+            pos: 0,
+            output_dir,
+            state: State {
+                destination: Destination::Normal,
+                style: Style::default(),
+                encoding,
+                uc: 1,
+                skip_chars: 0,
+                in_table: false,
+            },
+            stack: Vec::new(),
+            fonts: HashMap::new(),
+            default_encoding: encoding,
+            font_index: 0,
+            ignorable: false,
+            pending: Vec::new(),
+            run: String::new(),
+            run_style: Style::default(),
+            paragraph: String::new(),
+            cells: Vec::new(),
+            rows: Vec::new(),
+            picture: Picture::default(),
+            picture_count: 0,
+            html: String::new(),
+        }
+    }
+
+    fn convert(&mut self) -> Result<String, Error> {
+        while self.pos < self.data.len() {
+            let c = self.data[self.pos];
+            self.pos += 1;
+
+            match c {
+                b'{' => {
+                    self.flush_pending();
+                    self.stack.push(self.state.clone());
+                }
+                b'}' => {
+                    self.flush_pending();
+                    let state = self.stack.pop().unwrap_or(self.state.clone());
+                    if self.state.destination == Destination::Picture && state.destination != Destination::Picture {
+                        self.end_picture()?;
+                    }
+
+                    self.state = state;
+                }
+                b'\\' => self.read_control()?,
+                b'\r' | b'\n' => {}
+                _ => self.push_byte(c),
+            }
+        }
+
+        self.end_paragraph();
+        self.end_table();
+        Ok(self.html.clone())
+    }
+
+    /// 读取控制字或控制符
+    fn read_control(&mut self) -> Result<(), Error> {
+        let c = match self.data.get(self.pos) {
+            Some(c) => *c,
+            None => return Ok(()),
+        };
+
+        if !c.is_ascii_alphabetic() {
+            self.pos += 1;
+            return self.apply_symbol(c);
+        }
+
+        let start = self.pos;
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_alphabetic() {
+            self.pos += 1;
+        }
+
+        let word = String::from_utf8_lossy(&self.data[start..self.pos]).to_string();
+
+        let param_start = self.pos;
+        if self.data.get(self.pos) == Some(&b'-') {
+            self.pos += 1;
+        }
+
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+
+        let param = String::from_utf8_lossy(&self.data[param_start..self.pos]).parse::<i32>().ok();
+
+        // 空格为分隔符
+        if self.data.get(self.pos) == Some(&b' ') {
+            self.pos += 1;
+        }
+
+        self.apply_word(&word, param)
+    }
+
+    /// 控制符
+    fn apply_symbol(&mut self, c: u8) -> Result<(), Error> {
+        match c {
+            b'\'' => {
+                let hex = self.data.get(self.pos..self.pos + 2).unwrap_or(&[]);
+                let byte = u8::from_str_radix(&String::from_utf8_lossy(hex), 16).ok();
+                self.pos += 2;
+                if let Some(byte) = byte {
+                    self.push_byte(byte);
+                }
+            }
+            b'\\' | b'{' | b'}' => self.push_byte(c),
+            b'~' => self.push_str("\u{a0}"),
+            b'_' => self.push_str("-"),
+            b'*' => self.ignorable = true,
+            b'\r' | b'\n' => self.apply_word("par", None)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// 控制字
+    fn apply_word(&mut self, word: &str, param: Option<i32>) -> Result<(), Error> {
+        let ignorable = self.ignorable;
+        self.ignorable = false;
+
+        // 二进制数据
+        if word == "bin" {
+            let size = param.unwrap_or(0).max(0) as usize;
+            let end = (self.pos + size).min(self.data.len());
+            if self.state.destination == Destination::Picture {
+                self.picture.data.extend_from_slice(&self.data[self.pos..end]);
+            }
+
+            self.pos = end;
+            return Ok(());
+        }
+
+        if self.state.destination == Destination::Skip {
+            return Ok(());
+        }
+
+        // 未知的可忽略目标
+        if ignorable && word != "shppict" {
+            self.state.destination = Destination::Skip;
+            return Ok(());
+        }
+
+        self.flush_pending();
+
+        match word {
+            // 需要跳过的目标
+            "colortbl" | "stylesheet" | "info" | "header" | "headerl" | "headerr" | "headerf" | "footer" | "footerl" | "footerr" | "footerf"
+            | "fldinst" | "nonshppict" | "listtable" | "listoverridetable" | "revtbl" | "rsidtbl" | "generator" | "xmlnstbl" => {
+                self.state.destination = Destination::Skip;
+            }
+            "shppict" => {}
+            "fonttbl" => self.state.destination = Destination::FontTable,
+            "pict" => {
+                self.state.destination = Destination::Picture;
+                self.picture = Picture::default();
+            }
+            "pngblip" => self.picture.extension = "png".to_string(),
+            "jpegblip" => self.picture.extension = "jpg".to_string(),
+            "ansicpg" => {
+                if let Some(encoding) = param.and_then(Rtf::get_encoding) {
+                    self.default_encoding = encoding;
+                    self.state.encoding = encoding;
+                }
+            }
+            "f" => {
+                let index = param.unwrap_or(0);
+                if self.state.destination == Destination::FontTable {
+                    self.font_index = index;
+                } else {
+                    self.state.encoding = self.fonts.get(&index).cloned().unwrap_or(self.default_encoding);
+                }
+            }
+            "fcharset" => {
+                if self.state.destination == Destination::FontTable {
+                    if let Some(encoding) = param.and_then(Rtf::get_charset_codepage).and_then(Rtf::get_encoding) {
+                        self.fonts.insert(self.font_index, encoding);
+                    }
+                }
+            }
+            "uc" => self.state.uc = param.unwrap_or(1).max(0) as usize,
+            "u" => {
+                let mut code = param.unwrap_or(0);
+                if code < 0 {
+                    code += 65536;
+                }
+
+                if let Some(c) = char::from_u32(code as u32) {
+                    self.push_str(&c.to_string());
+                }
+
+                self.state.skip_chars = self.state.uc;
+            }
+            _ if self.state.destination != Destination::Normal => {}
+            "par" | "sect" | "page" => self.end_paragraph(),
+            "line" => {
+                self.flush_run();
+                self.paragraph.push_str("<br/>");
+            }
+            "tab" => self.push_str("\u{a0}\u{a0}\u{a0}\u{a0}"),
+            "emdash" => self.push_str("\u{2014}"),
+            "endash" => self.push_str("\u{2013}"),
+            "bullet" => self.push_str("\u{2022}"),
+            "lquote" => self.push_str("\u{2018}"),
+            "rquote" => self.push_str("\u{2019}"),
+            "ldblquote" => self.push_str("\u{201c}"),
+            "rdblquote" => self.push_str("\u{201d}"),
+            "b" => self.state.style.bold = param != Some(0),
+            "i" => self.state.style.italic = param != Some(0),
+            "ul" => self.state.style.underline = param != Some(0),
+            "ulnone" => self.state.style.underline = false,
+            "plain" => self.state.style = Style::default(),
+            "pard" => self.state.in_table = false,
+            "intbl" => self.state.in_table = true,
+            "cell" => self.end_cell(),
+            "row" => self.end_row(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// 文本字节
+    fn push_byte(&mut self, byte: u8) {
+        if self.state.skip_chars > 0 {
+            self.state.skip_chars -= 1;
+            return;
+        }
+
+        match self.state.destination {
+            Destination::Normal => self.pending.push(byte),
+            Destination::Picture => {
+                if byte.is_ascii_hexdigit() {
+                    self.picture.hex.push(byte as char);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 文本字符串
+    fn push_str(&mut self, text: &str) {
+        if self.state.destination != Destination::Normal {
+            return;
+        }
+
+        self.flush_pending();
+        self.prepare_run();
+        self.run.push_str(&Utils::escape_html(text));
+    }
+
+    /// 按当前编码解码等待中的字节
+    fn flush_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let bytes = std::mem::take(&mut self.pending);
+        let (text, _, _) = self.state.encoding.decode(&bytes);
+        self.prepare_run();
+        self.run.push_str(&Utils::escape_html(&text));
+    }
+
+    /// 格式变化时结束上一段文字
+    fn prepare_run(&mut self) {
+        if self.run_style != self.state.style {
+            self.flush_run();
+            self.run_style = self.state.style;
+        }
+    }
+
+    fn flush_run(&mut self) {
+        if self.run.is_empty() {
+            return;
+        }
+
+        let run = std::mem::take(&mut self.run);
+        let mut css = String::new();
+        if self.run_style.bold {
+            css.push_str("font-weight: bold;");
+        }
+
+        if self.run_style.italic {
+            css.push_str("font-style: italic;");
+        }
+
+        if self.run_style.underline {
+            css.push_str("text-decoration: underline;");
+        }
+
+        if css.is_empty() {
+            self.paragraph.push_str(&run);
+        } else {
+            self.paragraph.push_str(&format!("<span style=\"{}\">{}</span>", css, run));
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        self.flush_pending();
+        self.flush_run();
+        let paragraph = std::mem::take(&mut self.paragraph);
+
+        if self.state.in_table {
+            self.paragraph = format!("{}<br/>", paragraph);
+            return;
+        }
+
+        self.end_table();
+        self.html.push_str(&format!("<p>{}</p>", if paragraph.is_empty() { "&#160;".to_string() } else { paragraph }));
+    }
+
+    fn end_cell(&mut self) {
+        self.flush_pending();
+        self.flush_run();
+        let paragraph = std::mem::take(&mut self.paragraph);
+        self.cells.push(format!("<td>{}</td>", paragraph));
+    }
+
+    fn end_row(&mut self) {
+        let cells = std::mem::take(&mut self.cells);
+        self.rows.push(format!("<tr>{}</tr>", cells.join("")));
+    }
+
+    fn end_table(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+
+        let rows = std::mem::take(&mut self.rows);
+        self.html.push_str(&format!("<table>{}</table>", rows.join("")));
+    }
+
+    /// 保存图片, 只支持 png 和 jpeg
+    fn end_picture(&mut self) -> Result<(), Error> {
+        let mut picture = std::mem::take(&mut self.picture);
+        if picture.extension.is_empty() {
+            return Ok(());
+        }
+
+        let hex = picture.hex.as_bytes();
+        for pair in hex.chunks(2) {
+            if let Ok(byte) = u8::from_str_radix(&String::from_utf8_lossy(pair), 16) {
+                picture.data.push(byte);
+            }
+        }
+
+        if picture.data.is_empty() {
+            return Ok(());
+        }
+
+        self.picture_count += 1;
+        let name = format!("picture-{}.{}", self.picture_count, picture.extension);
+        let output_path = self.output_dir.join(&name);
+        fs::write(&output_path, &picture.data).map_err(|err| Error::io(err, &output_path.to_string_lossy()))?;
+
+        self.flush_pending();
+        self.flush_run();
+        self.paragraph.push_str(&format!("<img src=\"{}\"/>", name));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(data: &str) -> String {
+        let output_dir = std::env::temp_dir();
+        Converter::new(data.as_bytes(), &output_dir).convert().unwrap()
+    }
+
+    #[test]
+    fn test_ansi_codepage() {
+        assert_eq!(convert("{\\rtf1\\ansi\\ansicpg1251 \\'cf\\'f0\\'e8}"), "<p>\u{41f}\u{440}\u{438}</p>");
+    }
+
+    #[test]
+    fn test_font_charset() {
+        let html = convert("{\\rtf1{\\fonttbl{\\f0\\fcharset134 SimSun;}{\\f1\\fcharset204 Arial;}}\\f0 \\'c4\\'e3\\f1 \\'cf}");
+        assert_eq!(html, "<p>\u{4f60}\u{41f}</p>");
+    }
+
+    #[test]
+    fn test_unicode_escape() {
+        assert_eq!(convert("{\\rtf1\\uc1\\u20320?\\u22909?}"), "<p>\u{4f60}\u{597d}</p>");
+        assert_eq!(convert("{\\rtf1\\uc2\\u8212\\'97\\'97-}"), "<p>\u{2014}-</p>");
+        // 负数按 65536 取补
+        assert_eq!(convert("{\\rtf1\\u-3913?}"), "<p>\u{f0b7}</p>");
+    }
+
+    #[test]
+    fn test_unicode_skip_ends_with_group() {
+        assert_eq!(convert("{\\rtf1{\\uc2\\u8212}abc}"), "<p>\u{2014}abc</p>");
+    }
+
+    #[test]
+    fn test_table() {
+        let html = convert("{\\rtf1\\trowd\\intbl A\\cell B\\cell\\row\\pard after}");
+        assert_eq!(html, "<table><tr><td>A</td><td>B</td></tr></table><p>after</p>");
+    }
+
+    #[test]
+    fn test_picture() {
+        let output_dir = std::env::temp_dir().join(format!("rtf-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&output_dir).unwrap();
+
+        let data = "{\\rtf1{\\pict\\pngblip 89504e47}}";
+        let html = Converter::new(data.as_bytes(), &output_dir).convert().unwrap();
+        assert_eq!(html, "<p><img src=\"picture-1.png\"/></p>");
+        assert_eq!(fs::read(output_dir.join("picture-1.png")).unwrap(), vec![0x89, 0x50, 0x4E, 0x47]);
+
+        let _ = fs::remove_dir_all(&output_dir);
+    }
+}
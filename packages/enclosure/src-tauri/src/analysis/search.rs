@@ -0,0 +1,250 @@
+//! 搜索 workbook 中的所有 sheet
+
+use crate::analysis::excel::Excel;
+use crate::config::{ExcelRow, HttpResponse, SearchEvent, SearchMatch, WORKBOOK_SEARCH_EVENT};
+use crate::error::Error;
+use crate::job::Job;
+use log::{error, info};
+use regex::{Regex, RegexBuilder};
+use tauri::{AppHandle, Manager};
+
+pub struct Search;
+
+/// 匹配方式
+enum Matcher {
+    // 子串, 不区分大小写时保存小写
+    Text(String, bool),
+    Regex(Regex),
+}
+
+// 每次发送的结果数
+const BATCH_SIZE: usize = 100;
+
+// 最多返回的结果数
+const MAX_MATCHES: usize = 10000;
+
+// 上下文取匹配单元格左右各几列
+const CONTEXT_COLUMNS: usize = 2;
+
+const SEARCH_MATCH: &str = "match";
+const SEARCH_FINISHED: &str = "finished";
+const SEARCH_CANCELLED: &str = "cancelled";
+const SEARCH_FAILED: &str = "failed";
+
+impl Matcher {
+    fn new(query: &str, regex: bool, case_sensitive: bool) -> Result<Self, Error> {
+        if regex {
+            let regex = RegexBuilder::new(query)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|err| match err {
+                    regex::Error::CompiledTooBig(limit) => Error::LimitExceeded {
+                        name: "regex".to_string(),
+                        limit,
+                    },
+                    err => Error::invalid_argument("regex", query, err),
+                })?;
+            return Ok(Matcher::Regex(regex));
+        }
+
+        if case_sensitive {
+            Ok(Matcher::Text(query.to_string(), true))
+        } else {
+            Ok(Matcher::Text(query.to_lowercase(), false))
+        }
+    }
+
+    fn is_match(&self, value: &str) -> bool {
+        match self {
+            Matcher::Text(query, true) => value.contains(query.as_str()),
+            Matcher::Text(query, false) => value.to_lowercase().contains(query.as_str()),
+            Matcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+impl Search {
+    /// 搜索, 立即返回任务 id, 结果通过 `WORKBOOK_SEARCH_EVENT` 分批发送到前端, 使用 `cancel_job` 取消
+    pub fn search_workbook(app: &AppHandle, file_path: &str, query: &str, regex: bool, case_sensitive: bool) -> Result<HttpResponse, Error> {
+        info!("search `{}` in `{}` ...", query, file_path);
+        let mut response = HttpResponse::default();
+        if query.is_empty() {
+            response.set_error(Error::invalid_argument("query", query, "it is empty"));
+            return Ok(response);
+        }
+
+        let matcher = match Matcher::new(query, regex, case_sensitive) {
+            Ok(matcher) => matcher,
+            Err(err) => {
+                response.set_error(err);
+                return Ok(response);
+            }
+        };
+
+        let job = Job::create(None, "search", file_path);
+        let id = job.id.clone();
+
+        let app = app.clone();
+        let file_path = file_path.to_string();
+        async_std::task::spawn_blocking(move || {
+            let result = Self::scan(&app, &job, &file_path, &matcher);
+            let (kind, total, error) = match result {
+                Ok(_) if job.is_cancelled() => (SEARCH_CANCELLED, 0, String::new()),
+                Ok(total) => (SEARCH_FINISHED, total, String::new()),
+                Err(err) => {
+                    error!("search `{}` error: {}", &file_path, &err);
+                    (SEARCH_FAILED, 0, err.to_string())
+                }
+            };
+
+            Self::send_event(&app, &job.id, kind, Vec::new(), total, &error);
+        });
+
+        response.code = 200;
+        response.body = id.clone();
+        response.job_id = id;
+        Ok(response)
+    }
+
+    /// 逐个 sheet 扫描, 返回结果总数
+    fn scan(app: &AppHandle, job: &Job, file_path: &str, matcher: &Matcher) -> Result<usize, Error> {
+        let sheet_names = Excel::get_sheet_names(file_path)?;
+        let mut total: usize = 0;
+        let mut matches: Vec<SearchMatch> = Vec::new();
+
+        for (index, sheet_name) in sheet_names.iter().enumerate() {
+            let sheet_index = index as u32 + 1;
+            Excel::scan_sheet(file_path, sheet_index, |row| {
+                if job.is_cancelled() || total >= MAX_MATCHES {
+                    return false;
+                }
+
+                let found = Self::match_row(&row, matcher, sheet_name, sheet_index, MAX_MATCHES - total);
+                total += found.len();
+                matches.extend(found);
+
+                if matches.len() >= BATCH_SIZE {
+                    Self::send_event(app, &job.id, SEARCH_MATCH, std::mem::take(&mut matches), total, "");
+                }
+
+                true
+            })?;
+
+            if !matches.is_empty() {
+                Self::send_event(app, &job.id, SEARCH_MATCH, std::mem::take(&mut matches), total, "");
+            }
+
+            if job.is_cancelled() || total >= MAX_MATCHES {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// 行中匹配的单元格, 最多 `limit` 个, 上下文为左右各 `CONTEXT_COLUMNS` 列
+    fn match_row(row: &ExcelRow, matcher: &Matcher, sheet_name: &str, sheet_index: u32, limit: usize) -> Vec<SearchMatch> {
+        let mut matches: Vec<SearchMatch> = Vec::new();
+        for (i, cell) in row.cells.iter().enumerate() {
+            if matches.len() >= limit {
+                break;
+            }
+
+            if cell.value.is_empty() || !matcher.is_match(&cell.value) {
+                continue;
+            }
+
+            let start = i.saturating_sub(CONTEXT_COLUMNS);
+            let end = (i + CONTEXT_COLUMNS + 1).min(row.cells.len());
+            matches.push(SearchMatch {
+                sheet: sheet_name.to_string(),
+                sheet_index,
+                row: row.index,
+                column: cell.cell_index,
+                value: cell.value.clone(),
+                context: row.cells[start..end].iter().map(|cell| cell.value.clone()).collect(),
+            });
+        }
+
+        matches
+    }
+
+    fn send_event(app: &AppHandle, id: &str, kind: &str, matches: Vec<SearchMatch>, total: usize, error: &str) {
+        let event = SearchEvent {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            matches,
+            total,
+            error: error.to_string(),
+        };
+
+        if let Err(err) = app.emit(WORKBOOK_SEARCH_EVENT, event) {
+            error!("send search event error: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExcelCell;
+
+    fn row(values: &[&str]) -> ExcelRow {
+        let cells = values
+            .iter()
+            .enumerate()
+            .map(|(cell_index, value)| ExcelCell {
+                value: value.to_string(),
+                cell_index,
+                ..Default::default()
+            })
+            .collect();
+        ExcelRow { index: 5, cells }
+    }
+
+    #[test]
+    fn test_matcher() {
+        let matcher = Matcher::new("Total", false, false).unwrap();
+        assert!(matcher.is_match("grand TOTAL"));
+        assert!(!matcher.is_match("tot"));
+
+        let matcher = Matcher::new("Total", false, true).unwrap();
+        assert!(!matcher.is_match("grand TOTAL"));
+        assert!(matcher.is_match("Total: 3"));
+
+        let matcher = Matcher::new("^a\\d+$", true, false).unwrap();
+        assert!(matcher.is_match("A12"));
+        assert!(!matcher.is_match("A12b"));
+
+        let err = Matcher::new("(unclosed", true, false).err().unwrap();
+        assert_eq!(err.code(), "invalid_argument");
+
+        let err = Matcher::new("\\w{1000}{1000}", true, false).err().unwrap();
+        assert_eq!(err.code(), "limit_exceeded");
+    }
+
+    #[test]
+    fn test_match_row() {
+        let matcher = Matcher::new("x", false, false).unwrap();
+        let matches = Search::match_row(&row(&["x1", "b", "c", "d", "x2", "f"]), &matcher, "Sheet1", 1, MAX_MATCHES);
+        assert_eq!(matches.len(), 2);
+        assert_eq!((matches[0].row, matches[0].column), (5, 0));
+
+        // 上下文在行首、行尾截断
+        assert_eq!(matches[0].context, vec!["x1", "b", "c"]);
+        assert_eq!(matches[1].context, vec!["c", "d", "x2", "f"]);
+
+        // 空单元格不匹配
+        let matcher = Matcher::new("^$", true, false).unwrap();
+        assert!(Search::match_row(&row(&["", "a"]), &matcher, "Sheet1", 1, MAX_MATCHES).is_empty());
+    }
+
+    #[test]
+    fn test_match_limit() {
+        let matcher = Matcher::new("x", false, false).unwrap();
+        let values = ["x"; 8];
+        assert_eq!(Search::match_row(&row(&values), &matcher, "Sheet1", 1, 3).len(), 3);
+        assert!(Search::match_row(&row(&values), &matcher, "Sheet1", 1, 0).is_empty());
+        assert_eq!(Search::match_row(&row(&values), &matcher, "Sheet1", 1, MAX_MATCHES - 2).len(), 8);
+    }
+}
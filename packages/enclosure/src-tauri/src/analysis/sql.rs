@@ -0,0 +1,517 @@
+//! 在内存数据库中对 sheet、csv 执行 sql 查询
+
+use crate::analysis::delimited::Delimited;
+use crate::analysis::excel::{Excel, CELL_BOOL, CELL_EMPTY, CELL_NUMBER, CELL_STRING};
+use crate::analysis::number_format::NumberFormat;
+use crate::analysis::xlsx::Xlsx;
+use crate::config::{ExcelCell, ExcelRow, HttpResponse, SqlQueryResult, DELIMITED_SUFFIXES};
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use log::info;
+use rusqlite::limits::Limit;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Connection, Transaction};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Instant;
+
+pub struct Sql;
+
+/// 导入的表
+struct Table {
+    name: String,
+    columns: Vec<String>,
+    types: Vec<&'static str>,
+}
+
+// 最多返回的行数
+const MAX_RESULT_ROWS: usize = 10000;
+
+// 推断列名和列类型的采样行数
+const SAMPLE_ROWS: usize = 1000;
+
+const TYPE_INTEGER: &str = "INTEGER";
+const TYPE_REAL: &str = "REAL";
+const TYPE_TEXT: &str = "TEXT";
+
+impl Sql {
+    /// 执行 sql, 每个 sheet 对应表 `sheet1`、`sheet2` ..., 同时可使用 sheet 名称(csv 为文件名)访问
+    pub fn query(file_path: &str, sql: &str) -> Result<HttpResponse, Error> {
+        info!("query sql `{}` in `{}` ...", sql, file_path);
+        let start_time = Instant::now();
+        let mut response = HttpResponse::default();
+        if sql.trim().is_empty() {
+            response.set_error(Error::invalid_argument("sql", sql, "it is empty"));
+            return Ok(response);
+        }
+
+        let mut conn = Connection::open_in_memory().map_err(|err| Error::Error(err.to_string()))?;
+        // 禁止 attach, 避免读写磁盘上的其它数据库
+        conn.set_limit(Limit::SQLITE_LIMIT_ATTACHED, 0);
+
+        // 读取、导入 sheet 的错误返回给前端
+        if let Err(err) = Self::create_tables(&mut conn, file_path, sql) {
+            response.set_error(err);
+            return Ok(response);
+        }
+
+        // sql 语法等错误返回给前端
+        match Self::execute(&conn, sql) {
+            Ok(result) => {
+                info!("query sql rows: {}, cost: {:?}", result.rows.len(), start_time.elapsed());
+                response.code = 200;
+                response.body = serde_json::to_string(&result).unwrap_or("".to_string());
+            }
+            Err(err) => response.set_error(err),
+        }
+
+        Ok(response)
+    }
+
+    /// 导入 sql 中引用的 sheet, sheet 名称不与 `sheetN` 冲突时创建同名视图
+    fn create_tables(conn: &mut Connection, file_path: &str, sql: &str) -> Result<(), Error> {
+        let sheet_names = Self::get_table_names(file_path)?;
+        let table_names: Vec<String> = (1..=sheet_names.len()).map(|index| format!("sheet{}", index)).collect();
+        let identifiers = Self::get_identifiers(sql);
+
+        for (index, sheet_name) in sheet_names.iter().enumerate() {
+            let table_name = &table_names[index];
+            let alias = sheet_name.to_lowercase();
+            if !identifiers.contains(table_name) && !identifiers.contains(&alias) {
+                continue;
+            }
+
+            Self::import_table(conn, file_path, index as u32 + 1, table_name)?;
+
+            // 名称不区分大小写, 与任一 `sheetN` 或其它 sheet 重名时不创建
+            let duplicated = sheet_names.iter().enumerate().any(|(i, name)| i != index && name.to_lowercase() == alias);
+            if !table_names.contains(&alias) && !duplicated {
+                let view = format!("CREATE VIEW {} AS SELECT * FROM {}", Self::quote(sheet_name), Self::quote(table_name));
+                conn.execute_batch(&view).map_err(|err| Error::Error(err.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// sql 中的标识符(小写), 忽略字符串和注释, 引号包裹的标识符取引号内的内容
+    fn get_identifiers(sql: &str) -> HashSet<String> {
+        let chars: Vec<char> = sql.chars().collect();
+        let mut identifiers: HashSet<String> = HashSet::new();
+        let mut index = 0;
+
+        // 读取到 `end` 为止, 两个连续的 `end` 为转义
+        let read_quoted = |index: &mut usize, end: char| {
+            let mut value = String::new();
+            *index += 1;
+            while *index < chars.len() {
+                let c = chars[*index];
+                *index += 1;
+                if c == end {
+                    if end != ']' && chars.get(*index) == Some(&end) {
+                        *index += 1;
+                    } else {
+                        break;
+                    }
+                }
+
+                value.push(c);
+            }
+
+            value
+        };
+
+        while index < chars.len() {
+            let c = chars[index];
+            match c {
+                '\'' => {
+                    read_quoted(&mut index, '\'');
+                }
+                '"' | '`' | '[' => {
+                    let end = if c == '[' { ']' } else { c };
+                    identifiers.insert(read_quoted(&mut index, end).to_lowercase());
+                }
+                '-' if chars.get(index + 1) == Some(&'-') => {
+                    while index < chars.len() && chars[index] != '\n' {
+                        index += 1;
+                    }
+                }
+                '/' if chars.get(index + 1) == Some(&'*') => {
+                    index += 2;
+                    while index < chars.len() && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/')) {
+                        index += 1;
+                    }
+                    index += 2;
+                }
+                _ if c.is_alphanumeric() || c == '_' || c == '$' => {
+                    let begin = index;
+                    while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_' || chars[index] == '$') {
+                        index += 1;
+                    }
+                    identifiers.insert(chars[begin..index].iter().collect::<String>().to_lowercase());
+                }
+                _ => index += 1,
+            }
+        }
+
+        identifiers
+    }
+
+    /// 表名, csv 为不含后缀的文件名
+    fn get_table_names(file_path: &str) -> Result<Vec<String>, Error> {
+        let suffix = FileUtils::get_file_suffix(file_path);
+        if DELIMITED_SUFFIXES.contains(&suffix.as_str()) {
+            let name = Path::new(file_path)
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return Ok(vec![name]);
+        }
+
+        Excel::get_sheet_names(file_path)
+    }
+
+    /// 导入 sheet, 按前 `SAMPLE_ROWS` 行推断列名和列类型后建表, 之后的行边读取边写入
+    fn import_table(conn: &mut Connection, file_path: &str, sheet_index: u32, table_name: &str) -> Result<(), Error> {
+        let tx = conn.transaction().map_err(|err| Error::Error(err.to_string()))?;
+        let mut sample: Vec<ExcelRow> = Vec::new();
+        let mut table: Option<Table> = None;
+        let mut error: Option<Error> = None;
+
+        Excel::scan_sheet(file_path, sheet_index, |row| {
+            let result = match table.as_mut() {
+                Some(table) => Self::insert_row(&tx, table, &row),
+                None => {
+                    sample.push(row);
+                    if sample.len() <= SAMPLE_ROWS {
+                        return true;
+                    }
+
+                    Self::create_table(&tx, file_path, table_name, std::mem::take(&mut sample)).map(|created| table = Some(created))
+                }
+            };
+
+            match result {
+                Ok(_) => true,
+                Err(err) => {
+                    error = Some(err);
+                    false
+                }
+            }
+        })?;
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        // 不足采样行数
+        if table.is_none() {
+            Self::create_table(&tx, file_path, table_name, sample)?;
+        }
+
+        tx.commit().map_err(|err| Error::Error(err.to_string()))
+    }
+
+    /// 第一行全部为不重复的文本, 且后续还有数据时作为表头
+    fn is_header(rows: &[ExcelRow]) -> bool {
+        let first = match rows.first() {
+            Some(row) if rows.len() > 1 && !row.cells.is_empty() => row,
+            _ => return false,
+        };
+
+        let mut names: HashSet<String> = HashSet::new();
+        first
+            .cells
+            .iter()
+            .all(|cell| cell.kind == CELL_STRING && !cell.value.trim().is_empty() && names.insert(cell.value.trim().to_lowercase()))
+    }
+
+    /// 列名, 没有表头或表头为空时使用 A、B、C ...
+    pub fn get_columns(header: &[String], columns_count: usize) -> Vec<String> {
+        let mut names: HashSet<String> = HashSet::new();
+        let mut columns: Vec<String> = Vec::new();
+        for column in 0..columns_count {
+            let name = header
+                .get(column)
+                .filter(|name| !name.is_empty())
+                .cloned()
+                .unwrap_or(Xlsx::get_column_name(column));
+
+            // 重名时添加序号
+            let mut unique = name.clone();
+            let mut index = 2;
+            while !names.insert(unique.to_lowercase()) {
+                unique = format!("{}_{}", name, index);
+                index += 1;
+            }
+
+            columns.push(unique);
+        }
+
+        columns
+    }
+
+    /// 全部为整数时为 INTEGER, 全部为数值时为 REAL, 否则为 TEXT
+    fn get_type(rows: &[ExcelRow], column: usize) -> &'static str {
+        let mut kind = TYPE_INTEGER;
+        let mut has_value = false;
+        for cell in rows.iter().filter_map(|row| row.cells.get(column)) {
+            if cell.kind == CELL_EMPTY || cell.value.is_empty() {
+                continue;
+            }
+
+            has_value = true;
+            match cell.kind.as_str() {
+                CELL_BOOL => {}
+                CELL_NUMBER if cell.raw.parse::<i64>().is_ok() => {}
+                CELL_NUMBER => kind = TYPE_REAL,
+                _ => return TYPE_TEXT,
+            }
+        }
+
+        if has_value {
+            kind
+        } else {
+            TYPE_TEXT
+        }
+    }
+
+    /// 按列类型转换, 采样之外与列类型不符的值保留为数值或文本
+    fn get_value(cell: &ExcelCell, kind: &str) -> Value {
+        if cell.kind == CELL_EMPTY || cell.value.is_empty() {
+            return Value::Null;
+        }
+
+        match kind {
+            TYPE_INTEGER if cell.kind == CELL_BOOL => Value::Integer(if cell.raw == "true" { 1 } else { 0 }),
+            TYPE_REAL if cell.kind == CELL_BOOL => Value::Real(if cell.raw == "true" { 1.0 } else { 0.0 }),
+            TYPE_INTEGER | TYPE_REAL if cell.kind == CELL_NUMBER => match cell.raw.parse::<i64>() {
+                Ok(value) if kind == TYPE_INTEGER => Value::Integer(value),
+                _ => cell.raw.parse::<f64>().map(Value::Real).unwrap_or(Value::Text(cell.value.clone())),
+            },
+            _ => Value::Text(cell.value.clone()),
+        }
+    }
+
+    /// 按采样行推断列名和列类型, 建表并写入采样行
+    fn create_table(tx: &Transaction, file_path: &str, table_name: &str, mut rows: Vec<ExcelRow>) -> Result<Table, Error> {
+        let suffix = FileUtils::get_file_suffix(file_path);
+        let header: Vec<String> = if DELIMITED_SUFFIXES.contains(&suffix.as_str()) {
+            Delimited::read_header(file_path)?
+        } else if Self::is_header(&rows) {
+            rows.remove(0).cells.into_iter().map(|cell| cell.value.trim().to_string()).collect()
+        } else {
+            Vec::new()
+        };
+
+        let columns_count = rows.iter().map(|row| row.cells.len()).max().unwrap_or(0).max(header.len());
+        let mut table = Table {
+            name: table_name.to_string(),
+            columns: Self::get_columns(&header, columns_count),
+            types: (0..columns_count).map(|column| Self::get_type(&rows, column)).collect(),
+        };
+
+        let columns: Vec<String> = table
+            .columns
+            .iter()
+            .zip(table.types.iter())
+            .map(|(column, kind)| format!("{} {}", Self::quote(column), kind))
+            .collect();
+        let create = format!("CREATE TABLE {} ({})", Self::quote(&table.name), columns.join(", "));
+        tx.execute_batch(&create).map_err(|err| Error::Error(err.to_string()))?;
+
+        for row in rows.iter() {
+            Self::insert_row(tx, &mut table, row)?;
+        }
+
+        Ok(table)
+    }
+
+    /// 写入一行, 超出已有列数时追加 TEXT 列
+    fn insert_row(tx: &Transaction, table: &mut Table, row: &ExcelRow) -> Result<(), Error> {
+        if row.cells.len() > table.columns.len() {
+            let columns = Self::get_columns(&table.columns, row.cells.len());
+            for column in columns.iter().skip(table.columns.len()) {
+                let alter = format!("ALTER TABLE {} ADD COLUMN {} {}", Self::quote(&table.name), Self::quote(column), TYPE_TEXT);
+                tx.execute_batch(&alter).map_err(|err| Error::Error(err.to_string()))?;
+                table.types.push(TYPE_TEXT);
+            }
+            table.columns = columns;
+        }
+
+        if table.columns.is_empty() {
+            return Ok(());
+        }
+
+        let placeholders: Vec<String> = (1..=table.columns.len()).map(|i| format!("?{}", i)).collect();
+        let insert = format!("INSERT INTO {} VALUES ({})", Self::quote(&table.name), placeholders.join(", "));
+        let mut stmt = tx.prepare_cached(&insert).map_err(|err| Error::Error(err.to_string()))?;
+        let values = table.types.iter().enumerate().map(|(column, kind)| match row.cells.get(column) {
+            Some(cell) => Self::get_value(cell, kind),
+            None => Value::Null,
+        });
+        stmt.execute(params_from_iter(values)).map_err(|err| Error::Error(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 执行只读查询, 结果转换为 `ExcelRow`
+    fn execute(conn: &Connection, sql: &str) -> Result<SqlQueryResult, Error> {
+        let mut stmt = conn.prepare(sql).map_err(|err| Error::Error(err.to_string()))?;
+        if !stmt.readonly() {
+            return Err(Error::invalid_argument("sql", sql, "only read-only statements are supported"));
+        }
+
+        let columns: Vec<String> = stmt.column_names().iter().map(|name| name.to_string()).collect();
+        let columns_count = stmt.column_count();
+        let mut result = SqlQueryResult {
+            columns,
+            ..SqlQueryResult::default()
+        };
+
+        let mut rows = stmt.query([]).map_err(|err| Error::Error(err.to_string()))?;
+        while let Some(row) = rows.next().map_err(|err| Error::Error(err.to_string()))? {
+            if result.rows.len() >= MAX_RESULT_ROWS {
+                result.truncated = true;
+                break;
+            }
+
+            let row_index = result.rows.len();
+            let mut cells: Vec<ExcelCell> = Vec::new();
+            for cell_index in 0..columns_count {
+                let value = row.get_ref(cell_index).map_err(|err| Error::Error(err.to_string()))?;
+                let (kind, value, raw) = match value {
+                    ValueRef::Null => (CELL_EMPTY, String::new(), String::new()),
+                    ValueRef::Integer(i) => (CELL_NUMBER, i.to_string(), i.to_string()),
+                    ValueRef::Real(f) => (CELL_NUMBER, NumberFormat::format_general(f), f.to_string()),
+                    ValueRef::Text(t) => {
+                        let text = String::from_utf8_lossy(t).to_string();
+                        (CELL_STRING, text.clone(), text)
+                    }
+                    ValueRef::Blob(b) => (CELL_STRING, format!("<{} bytes>", b.len()), String::new()),
+                };
+
+                cells.push(ExcelCell {
+                    value,
+                    kind: kind.to_string(),
+                    raw,
+                    formula: String::new(),
+                    row_index,
+                    cell_index,
+                });
+            }
+
+            result.rows.push(ExcelRow { index: row_index, cells });
+        }
+
+        Ok(result)
+    }
+
+    /// 标识符加双引号
+    fn quote(name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(kind: &str, value: &str) -> ExcelCell {
+        ExcelCell {
+            value: value.to_string(),
+            kind: kind.to_string(),
+            raw: value.to_string(),
+            formula: String::new(),
+            row_index: 0,
+            cell_index: 0,
+        }
+    }
+
+    fn row(cells: Vec<ExcelCell>) -> ExcelRow {
+        ExcelRow { index: 0, cells }
+    }
+
+    #[test]
+    fn test_is_header() {
+        let header = row(vec![cell(CELL_STRING, "Name"), cell(CELL_STRING, "Age")]);
+        let data = row(vec![cell(CELL_STRING, "Tom"), cell(CELL_NUMBER, "3")]);
+        assert!(Sql::is_header(&[header.clone(), data.clone()]));
+
+        // 只有一行、包含数值、空白或重名时不是表头
+        assert!(!Sql::is_header(&[header.clone()]));
+        assert!(!Sql::is_header(&[data.clone(), data.clone()]));
+        assert!(!Sql::is_header(&[row(vec![cell(CELL_STRING, "Name"), cell(CELL_STRING, " ")]), data.clone()]));
+        assert!(!Sql::is_header(&[row(vec![cell(CELL_STRING, "Name"), cell(CELL_STRING, "name")]), data]));
+    }
+
+    #[test]
+    fn test_get_type() {
+        let rows = vec![
+            row(vec![cell(CELL_NUMBER, "1"), cell(CELL_NUMBER, "1"), cell(CELL_NUMBER, "1"), cell(CELL_EMPTY, "")]),
+            row(vec![cell(CELL_BOOL, "true"), cell(CELL_NUMBER, "1.5"), cell(CELL_STRING, "a")]),
+            row(vec![cell(CELL_EMPTY, "")]),
+        ];
+
+        assert_eq!(Sql::get_type(&rows, 0), TYPE_INTEGER);
+        assert_eq!(Sql::get_type(&rows, 1), TYPE_REAL);
+        assert_eq!(Sql::get_type(&rows, 2), TYPE_TEXT);
+        assert_eq!(Sql::get_type(&rows, 3), TYPE_TEXT);
+    }
+
+    #[test]
+    fn test_get_value() {
+        assert_eq!(Sql::get_value(&cell(CELL_EMPTY, ""), TYPE_INTEGER), Value::Null);
+        assert_eq!(Sql::get_value(&cell(CELL_NUMBER, "42"), TYPE_INTEGER), Value::Integer(42));
+        assert_eq!(Sql::get_value(&cell(CELL_NUMBER, "42"), TYPE_REAL), Value::Real(42.0));
+        assert_eq!(Sql::get_value(&cell(CELL_BOOL, "true"), TYPE_INTEGER), Value::Integer(1));
+        assert_eq!(Sql::get_value(&cell(CELL_BOOL, "false"), TYPE_REAL), Value::Real(0.0));
+        assert_eq!(Sql::get_value(&cell(CELL_NUMBER, "1"), TYPE_TEXT), Value::Text("1".to_string()));
+
+        // 采样之外与列类型不符的值
+        assert_eq!(Sql::get_value(&cell(CELL_NUMBER, "1.5"), TYPE_INTEGER), Value::Real(1.5));
+        assert_eq!(Sql::get_value(&cell(CELL_STRING, "n/a"), TYPE_INTEGER), Value::Text("n/a".to_string()));
+    }
+
+    #[test]
+    fn test_insert_row() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let tx = conn.transaction().unwrap();
+        let rows = vec![
+            row(vec![cell(CELL_STRING, "id"), cell(CELL_STRING, "name")]),
+            row(vec![cell(CELL_NUMBER, "1"), cell(CELL_STRING, "a")]),
+        ];
+        let mut table = Sql::create_table(&tx, "book.xlsx", "sheet1", rows).unwrap();
+        assert_eq!(table.columns, vec!["id", "name"]);
+        assert_eq!(table.types, vec![TYPE_INTEGER, TYPE_TEXT]);
+
+        // 超出采样列数时追加列
+        let wider = row(vec![cell(CELL_STRING, "x"), cell(CELL_STRING, "b"), cell(CELL_NUMBER, "2.5")]);
+        Sql::insert_row(&tx, &mut table, &wider).unwrap();
+        assert_eq!(table.columns, vec!["id", "name", "C"]);
+        tx.commit().unwrap();
+
+        let result = Sql::execute(&conn, "SELECT id, name, C FROM sheet1 ORDER BY rowid").unwrap();
+        let values: Vec<Vec<String>> = result.rows.iter().map(|row| row.cells.iter().map(|cell| cell.value.clone()).collect()).collect();
+        assert_eq!(values, vec![vec!["1", "a", ""], vec!["x", "b", "2.5"]]);
+    }
+
+    #[test]
+    fn test_get_identifiers() {
+        let sql = "SELECT a, \"My Sheet\" FROM sheet10 JOIN [Data] ON `x`.id = 1 -- sheet2\nWHERE b = 'sheet3' /* sheet4 */";
+        let identifiers = Sql::get_identifiers(sql);
+        for name in ["select", "a", "my sheet", "from", "sheet10", "data", "x", "id", "1", "where", "b"] {
+            assert!(identifiers.contains(name), "{}", name);
+        }
+
+        // 前缀、字符串、注释不算引用
+        for name in ["sheet1", "sheet2", "sheet3", "sheet4"] {
+            assert!(!identifiers.contains(name), "{}", name);
+        }
+
+        // 转义的引号
+        let identifiers = Sql::get_identifiers("SELECT * FROM \"a\"\"b\" WHERE c = 'it''s'");
+        assert!(identifiers.contains("a\"b"));
+        assert!(!identifiers.contains("s"));
+    }
+}
@@ -0,0 +1,615 @@
+//! 读取 xlsx 中 calamine 未提供的信息(单元格格式、布局等)
+
+use crate::analysis::number_format::NumberFormat;
+use crate::config::{
+    ExcelColumnLayout, ExcelMergedCell, ExcelRowLayout, ExcelSheetLayout, WorkbookChart, WorkbookDefinedName, WorkbookPivotTable, WorkbookProps,
+    WorkbookTable,
+};
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use calamine::Range;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+pub struct Xlsx;
+
+/// xlsx 压缩包, 保留文件路径用于错误信息
+pub struct XlsxArchive {
+    path: String,
+    archive: zip::ZipArchive<BufReader<File>>,
+}
+
+/// workbook 中的 sheet
+#[derive(Default, Debug, Clone)]
+pub struct XlsxSheet {
+    pub name: String,
+    // 压缩包中的路径, 如 `xl/worksheets/sheet1.xml`
+    pub path: String,
+    // visible、hidden、veryHidden
+    pub state: String,
+}
+
+/// workbook
+#[derive(Default, Debug, Clone)]
+pub struct XlsxWorkbook {
+    pub date1904: bool,
+    pub sheets: Vec<XlsxSheet>,
+    pub defined_names: Vec<WorkbookDefinedName>,
+}
+
+/// 关系
+#[derive(Default, Debug, Clone)]
+struct XlsxRelationship {
+    id: String,
+    // 关系类型, 如 `.../relationships/table`
+    kind: String,
+    // 压缩包中的路径
+    path: String,
+}
+
+/// 单元格格式、公式
+#[derive(Default, Debug, Clone)]
+pub struct CellFormats {
+    pub date1904: bool,
+    // calamine Range 左上角的绝对位置
+    pub origin: (u32, u32),
+    // 公式
+    pub formulas: Option<Range<String>>,
+    // 显示公式而不是值
+    pub show_formulas: bool,
+    // cellXfs 下标对应的格式代码
+    codes: Vec<String>,
+    // 绝对位置 (row, col) 对应的 cellXfs 下标, 只保存非常规格式
+    cells: HashMap<(u32, u32), u32>,
+}
+
+impl CellFormats {
+    /// 获取格式代码, `row`、`col` 为 Range 中的相对位置
+    pub fn get(&self, row: usize, col: usize) -> Option<&str> {
+        let key = (self.origin.0 + row as u32, self.origin.1 + col as u32);
+        let index = self.cells.get(&key)?;
+        self.codes.get(*index as usize).map(|code| code.as_str())
+    }
+
+    /// 获取公式, `row`、`col` 为 Range 中的相对位置
+    pub fn get_formula(&self, row: usize, col: usize) -> Option<&str> {
+        let formulas = self.formulas.as_ref()?;
+        let key = (self.origin.0 + row as u32, self.origin.1 + col as u32);
+        formulas.get_value(key).map(|formula| formula.as_str()).filter(|formula| !formula.is_empty())
+    }
+}
+
+const WORKBOOK_FILE: &str = "xl/workbook.xml";
+const WORKBOOK_RELS_FILE: &str = "xl/_rels/workbook.xml.rels";
+const STYLES_FILE: &str = "xl/styles.xml";
+
+// 默认列宽(字符数)、行高(磅)
+const DEFAULT_COLUMN_WIDTH: f32 = 8.43;
+const DEFAULT_ROW_HEIGHT: f32 = 15.0;
+
+// 单个 `<col>` 最多展开的列数
+const MAX_COLUMN_RANGE: u32 = 1024;
+
+// 可以读取的后缀
+const XLSX_SUFFIXES: [&str; 3] = ["xlsx", "xlsm", "xlam"];
+
+impl Xlsx {
+    /// 是否为 xlsx 格式
+    pub fn is_xlsx(suffix: &str) -> bool {
+        XLSX_SUFFIXES.contains(&suffix)
+    }
+
+    /// 打开压缩包
+    pub fn open(file_path: &str) -> Result<XlsxArchive, Error> {
+        let reader = FileUtils::read_file_buffer(file_path)?;
+        let archive = zip::ZipArchive::new(reader).map_err(|err| Error::corrupt_file(file_path, err))?;
+        Ok(XlsxArchive {
+            path: file_path.to_string(),
+            archive,
+        })
+    }
+
+    /// 读取 workbook, sheet 顺序与 calamine 一致
+    pub fn read_workbook(archive: &mut XlsxArchive) -> Result<XlsxWorkbook, Error> {
+        let relationships = Self::read_relationships(archive)?;
+        let content = Self::read_entry(archive, WORKBOOK_FILE)?;
+        let mut workbook = XlsxWorkbook::default();
+        let mut reader = quick_xml::Reader::from_str(&content);
+        // 正在读取的名称定义, localSheetId 暂存在 scope 中
+        let mut defined_name: Option<WorkbookDefinedName> = None;
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(&archive.path, err))? {
+                Event::Start(e) if e.local_name().as_ref() == b"definedName" => {
+                    defined_name = Some(WorkbookDefinedName {
+                        name: Self::get_attribute(&e, b"name").unwrap_or_default(),
+                        scope: Self::get_attribute(&e, b"localSheetId").unwrap_or_default(),
+                        hidden: Self::get_bool(&e, b"hidden"),
+                        ..WorkbookDefinedName::default()
+                    });
+                }
+                Event::Text(e) => {
+                    if let Some(defined_name) = defined_name.as_mut() {
+                        let text = e.unescape().map_err(|err| Error::corrupt_file(&archive.path, err))?;
+                        defined_name.range.push_str(&text);
+                    }
+                }
+                Event::End(e) if e.local_name().as_ref() == b"definedName" => {
+                    if let Some(mut defined_name) = defined_name.take() {
+                        defined_name.scope = defined_name
+                            .scope
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|index| workbook.sheets.get(index))
+                            .map(|sheet| sheet.name.clone())
+                            .unwrap_or_default();
+                        workbook.defined_names.push(defined_name);
+                    }
+                }
+                Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                    b"workbookPr" => {
+                        let date1904 = Self::get_attribute(&e, b"date1904").unwrap_or_default();
+                        workbook.date1904 = date1904 == "1" || date1904 == "true";
+                    }
+                    b"sheet" => {
+                        let id = Self::get_attribute(&e, b"r:id").unwrap_or_default();
+                        workbook.sheets.push(XlsxSheet {
+                            name: Self::get_attribute(&e, b"name").unwrap_or_default(),
+                            path: relationships.get(&id).cloned().unwrap_or_default(),
+                            state: Self::get_attribute(&e, b"state").unwrap_or("visible".to_string()),
+                        });
+                    }
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(workbook)
+    }
+
+    /// 读取单张 sheet 的单元格格式和布局, `origin` 为 calamine Range 左上角的绝对位置
+    pub fn read_sheet(file_path: &str, sheet_name: &str, origin: (u32, u32)) -> Result<(CellFormats, ExcelSheetLayout), Error> {
+        let mut archive = Self::open(file_path)?;
+        let workbook = Self::read_workbook(&mut archive)?;
+        let mut formats = CellFormats::default();
+        formats.date1904 = workbook.date1904;
+        formats.origin = origin;
+        formats.codes = Self::read_styles(&mut archive)?;
+
+        let mut layout = ExcelSheetLayout::default();
+        layout.row_offset = origin.0;
+        layout.col_offset = origin.1;
+        layout.default_column_width = DEFAULT_COLUMN_WIDTH;
+        layout.default_row_height = DEFAULT_ROW_HEIGHT;
+
+        let sheet = match workbook.sheets.iter().find(|sheet| sheet.name == sheet_name) {
+            Some(sheet) => sheet,
+            None => return Ok((formats, layout)),
+        };
+
+        layout.visibility = sheet.state.clone();
+        let general: Vec<bool> = formats.codes.iter().map(|code| NumberFormat::is_general(code)).collect();
+        let file = archive.archive.by_name(&sheet.path).map_err(|err| Error::corrupt_file(file_path, err))?;
+        let mut reader = quick_xml::Reader::from_reader(BufReader::new(file));
+        let mut buffer: Vec<u8> = Vec::new();
+
+        loop {
+            match reader.read_event_into(&mut buffer).map_err(|err| Error::corrupt_file(file_path, err))? {
+                Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                    b"c" => {
+                        let style = Self::get_attribute(&e, b"s").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0);
+                        let is_general = general.get(style as usize).copied().unwrap_or(true);
+                        if !is_general {
+                            if let Some(position) = Self::get_attribute(&e, b"r").and_then(|r| Self::parse_reference(&r)) {
+                                formats.cells.insert(position, style);
+                            }
+                        }
+                    }
+                    b"row" => Self::read_row_layout(&e, &mut layout),
+                    b"col" => Self::read_column_layout(&e, &mut layout),
+                    b"sheetFormatPr" => {
+                        if let Some(width) = Self::get_float(&e, b"defaultColWidth").or(Self::get_float(&e, b"baseColWidth")) {
+                            layout.default_column_width = width;
+                        }
+
+                        if let Some(height) = Self::get_float(&e, b"defaultRowHeight") {
+                            layout.default_row_height = height;
+                        }
+                    }
+                    b"pane" => {
+                        // 只处理冻结窗格, 拆分窗格的 xSplit、ySplit 单位为 1/20 磅
+                        let state = Self::get_attribute(&e, b"state").unwrap_or_default();
+                        if state == "frozen" || state == "frozenSplit" {
+                            let rows = Self::get_float(&e, b"ySplit").unwrap_or(0.0) as u32;
+                            let columns = Self::get_float(&e, b"xSplit").unwrap_or(0.0) as u32;
+                            layout.frozen_rows = rows.saturating_sub(origin.0);
+                            layout.frozen_columns = columns.saturating_sub(origin.1);
+                        }
+                    }
+                    b"mergeCell" => {
+                        let reference = Self::get_attribute(&e, b"ref").unwrap_or_default();
+                        if let Some(merged) = Self::parse_merged(&reference, origin) {
+                            layout.merged.push(merged);
+                        }
+                    }
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+
+            buffer.clear();
+        }
+
+        Ok((formats, layout))
+    }
+
+    /// 行高、隐藏行, 只保存自定义的行
+    fn read_row_layout(e: &BytesStart, layout: &mut ExcelSheetLayout) {
+        let hidden = Self::get_bool(e, b"hidden");
+        let custom_height = Self::get_bool(e, b"customHeight");
+        if !hidden && !custom_height {
+            return;
+        }
+
+        let row = match Self::get_attribute(e, b"r").and_then(|r| r.parse::<u32>().ok()) {
+            Some(row) if row > layout.row_offset => row - 1 - layout.row_offset,
+            _ => return,
+        };
+
+        layout.rows.push(ExcelRowLayout {
+            index: row as usize,
+            height: Self::get_float(e, b"ht").unwrap_or(layout.default_row_height),
+            hidden,
+        });
+    }
+
+    /// 列宽、隐藏列, `min`、`max` 为从 1 开始的列范围
+    fn read_column_layout(e: &BytesStart, layout: &mut ExcelSheetLayout) {
+        let min = Self::get_attribute(e, b"min").and_then(|min| min.parse::<u32>().ok()).unwrap_or(1).max(1);
+        let max = Self::get_attribute(e, b"max").and_then(|max| max.parse::<u32>().ok()).unwrap_or(min);
+        let width = Self::get_float(e, b"width").unwrap_or(layout.default_column_width);
+        let hidden = Self::get_bool(e, b"hidden");
+
+        // 整行样式常常覆盖到最后一列, 只保留合理的范围
+        let max = max.min(min.saturating_add(MAX_COLUMN_RANGE));
+        for col in min..=max {
+            if col - 1 < layout.col_offset {
+                continue;
+            }
+
+            layout.columns.push(ExcelColumnLayout {
+                index: (col - 1 - layout.col_offset) as usize,
+                width,
+                hidden,
+            });
+        }
+    }
+
+    /// `A1:C2` => 合并单元格
+    fn parse_merged(reference: &str, origin: (u32, u32)) -> Option<ExcelMergedCell> {
+        let (start, end) = reference.split_once(':')?;
+        let (row_start, col_start) = Self::parse_reference(start)?;
+        let (row_end, col_end) = Self::parse_reference(end)?;
+        if row_end < origin.0 || col_end < origin.1 {
+            return None;
+        }
+
+        Some(ExcelMergedCell {
+            row_start: row_start.saturating_sub(origin.0) as usize,
+            row_end: (row_end - origin.0) as usize,
+            col_start: col_start.saturating_sub(origin.1) as usize,
+            col_end: (col_end - origin.1) as usize,
+        })
+    }
+
+    /// 读取 cellXfs 对应的格式代码
+    fn read_styles(archive: &mut XlsxArchive) -> Result<Vec<String>, Error> {
+        let content = match Self::read_entry(archive, STYLES_FILE) {
+            Ok(content) => content,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let mut custom: HashMap<u32, String> = HashMap::new();
+        let mut codes: Vec<String> = Vec::new();
+        let mut in_cell_xfs = false;
+        let mut reader = quick_xml::Reader::from_str(&content);
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(&archive.path, err))? {
+                Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                    b"numFmt" => {
+                        let id = Self::get_attribute(&e, b"numFmtId").and_then(|id| id.parse::<u32>().ok());
+                        let code = Self::get_attribute(&e, b"formatCode");
+                        if let (Some(id), Some(code)) = (id, code) {
+                            custom.insert(id, code);
+                        }
+                    }
+                    b"cellXfs" => in_cell_xfs = true,
+                    b"xf" if in_cell_xfs => {
+                        let id = Self::get_attribute(&e, b"numFmtId").and_then(|id| id.parse::<u32>().ok()).unwrap_or(0);
+                        let code = custom
+                            .get(&id)
+                            .cloned()
+                            .or(NumberFormat::get_builtin(id).map(|code| code.to_string()))
+                            .unwrap_or_default();
+                        codes.push(code);
+                    }
+                    _ => {}
+                },
+                Event::End(e) if e.local_name().as_ref() == b"cellXfs" => in_cell_xfs = false,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(codes)
+    }
+
+    /// 读取 workbook 关系, 返回 id => 压缩包中的路径
+    fn read_relationships(archive: &mut XlsxArchive) -> Result<HashMap<String, String>, Error> {
+        let relationships = Self::read_part_relationships(archive, WORKBOOK_FILE)?;
+        Ok(relationships.into_iter().map(|relationship| (relationship.id, relationship.path)).collect())
+    }
+
+    /// 读取部件的关系, 如 `xl/worksheets/sheet1.xml` 对应 `xl/worksheets/_rels/sheet1.xml.rels`, 不存在时返回空
+    fn read_part_relationships(archive: &mut XlsxArchive, part: &str) -> Result<Vec<XlsxRelationship>, Error> {
+        let (dir, name) = part.rsplit_once('/').unwrap_or(("", part));
+        let rels_path = if dir.is_empty() {
+            format!("_rels/{}.rels", name)
+        } else {
+            format!("{}/_rels/{}.rels", dir, name)
+        };
+
+        if archive.archive.by_name(&rels_path).is_err() {
+            return Ok(Vec::new());
+        }
+
+        let content = Self::read_entry(archive, &rels_path)?;
+        let mut relationships: Vec<XlsxRelationship> = Vec::new();
+        let mut reader = quick_xml::Reader::from_str(&content);
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(&archive.path, err))? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                    // 外部链接不在压缩包中
+                    if Self::get_attribute(&e, b"TargetMode").as_deref() == Some("External") {
+                        continue;
+                    }
+
+                    let target = Self::get_attribute(&e, b"Target").unwrap_or_default();
+                    relationships.push(XlsxRelationship {
+                        id: Self::get_attribute(&e, b"Id").unwrap_or_default(),
+                        kind: Self::get_attribute(&e, b"Type").unwrap_or_default(),
+                        path: Self::resolve_path(dir, &target),
+                    });
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(relationships)
+    }
+
+    /// 相对路径转换为压缩包中的路径
+    fn resolve_path(dir: &str, target: &str) -> String {
+        if let Some(path) = target.strip_prefix('/') {
+            return path.to_string();
+        }
+
+        let mut parts: Vec<&str> = dir.split('/').filter(|part| !part.is_empty()).collect();
+        for part in target.split('/') {
+            match part {
+                ".." => {
+                    parts.pop();
+                }
+                "." | "" => {}
+                _ => parts.push(part),
+            }
+        }
+
+        parts.join("/")
+    }
+
+    /// 读取名称定义、表格、数据透视表和图表, sheet 列表由 calamine 提供
+    pub fn read_workbook_props(file_path: &str) -> Result<WorkbookProps, Error> {
+        let mut archive = Self::open(file_path)?;
+        let workbook = Self::read_workbook(&mut archive)?;
+        let mut props = WorkbookProps::default();
+        props.defined_names = workbook.defined_names.clone();
+
+        for sheet in workbook.sheets.iter() {
+            for relationship in Self::read_part_relationships(&mut archive, &sheet.path)? {
+                let kind = relationship.kind.rsplit('/').next().unwrap_or_default();
+                match kind {
+                    "table" => props.tables.push(Self::read_table(&mut archive, &relationship.path, &sheet.name)?),
+                    "pivotTable" => props.pivot_tables.push(Self::read_pivot_table(&mut archive, &relationship.path, &sheet.name)?),
+                    "drawing" => {
+                        for chart in Self::read_part_relationships(&mut archive, &relationship.path)? {
+                            if chart.kind.ends_with("/chart") {
+                                let title = Self::read_chart_title(&mut archive, &chart.path)?;
+                                props.charts.push(WorkbookChart {
+                                    sheet: sheet.name.clone(),
+                                    title,
+                                });
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(props)
+    }
+
+    fn read_table(archive: &mut XlsxArchive, path: &str, sheet_name: &str) -> Result<WorkbookTable, Error> {
+        let content = Self::read_entry(archive, path)?;
+        let mut reader = quick_xml::Reader::from_str(&content);
+        let mut table = WorkbookTable::default();
+        table.sheet = sheet_name.to_string();
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(&archive.path, err))? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"table" => {
+                    table.name = Self::get_attribute(&e, b"displayName")
+                        .or(Self::get_attribute(&e, b"name"))
+                        .unwrap_or_default();
+                    table.range = Self::get_attribute(&e, b"ref").unwrap_or_default();
+                    break;
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(table)
+    }
+
+    fn read_pivot_table(archive: &mut XlsxArchive, path: &str, sheet_name: &str) -> Result<WorkbookPivotTable, Error> {
+        let content = Self::read_entry(archive, path)?;
+        let mut reader = quick_xml::Reader::from_str(&content);
+        let mut pivot_table = WorkbookPivotTable::default();
+        pivot_table.sheet = sheet_name.to_string();
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(&archive.path, err))? {
+                Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                    b"pivotTableDefinition" => pivot_table.name = Self::get_attribute(&e, b"name").unwrap_or_default(),
+                    b"location" => pivot_table.range = Self::get_attribute(&e, b"ref").unwrap_or_default(),
+                    b"dataField" => pivot_table.data_fields.push(Self::get_attribute(&e, b"name").unwrap_or_default()),
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(pivot_table)
+    }
+
+    /// 图表标题, 取第一个 `title` 中的文本
+    fn read_chart_title(archive: &mut XlsxArchive, path: &str) -> Result<String, Error> {
+        let content = Self::read_entry(archive, path)?;
+        let mut reader = quick_xml::Reader::from_str(&content);
+        let mut title = String::new();
+        let mut in_title = false;
+        let mut in_text = false;
+
+        loop {
+            match reader.read_event().map_err(|err| Error::corrupt_file(&archive.path, err))? {
+                Event::Start(e) => match e.local_name().as_ref() {
+                    b"title" => in_title = true,
+                    b"t" if in_title => in_text = true,
+                    _ => {}
+                },
+                Event::Text(e) if in_text => {
+                    let text = e.unescape().map_err(|err| Error::corrupt_file(&archive.path, err))?;
+                    title.push_str(&text);
+                }
+                Event::End(e) => match e.local_name().as_ref() {
+                    b"title" => break,
+                    b"t" => in_text = false,
+                    _ => {}
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(title)
+    }
+
+    /// 读取压缩包中的文件
+    pub fn read_entry(archive: &mut XlsxArchive, name: &str) -> Result<String, Error> {
+        let mut file = archive.archive.by_name(name).map_err(|err| Error::corrupt_file(&archive.path, err))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|err| Error::corrupt_file(&archive.path, err))?;
+        Ok(content)
+    }
+
+    /// `AB12` => (11, 27), 从 0 开始
+    pub fn parse_reference(reference: &str) -> Option<(u32, u32)> {
+        let mut col: u32 = 0;
+        let mut row = String::new();
+        for c in reference.chars() {
+            if c == '$' {
+                continue;
+            }
+
+            if c.is_ascii_alphabetic() {
+                // 异常的引用可能溢出
+                col = col.checked_mul(26)?.checked_add(c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)?;
+            } else {
+                row.push(c);
+            }
+        }
+
+        let row = row.parse::<u32>().ok()?;
+        if row == 0 || col == 0 {
+            return None;
+        }
+
+        Some((row - 1, col - 1))
+    }
+
+    /// 列号转列名, 0 => A, 26 => AA
+    pub fn get_column_name(column: usize) -> String {
+        let mut name = String::new();
+        let mut index = column + 1;
+        while index > 0 {
+            let remainder = (index - 1) % 26;
+            name.insert(0, (b'A' + remainder as u8) as char);
+            index = (index - 1) / 26;
+        }
+
+        name
+    }
+
+    fn get_float(e: &BytesStart, name: &[u8]) -> Option<f32> {
+        Self::get_attribute(e, name).and_then(|value| value.parse::<f32>().ok())
+    }
+
+    fn get_bool(e: &BytesStart, name: &[u8]) -> bool {
+        let value = Self::get_attribute(e, name).unwrap_or_default();
+        value == "1" || value == "true"
+    }
+
+    /// 获取属性
+    pub fn get_attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+        let attribute = e.try_get_attribute(name).ok().flatten()?;
+        let value = attribute.unescape_value().ok()?;
+        Some(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reference() {
+        assert_eq!(Xlsx::parse_reference("A1"), Some((0, 0)));
+        assert_eq!(Xlsx::parse_reference("AB12"), Some((11, 27)));
+        assert_eq!(Xlsx::parse_reference("$C$3"), Some((2, 2)));
+        assert_eq!(Xlsx::parse_reference("XFD1048576"), Some((1048575, 16383)));
+        assert_eq!(Xlsx::parse_reference("A0"), None);
+        assert_eq!(Xlsx::parse_reference("12"), None);
+        assert_eq!(Xlsx::parse_reference("ZZZZZZZZZZZZ1"), None);
+        assert_eq!(Xlsx::parse_reference("A99999999999"), None);
+    }
+
+    #[test]
+    fn test_get_column_name() {
+        assert_eq!(Xlsx::get_column_name(0), "A");
+        assert_eq!(Xlsx::get_column_name(25), "Z");
+        assert_eq!(Xlsx::get_column_name(26), "AA");
+        assert_eq!(Xlsx::get_column_name(16383), "XFD");
+    }
+}
@@ -0,0 +1,130 @@
+//! 预览清单(preview.json), 同一文档的读写串行执行, 写入时先写临时文件再重命名
+
+use crate::config::PREVIEW_FILE;
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use log::info;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+pub struct Manifest;
+
+// 每个文档目录一把锁
+static LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceLock::new();
+
+impl Manifest {
+    /// 清单路径
+    pub fn get_path(temp_dir: &Path) -> PathBuf {
+        temp_dir.join(PREVIEW_FILE)
+    }
+
+    /// 读取清单, 不存在或为空时返回 None
+    pub fn read<T: DeserializeOwned>(temp_dir: &Path) -> Result<Option<T>, Error> {
+        let lock = Self::get_lock(temp_dir);
+        let _guard = lock.lock().unwrap_or_else(|err| err.into_inner());
+        Self::read_unlocked(temp_dir)
+    }
+
+    /// 写入清单
+    pub fn write<T: Serialize>(temp_dir: &Path, value: &T) -> Result<(), Error> {
+        let lock = Self::get_lock(temp_dir);
+        let _guard = lock.lock().unwrap_or_else(|err| err.into_inner());
+        Self::write_unlocked(temp_dir, value)
+    }
+
+    /// 读取、修改并写回清单, 整个过程持有锁
+    pub fn update<T, F>(temp_dir: &Path, f: F) -> Result<(), Error>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T),
+    {
+        let lock = Self::get_lock(temp_dir);
+        let _guard = lock.lock().unwrap_or_else(|err| err.into_inner());
+        let mut value: T = Self::read_unlocked(temp_dir)?.unwrap_or_default();
+        f(&mut value);
+        Self::write_unlocked(temp_dir, &value)
+    }
+
+    fn get_lock(temp_dir: &Path) -> Arc<Mutex<()>> {
+        let locks = LOCKS.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut locks = locks.lock().unwrap_or_else(|err| err.into_inner());
+        locks.entry(temp_dir.to_path_buf()).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
+    }
+
+    fn read_unlocked<T: DeserializeOwned>(temp_dir: &Path) -> Result<Option<T>, Error> {
+        let path = Self::get_path(temp_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = FileUtils::read_file_string(&path.to_string_lossy().to_string())?;
+        if content.is_empty() {
+            return Ok(None);
+        }
+
+        let value = serde_json::from_str(&content).map_err(|err| Error::corrupt_file(&path.to_string_lossy(), err))?;
+        Ok(Some(value))
+    }
+
+    fn write_unlocked<T: Serialize>(temp_dir: &Path, value: &T) -> Result<(), Error> {
+        let path = Self::get_path(temp_dir);
+        let file_path = path.to_string_lossy().to_string();
+        let content = serde_json::to_string_pretty(value).map_err(|err| Error::Error(err.to_string()))?;
+        FileUtils::write_to_file_atomic(&file_path, &content)?;
+        info!("write `{}` success !", &file_path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExcelSheet;
+    use std::fs;
+    use std::thread;
+
+    #[test]
+    fn test_parallel_update() {
+        let temp_dir = std::env::temp_dir().join(format!("manifest-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let count: u32 = 32;
+        let handles: Vec<_> = (1..=count)
+            .map(|index| {
+                let temp_dir = temp_dir.clone();
+                thread::spawn(move || {
+                    Manifest::update(&temp_dir, |sheets: &mut Vec<ExcelSheet>| {
+                        sheets.push(ExcelSheet {
+                            name: format!("Sheet{}", index),
+                            index,
+                            ..Default::default()
+                        });
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let sheets: Vec<ExcelSheet> = Manifest::read(&temp_dir).unwrap().unwrap();
+        let mut indexes: Vec<u32> = sheets.iter().map(|sheet| sheet.index).collect();
+        indexes.sort();
+        assert_eq!(indexes, (1..=count).collect::<Vec<u32>>());
+
+        // 临时文件都已重命名
+        let temp_files = fs::read_dir(&temp_dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(temp_files, 0);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
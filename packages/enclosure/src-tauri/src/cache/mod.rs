@@ -1,6 +1,8 @@
 //! cache
 
-use crate::config::{FileProps, History, HISTORY_COUNT, HISTORY_FILE};
+pub mod manifest;
+
+use crate::config::{FileProps, History, Settings, HISTORY_COUNT, HISTORY_FILE, SETTINGS_FILE};
 use crate::error::Error;
 use crate::system::menu::FILE_RECENT_FILES_ID;
 use crate::utils::file::FileUtils;
@@ -11,7 +13,7 @@ pub struct Cache;
 
 impl Cache {
     /// 读取历史记录
-    pub fn read_history() -> Result<(String, Vec<History>), String> {
+    pub fn read_history() -> Result<(String, Vec<History>), Error> {
         let path = FileUtils::create_temp_dir("", false)?;
         let path = path.join(HISTORY_FILE);
         let file_path = path.as_path().to_string_lossy().to_string();
@@ -23,7 +25,7 @@ impl Cache {
             info!("history found ...");
             let content = FileUtils::read_file_string(&file_path)?;
             if !content.is_empty() {
-                contents = serde_json::from_str(&content).map_err(|err| Error::Error(err.to_string()).to_string())?;
+                contents = serde_json::from_str(&content).map_err(|err| Error::corrupt_file(&file_path, err))?;
             }
         } else {
             info!("no history found ...");
@@ -32,8 +34,28 @@ impl Cache {
         Ok((file_path, contents))
     }
 
+    /// 读取设置, 文件不存在时使用默认值
+    pub fn read_settings() -> Result<Settings, Error> {
+        let path = FileUtils::create_temp_dir("", false)?;
+        let path = path.join(SETTINGS_FILE);
+        let file_path = path.as_path().to_string_lossy().to_string();
+        info!("settings file path: {}", &file_path);
+
+        if !path.exists() {
+            info!("no settings found ...");
+            return Ok(Settings::default());
+        }
+
+        let content = FileUtils::read_file_string(&file_path)?;
+        if content.trim().is_empty() {
+            return Ok(Settings::default());
+        }
+
+        serde_json::from_str(&content).map_err(|err| Error::corrupt_file(&file_path, err))
+    }
+
     /// 保存历史记录
-    pub fn save_history(file_props: &FileProps) -> Result<(), String> {
+    pub fn save_history(file_props: &FileProps) -> Result<(), Error> {
         info!("save history ...");
         // 存储历史记录
         let (file_path, mut contents) = Cache::read_history()?;
@@ -1,16 +1,25 @@
 //! configs
 
+use crate::error::Error;
+use crate::job::Job;
 use crate::prepare::HttpResponseData;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// 图片后缀
 pub const IMAGE_SUFFIXES: [&str; 11] = ["jpeg", "jpg", "png", "gif", "tiff", "tif", "webp", "ico", "heic", "bmp", "svg"];
 
 /// 文档后缀
-pub const DOCUMENT_SUFFIXES: [&str; 5] = ["pdf", "doc", "docx", "ppt", "pptx"];
+pub const DOCUMENT_SUFFIXES: [&str; 13] = ["pdf", "doc", "docx", "ppt", "pptx", "epub", "xps", "oxps", "cbz", "fb2", "odt", "odp", "rtf"];
 
-pub const EXCEL_SUFFIXES: [&str; 6] = ["xls", "xlsx", "xlsm", "xlsb", "xla", "xlam"];
+/// mupdf 可直接打开的文档后缀
+pub const MUPDF_SUFFIXES: [&str; 6] = ["pdf", "epub", "xps", "oxps", "cbz", "fb2"];
+
+pub const EXCEL_SUFFIXES: [&str; 7] = ["xls", "xlsx", "xlsm", "xlsb", "xla", "xlam", "ods"];
+
+/// 分隔符文本后缀, 与 excel 共用分块
+pub const DELIMITED_SUFFIXES: [&str; 3] = ["csv", "tsv", "psv"];
 
 /// 压缩包后缀
 pub const ARCHIVE_SUFFIXES: [&str; 10] = ["zip", "bz2", "gz", "zlib", "tar", "rar", "7z", "tar.xz", "xz", "tgz"];
@@ -24,9 +33,30 @@ pub const HISTORY_FILE: &str = "history";
 // history count
 pub const HISTORY_COUNT: usize = 30;
 
-// 最大异步线程数
+// 加密文档需要密码
+pub const PASSWORD_REQUIRED_CODE: u16 = 401;
+
+// 文档缩略图事件
+pub const DOCUMENT_THUMBNAIL_EVENT: &str = "document_thumbnail";
+
+// excel sheet 进度事件
+pub const EXCEL_SHEET_EVENT: &str = "excel_sheet";
+
+// workbook 搜索事件
+pub const WORKBOOK_SEARCH_EVENT: &str = "workbook_search";
+
+// sheet 导出进度事件
+pub const EXPORT_SHEET_EVENT: &str = "export_sheet";
+
+// 默认同时执行的任务块数
 pub const MAX_ASYNC_TASK_COUNT: usize = 10;
 
+// 默认单个任务同时执行的任务块数
+pub const MAX_JOB_TASK_COUNT: usize = 4;
+
+// 设置, 不随临时目录清理
+pub const SETTINGS_FILE: &str = "settings.json";
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct History {
     pub id: String,
@@ -48,6 +78,9 @@ pub struct FileProps {
     pub key: String,
     pub name: String,
     pub suffix: String,
+    // 根据文件头识别的 mime
+    #[serde(default)]
+    pub mime: String,
     pub prefix: String,
     pub path: String,
     #[serde(rename = "fullPath")]
@@ -72,12 +105,203 @@ pub struct HttpResponse {
     #[serde(rename = "fileProps")]
     pub(crate) file_props: FileProps,
     pub(crate) error: String,
+    // 错误码, 前端根据错误码和参数显示本地化的提示
+    #[serde(rename = "errorCode", default)]
+    pub(crate) error_code: String,
+    #[serde(rename = "errorParams", default)]
+    pub(crate) error_params: HashMap<String, String>,
     #[serde(rename = "suffixProps")]
     pub(crate) suffix_props: SuffixProps,
+    #[serde(rename = "documentProps", default)]
+    pub(crate) document_props: DocumentProps,
+    #[serde(rename = "workbookProps", default)]
+    pub(crate) workbook_props: WorkbookProps,
+    // 任务 id, 用于取消
+    #[serde(rename = "jobId", default)]
+    pub(crate) job_id: String,
+    // 请求参数, 不写入 json 文件
+    #[serde(skip)]
+    pub(crate) options: RequestOptions,
 }
 
 impl HttpResponseData for HttpResponse {}
 
+impl HttpResponse {
+    /// 设置错误信息、错误码和参数
+    pub fn set_error(&mut self, error: Error) {
+        if let Error::PasswordRequired { .. } = error {
+            self.code = PASSWORD_REQUIRED_CODE;
+        }
+
+        self.error = error.to_string();
+        self.error_code = error.code().to_string();
+        self.error_params = error.params();
+    }
+}
+
+/// 请求参数
+#[derive(Default, Debug, Clone)]
+pub struct RequestOptions {
+    // 加密文档密码
+    pub password: Option<String>,
+    // 可重排文档(epub 等)页面宽度
+    pub page_width: Option<f32>,
+    // 可重排文档页面高度
+    pub page_height: Option<f32>,
+    // 可重排文档字体大小
+    pub font_size: Option<f32>,
+    // 用于向前端发送事件
+    pub app: Option<tauri::AppHandle>,
+    // excel 显示公式而不是值
+    pub show_formulas: bool,
+    // csv 等第一行是否为表头, 为空时自动检测
+    pub has_header: Option<bool>,
+    // 当前任务, 用于取消
+    pub job: Option<Arc<Job>>,
+}
+
+impl RequestOptions {
+    /// 是否设置了重排参数
+    pub fn has_layout(&self) -> bool {
+        self.page_width.is_some() || self.page_height.is_some() || self.font_size.is_some()
+    }
+
+    /// 重排参数组成的目录后缀, 未设置时为空
+    pub fn get_layout_key(&self) -> Option<String> {
+        if !self.has_layout() {
+            return None;
+        }
+
+        let get_value = |value: Option<f32>| value.map(|value| value.to_string()).unwrap_or_default();
+        Some(format!("{}x{}-{}", get_value(self.page_width), get_value(self.page_height), get_value(self.font_size)))
+    }
+
+    /// 任务是否已取消
+    pub fn is_cancelled(&self) -> bool {
+        self.job.as_ref().map(|job| job.is_cancelled()).unwrap_or(false)
+    }
+
+    /// 检查点, 任务已取消时返回错误
+    pub fn check_cancelled(&self) -> Result<(), Error> {
+        match &self.job {
+            Some(job) => job.check(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentProps {
+    pub outlines: Vec<DocumentOutline>,
+    pub attachments: Vec<DocumentAttachment>,
+    pub fields: Vec<DocumentField>,
+    pub annotations: Vec<DocumentAnnotation>,
+}
+
+/// workbook 结构, 用于检查隐藏内容
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkbookProps {
+    pub sheets: Vec<WorkbookSheet>,
+    pub defined_names: Vec<WorkbookDefinedName>,
+    pub has_vba: bool,
+    pub tables: Vec<WorkbookTable>,
+    pub pivot_tables: Vec<WorkbookPivotTable>,
+    pub charts: Vec<WorkbookChart>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkbookSheet {
+    pub name: String,
+    pub index: u32,
+    // visible、hidden、veryHidden
+    pub visibility: String,
+    // worksheet、chartsheet、dialogsheet、macrosheet、vba
+    pub kind: String,
+}
+
+/// 名称定义
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkbookDefinedName {
+    pub name: String,
+    // 引用的范围或公式, 如 `Sheet1!$A$1:$B$2`
+    pub range: String,
+    // 作用域为 sheet 时为 sheet 名称, 否则为空
+    pub scope: String,
+    pub hidden: bool,
+}
+
+/// 表格
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkbookTable {
+    pub name: String,
+    pub sheet: String,
+    pub range: String,
+}
+
+/// 数据透视表
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkbookPivotTable {
+    pub name: String,
+    pub sheet: String,
+    pub range: String,
+    // 值字段名称
+    pub data_fields: Vec<String>,
+}
+
+/// 图表
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkbookChart {
+    pub sheet: String,
+    pub title: String,
+}
+
+/// 文档目录
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentOutline {
+    pub title: String,
+    pub page: Option<u32>,
+    pub uri: String,
+    pub children: Vec<DocumentOutline>,
+}
+
+/// 文档缩略图
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentThumbnail {
+    pub hash: String,
+    pub index: usize,
+    pub count: usize,
+    pub content: String,
+    pub finished: bool,
+    pub error: String,
+}
+
+/// 文档附件
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAttachment {
+    pub name: String,
+    pub description: String,
+    pub size: String,
+}
+
+/// 表单字段
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentField {
+    pub name: String,
+    pub kind: String,
+    pub value: String,
+}
+
+/// 批注
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAnnotation {
+    pub kind: String,
+    pub author: String,
+    pub content: String,
+    pub modified: String,
+    pub page: u32,
+    pub rect: Vec<f32>,
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct ExcelResponse {
   pub sheets: Vec<ExcelSheet>
@@ -89,7 +313,96 @@ pub struct ExcelSheet {
     pub index: u32,
     pub rows_count: usize,
     pub cells_count: usize,
-    pub metadata: Vec<ExcelSheetMetadata>
+    pub metadata: Vec<ExcelSheetMetadata>,
+    #[serde(default)]
+    pub layout: ExcelSheetLayout,
+    // csv 等的表头, 不计入行
+    #[serde(default)]
+    pub header: Vec<String>,
+    // 列统计, sheet 处理完成后生成
+    #[serde(default)]
+    pub profile: Vec<ExcelColumnProfile>,
+}
+
+/// 列统计
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelColumnProfile {
+    pub index: usize,
+    // 表头, 没有表头时为列名 A、B、C ...
+    pub name: String,
+    // 非空值的类型一致时为该类型, 否则为 mixed, 全部为空时为 empty
+    pub kind: String,
+    pub count: usize,
+    pub empty_count: usize,
+    pub distinct_count: usize,
+    // 不同值过多时 distinct_count、top_values 为近似值
+    pub approximate: bool,
+    pub min: String,
+    pub max: String,
+    pub mean: Option<f64>,
+    pub top_values: Vec<ExcelValueCount>,
+    // 数值列的等宽直方图
+    pub histogram: Vec<ExcelHistogramBin>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelHistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// sheet 布局, 行列索引与 `ExcelRow.index`、`ExcelCell.cell_index` 一致
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelSheetLayout {
+    // visible、hidden、veryHidden
+    pub visibility: String,
+    // 第一个单元格在 sheet 中的绝对位置(从 0 开始)
+    pub row_offset: u32,
+    pub col_offset: u32,
+    pub merged: Vec<ExcelMergedCell>,
+    pub columns: Vec<ExcelColumnLayout>,
+    pub rows: Vec<ExcelRowLayout>,
+    // 默认列宽(字符数)
+    pub default_column_width: f32,
+    // 默认行高(磅)
+    pub default_row_height: f32,
+    // 冻结的行数、列数
+    pub frozen_rows: u32,
+    pub frozen_columns: u32,
+}
+
+/// 合并单元格
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelMergedCell {
+    pub row_start: usize,
+    pub row_end: usize,
+    pub col_start: usize,
+    pub col_end: usize,
+}
+
+/// 列宽、隐藏列
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelColumnLayout {
+    pub index: usize,
+    // 字符数
+    pub width: f32,
+    pub hidden: bool,
+}
+
+/// 行高、隐藏行
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelRowLayout {
+    pub index: usize,
+    // 磅
+    pub height: f32,
+    pub hidden: bool,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -97,18 +410,180 @@ pub struct ExcelSheetMetadata {
     pub name: String,
     pub row_start: usize,
     pub row_end: usize,
-    pub task_id: usize
+    pub task_id: usize,
+    // 块文件名
+    #[serde(default)]
+    pub file: String,
+}
+
+/// sheet 进度事件
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExcelSheetEvent {
+    // started、progress、chunk、finished、failed
+    pub kind: String,
+    pub path: String,
+    pub sheet: ExcelSheet,
+    pub rows_processed: usize,
+    pub error: String,
+}
+
+/// 搜索结果
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub sheet: String,
+    pub sheet_index: u32,
+    pub row: usize,
+    pub column: usize,
+    pub value: String,
+    // 同一行相邻单元格的值
+    pub context: Vec<String>,
+}
+
+/// 搜索事件
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SearchEvent {
+    pub id: String,
+    // match、finished、cancelled、failed
+    pub kind: String,
+    pub matches: Vec<SearchMatch>,
+    // 已找到的总数
+    pub total: usize,
+    pub error: String,
+}
+
+/// sheet 查询条件
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SheetQuery {
+    #[serde(default)]
+    pub sorts: Vec<SheetSort>,
+    #[serde(default)]
+    pub filters: Vec<SheetFilter>,
+    #[serde(default)]
+    pub start: usize,
+    #[serde(default)]
+    pub count: usize,
+}
+
+/// 排序, 按数组顺序依次比较
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SheetSort {
+    pub column: usize,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// 过滤, 多个条件之间为且
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SheetFilter {
+    pub column: usize,
+    // equals、contains、range、empty、not_empty
+    pub operator: String,
+    #[serde(default)]
+    pub value: String,
+    // range 的上下界, 日期为序列号
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+/// sheet 查询结果
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SheetQueryResult {
+    // 满足过滤条件的总行数
+    pub total: usize,
+    pub rows: Vec<ExcelRow>,
+}
+
+/// 导出范围, 从 0 开始且包含两端, 为空时不限制
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SheetRange {
+    #[serde(default)]
+    pub row_start: Option<usize>,
+    #[serde(default)]
+    pub row_end: Option<usize>,
+    #[serde(default)]
+    pub col_start: Option<usize>,
+    #[serde(default)]
+    pub col_end: Option<usize>,
+}
+
+/// 导出进度事件
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ExportEvent {
+    // started、progress、finished、failed
+    pub kind: String,
+    pub path: String,
+    pub output_path: String,
+    pub rows_written: usize,
+    pub error: String,
+}
+
+/// sql 查询结果
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SqlQueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<ExcelRow>,
+    // 超过最大行数时截断
+    pub truncated: bool,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct ExcelRow {
+    // 整张 sheet 中的行索引, 从 0 开始
     pub index: usize,
     pub cells: Vec<ExcelCell>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct ExcelCell {
+    // 按单元格格式显示的值
     pub value: String,
+    // empty、string、number、bool、date、duration、error
+    #[serde(rename = "type")]
+    pub kind: String,
+    // 原始值, 日期为序列号
+    pub raw: String,
+    // 公式, 不含 `=`
+    #[serde(default)]
+    pub formula: String,
     pub row_index: usize,
     pub cell_index: usize,
 }
+
+/// 设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // 同时执行的任务块数
+    #[serde(rename = "maxTasks")]
+    pub max_tasks: usize,
+    // 单个任务同时执行的任务块数
+    #[serde(rename = "maxJobTasks")]
+    pub max_job_tasks: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_tasks: MAX_ASYNC_TASK_COUNT,
+            max_job_tasks: MAX_JOB_TASK_COUNT,
+        }
+    }
+}
+
+/// 任务信息
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: String,
+    // process、search、export
+    pub kind: String,
+    pub path: String,
+    // running、queued
+    pub status: String,
+    // 执行中的任务块数
+    pub running: usize,
+    // 等待中的任务块数
+    pub queued: usize,
+    pub cancelled: bool,
+}
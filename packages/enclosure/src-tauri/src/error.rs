@@ -1,9 +1,152 @@
 //! custom error
 
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::io;
 use thiserror::Error;
 
+/// 错误, `code` 保持不变, 前端根据 `code` 和 `params` 显示本地化的提示
 #[derive(Debug, Error)]
 pub enum Error {
+    // 其它错误
     #[error("{0}")]
     Error(String),
+
+    // file、directory、sheet、attachment、job ...
+    #[error("the {kind} `{name}` not found !")]
+    NotFound { kind: String, name: String },
+
+    #[error("permission denied: `{0}` !")]
+    PermissionDenied(String),
+
+    // archive、export、convert ...
+    #[error("unsupported {kind} format `{format}` !")]
+    UnsupportedFormat { kind: String, format: String },
+
+    // 密码为空或错误
+    #[error("{}", Self::get_password_message(.path, *.incorrect))]
+    PasswordRequired { path: String, incorrect: bool },
+
+    // 空查询、页码范围、输出路径 ...
+    #[error("invalid {name} `{value}`: {reason} !")]
+    InvalidArgument { name: String, value: String, reason: String },
+
+    #[error("the {name} exceeds the limit of {limit} !")]
+    LimitExceeded { name: String, limit: usize },
+
+    #[error("the `{path}` file is corrupt: {reason}")]
+    CorruptFile { path: String, reason: String },
+
+    // 任务 id
+    #[error("the job `{0}` was cancelled !")]
+    Cancelled(String),
+
+    #[error("{message}")]
+    Io { path: String, message: String },
+}
+
+impl Error {
+    /// 错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Error(_) => "error",
+            Error::NotFound { .. } => "not_found",
+            Error::PermissionDenied(_) => "permission_denied",
+            Error::UnsupportedFormat { .. } => "unsupported_format",
+            Error::PasswordRequired { .. } => "password_required",
+            Error::InvalidArgument { .. } => "invalid_argument",
+            Error::LimitExceeded { .. } => "limit_exceeded",
+            Error::CorruptFile { .. } => "corrupt_file",
+            Error::Cancelled(_) => "cancelled",
+            Error::Io { .. } => "io",
+        }
+    }
+
+    /// 错误参数, 用于拼接本地化的提示
+    pub fn params(&self) -> HashMap<String, String> {
+        let params: Vec<(&str, String)> = match self {
+            Error::Error(_) => Vec::new(),
+            Error::NotFound { kind, name } => vec![("kind", kind.clone()), ("name", name.clone())],
+            Error::PermissionDenied(path) => vec![("path", path.clone())],
+            Error::UnsupportedFormat { kind, format } => vec![("kind", kind.clone()), ("format", format.clone())],
+            Error::PasswordRequired { path, incorrect } => vec![("path", path.clone()), ("incorrect", incorrect.to_string())],
+            Error::InvalidArgument { name, value, reason } => vec![("name", name.clone()), ("value", value.clone()), ("reason", reason.clone())],
+            Error::LimitExceeded { name, limit } => vec![("name", name.clone()), ("limit", limit.to_string())],
+            Error::CorruptFile { path, reason } => vec![("path", path.clone()), ("reason", reason.clone())],
+            Error::Cancelled(id) => vec![("id", id.clone())],
+            Error::Io { path, .. } => vec![("path", path.clone())],
+        };
+
+        params.into_iter().map(|(key, value)| (key.to_string(), value)).collect()
+    }
+
+    pub fn not_found(kind: &str, name: &str) -> Self {
+        Error::NotFound {
+            kind: kind.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    pub fn unsupported_format(kind: &str, format: &str) -> Self {
+        Error::UnsupportedFormat {
+            kind: kind.to_string(),
+            format: format.to_string(),
+        }
+    }
+
+    pub fn invalid_argument(name: &str, value: &str, reason: impl ToString) -> Self {
+        Error::InvalidArgument {
+            name: name.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn corrupt_file(path: &str, reason: impl ToString) -> Self {
+        Error::CorruptFile {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// 按 io 错误类型区分文件不存在、没有权限
+    pub fn io(err: io::Error, path: &str) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => Self::not_found("file", path),
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(path.to_string()),
+            _ => Error::Io {
+                path: path.to_string(),
+                message: err.to_string(),
+            },
+        }
+    }
+
+    fn get_password_message(path: &str, incorrect: bool) -> String {
+        if incorrect {
+            format!("the password of `{}` is incorrect !", path)
+        } else {
+            format!("the `{}` file is encrypted, password required !", path)
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(err: String) -> Self {
+        Error::Error(err)
+    }
+}
+
+/// 命令返回错误时序列化为 `{ code, message, params }`
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Error", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("params", &self.params())?;
+        state.end()
+    }
 }
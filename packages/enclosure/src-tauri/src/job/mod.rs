@@ -0,0 +1,99 @@
+//! 可取消的任务
+
+use crate::config::JobInfo;
+use crate::error::Error;
+use crate::scheduler::Scheduler;
+use log::info;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use uuid::Uuid;
+
+/// 任务句柄, 所有持有者释放后任务结束
+#[derive(Debug)]
+pub struct Job {
+    pub id: String,
+    // process、search、export
+    pub kind: String,
+    pub path: String,
+    cancelled: AtomicBool,
+}
+
+// 正在进行的任务, 只保存弱引用, 不影响任务结束
+static JOBS: OnceLock<Mutex<HashMap<String, Weak<Job>>>> = OnceLock::new();
+
+impl Job {
+    /// 创建任务, `id` 为空时自动生成, 前端可以传入 id 以便在请求返回前取消
+    pub fn create(id: Option<&str>, kind: &str, path: &str) -> Arc<Job> {
+        let id = id
+            .filter(|id| !id.is_empty())
+            .map(|id| id.to_string())
+            .unwrap_or(Uuid::new_v4().to_string());
+        let job = Arc::new(Job {
+            id: id.clone(),
+            kind: kind.to_string(),
+            path: path.to_string(),
+            cancelled: AtomicBool::new(false),
+        });
+
+        let mut jobs = Self::get_jobs().lock().unwrap_or_else(|err| err.into_inner());
+        jobs.retain(|_, job| job.strong_count() > 0);
+        jobs.insert(id, Arc::downgrade(&job));
+        job
+    }
+
+    /// 取消任务, 任务不存在或已结束时返回 false
+    pub fn cancel(id: &str) -> bool {
+        let jobs = Self::get_jobs().lock().unwrap_or_else(|err| err.into_inner());
+        match jobs.get(id).and_then(|job| job.upgrade()) {
+            Some(job) => {
+                info!("cancel {} job `{}` ...", &job.kind, id);
+                job.cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 正在执行或等待中的任务
+    pub fn list() -> Vec<JobInfo> {
+        let jobs: Vec<Arc<Job>> = {
+            let jobs = Self::get_jobs().lock().unwrap_or_else(|err| err.into_inner());
+            jobs.values().filter_map(|job| job.upgrade()).collect()
+        };
+
+        jobs.iter()
+            .map(|job| {
+                let (running, queued) = Scheduler::get_job_tasks(&job.id);
+                // 没有执行中的任务块而有等待中的任务块时为排队
+                let status = if running == 0 && queued > 0 { "queued" } else { "running" };
+                JobInfo {
+                    id: job.id.clone(),
+                    kind: job.kind.clone(),
+                    path: job.path.clone(),
+                    status: status.to_string(),
+                    running,
+                    queued,
+                    cancelled: job.is_cancelled(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 检查点, 任务已取消时返回错误
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled(self.id.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn get_jobs() -> &'static Mutex<HashMap<String, Weak<Job>>> {
+        JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+}
@@ -5,13 +5,17 @@ mod analysis;
 mod cache;
 mod config;
 mod error;
+mod job;
 mod prepare;
-mod semaphore;
+mod scheduler;
 mod system;
 mod utils;
 
 use crate::system::tray::Tray;
-use analysis::{process, unarchive};
+use analysis::{
+    cancel_job, convert_document, export_pages, export_sheet, extract_attachment, list_jobs, process, query_sheet, query_sql,
+    read_sheet_rows, search_workbook, thumbnails, unarchive,
+};
 use tauri::Manager;
 
 fn main() {
@@ -35,7 +39,21 @@ fn main() {
             Ok(())
         })
         .menu(system::menu::Menu::create_system_menus)
-        .invoke_handler(tauri::generate_handler![process, unarchive])
+        .invoke_handler(tauri::generate_handler![
+            process,
+            unarchive,
+            extract_attachment,
+            export_pages,
+            thumbnails,
+            convert_document,
+            read_sheet_rows,
+            query_sheet,
+            query_sql,
+            export_sheet,
+            search_workbook,
+            cancel_job,
+            list_jobs
+        ])
         .run(tauri::generate_context!())
         .expect("error while running `QuickLook` application");
 
@@ -1,6 +1,7 @@
 //! prepare trait
 
 use crate::config::FileProps;
+use crate::error::Error;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
@@ -15,12 +16,12 @@ where
     R: HttpResponseData,
 {
     // 通过 file reader 处理
-    fn with_file_reader(reader: BufReader<File>, response: R) -> Result<R, String> {
+    fn with_file_reader(reader: BufReader<File>, response: R) -> Result<R, Error> {
         Ok(response)
     }
 
     // 通过 response 处理
-    fn with_response(response: R) -> Result<R, String> {
+    fn with_response(response: R) -> Result<R, Error> {
         Ok(response)
     }
 }
@@ -29,23 +30,23 @@ pub trait Treat<R>
 where
     R: HttpResponseData,
 {
-    fn get_filename(headers: &tauri::http::HeaderMap) -> Result<String, String>;
+    fn get_filename(headers: &tauri::http::HeaderMap) -> Result<String, Error>;
 
     fn get_response(filename: &str) -> R;
 
-    fn prepare(app: &tauri::AppHandle, body: &tauri::ipc::InvokeBody, response: &R) -> Result<R, String>;
+    fn prepare(app: &tauri::AppHandle, body: &tauri::ipc::InvokeBody, response: &R) -> Result<R, Error>;
 
-    fn prepare_blob(data: &Vec<u8>, response: R) -> Result<R, String>;
+    fn prepare_blob(data: &Vec<u8>, response: R) -> Result<R, Error>;
 
-    fn prepare_json(file_path: &str, response: R) -> Result<R, String>;
+    fn prepare_json(file_path: &str, response: R) -> Result<R, Error>;
 
-    fn prepare_directory(path: &PathBuf, response: R) -> Result<R, String>;
+    fn prepare_directory(path: &PathBuf, response: R) -> Result<R, Error>;
 
-    fn prepare_file(file_path: &str, response: R) -> Result<R, String>;
+    fn prepare_file(file_path: &str, response: R) -> Result<R, Error>;
 
-    fn compare_file(file_path: &str, response: R) -> Result<R, String>;
+    fn compare_file(file_path: &str, response: R) -> Result<R, Error>;
 
-    fn prepare_file_props(file_path: &str) -> Result<FileProps, String>;
+    fn prepare_file_props(file_path: &str) -> Result<FileProps, Error>;
 
     fn prepare_response(response: R, content: Option<String>, _type: &str) -> R;
 }
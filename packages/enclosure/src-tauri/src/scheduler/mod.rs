@@ -0,0 +1,313 @@
+//! 异步任务调度器, 按优先级和先后顺序分配许可
+
+use crate::cache::Cache;
+use crate::config::Settings;
+use log::{error, info};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::task::{Context, Poll, Waker};
+
+/// 优先级, 可见的页面、sheet 优先
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Normal,
+    High,
+}
+
+pub struct Scheduler;
+
+#[derive(Default)]
+struct State {
+    // 同时执行的任务块数
+    limit: usize,
+    // 单个任务同时执行的任务块数
+    job_limit: usize,
+    running: usize,
+    next_id: u64,
+    // 等待者, 先按优先级再按先后顺序排列
+    waiters: BTreeMap<(Reverse<Priority>, u64), Waiter>,
+    // 已分配许可但还未取走的等待者
+    granted: HashSet<u64>,
+    // 任务 id => (执行中, 等待中)
+    jobs: HashMap<String, (usize, usize)>,
+}
+
+struct Waiter {
+    job_id: String,
+    waker: Waker,
+}
+
+/// 许可, 释放时唤醒下一个等待者
+pub struct Permit {
+    state: &'static Mutex<State>,
+    job_id: String,
+}
+
+/// 等待许可, 未取得许可前释放会退出队列
+pub struct Acquire {
+    state: &'static Mutex<State>,
+    job_id: String,
+    priority: Priority,
+    // 入队后的序号
+    id: Option<u64>,
+    done: bool,
+}
+
+static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+
+impl Scheduler {
+    /// 申请许可, 同一任务的许可数不超过设置中的 `maxJobTasks`
+    pub fn acquire(job_id: &str, priority: Priority) -> Acquire {
+        State::acquire(Self::get_state(), job_id, priority)
+    }
+
+    /// 任务执行中、等待中的任务块数
+    pub fn get_job_tasks(job_id: &str) -> (usize, usize) {
+        State::lock(Self::get_state()).jobs.get(job_id).copied().unwrap_or_default()
+    }
+
+    fn get_state() -> &'static Mutex<State> {
+        STATE.get_or_init(|| {
+            let settings = Cache::read_settings().unwrap_or_else(|err| {
+                error!("read settings error: {}", err);
+                Settings::default()
+            });
+
+            info!("scheduler max tasks: {}, max job tasks: {}", settings.max_tasks, settings.max_job_tasks);
+            Mutex::new(State {
+                limit: settings.max_tasks.max(1),
+                job_limit: settings.max_job_tasks.max(1),
+                ..State::default()
+            })
+        })
+    }
+}
+
+impl State {
+    fn acquire(state: &'static Mutex<State>, job_id: &str, priority: Priority) -> Acquire {
+        Acquire {
+            state,
+            job_id: job_id.to_string(),
+            priority,
+            id: None,
+            done: false,
+        }
+    }
+
+    fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+        state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// 按顺序分配许可, 跳过已达到上限的任务
+    fn dispatch(&mut self) {
+        while self.running < self.limit {
+            let key = self
+                .waiters
+                .iter()
+                .find(|(_, waiter)| self.jobs.get(&waiter.job_id).map(|job| job.0).unwrap_or(0) < self.job_limit)
+                .map(|(key, _)| *key);
+
+            let Some(key) = key else {
+                break;
+            };
+
+            if let Some(waiter) = self.waiters.remove(&key) {
+                let job = self.jobs.entry(waiter.job_id).or_default();
+                job.0 += 1;
+                job.1 = job.1.saturating_sub(1);
+                self.running += 1;
+                self.granted.insert(key.1);
+                waiter.waker.wake();
+            }
+        }
+    }
+
+    fn release(&mut self, job_id: &str) {
+        self.running = self.running.saturating_sub(1);
+        if let Some(job) = self.jobs.get_mut(job_id) {
+            job.0 = job.0.saturating_sub(1);
+            if *job == (0, 0) {
+                self.jobs.remove(job_id);
+            }
+        }
+
+        self.dispatch();
+    }
+}
+
+impl Future for Acquire {
+    type Output = Permit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = State::lock(self.state);
+        let id = match self.id {
+            Some(id) => {
+                if let Some(waiter) = state.waiters.get_mut(&(Reverse(self.priority), id)) {
+                    waiter.waker = cx.waker().clone();
+                }
+
+                id
+            }
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.insert(
+                    (Reverse(self.priority), id),
+                    Waiter {
+                        job_id: self.job_id.clone(),
+                        waker: cx.waker().clone(),
+                    },
+                );
+                state.jobs.entry(self.job_id.clone()).or_default().1 += 1;
+                state.dispatch();
+                self.id = Some(id);
+                id
+            }
+        };
+
+        if !state.granted.remove(&id) {
+            return Poll::Pending;
+        }
+
+        self.done = true;
+        Poll::Ready(Permit {
+            state: self.state,
+            job_id: self.job_id.clone(),
+        })
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        let Some(id) = self.id else {
+            return;
+        };
+
+        if self.done {
+            return;
+        }
+
+        let mut state = State::lock(self.state);
+        if state.waiters.remove(&(Reverse(self.priority), id)).is_some() {
+            if let Some(job) = state.jobs.get_mut(&self.job_id) {
+                job.1 = job.1.saturating_sub(1);
+                if *job == (0, 0) {
+                    state.jobs.remove(&self.job_id);
+                }
+            }
+        } else if state.granted.remove(&id) {
+            state.release(&self.job_id);
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        State::lock(self.state).release(&self.job_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn create_state(limit: usize, job_limit: usize) -> &'static Mutex<State> {
+        Box::leak(Box::new(Mutex::new(State {
+            limit,
+            job_limit,
+            ..State::default()
+        })))
+    }
+
+    fn poll(acquire: &mut Acquire) -> Option<Permit> {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        match Pin::new(acquire).poll(&mut cx) {
+            Poll::Ready(permit) => Some(permit),
+            Poll::Pending => None,
+        }
+    }
+
+    #[test]
+    fn test_fifo() {
+        let state = create_state(1, 10);
+        let permit = poll(&mut State::acquire(state, "a", Priority::Normal)).unwrap();
+        let mut waiters: Vec<Acquire> = (0..3).map(|_| State::acquire(state, "a", Priority::Normal)).collect();
+        assert!(waiters.iter_mut().all(|acquire| poll(acquire).is_none()));
+
+        // 同一优先级按入队顺序分配
+        drop(permit);
+        for index in 0..waiters.len() {
+            let permit = poll(&mut waiters[index]).unwrap();
+            assert!(waiters[index + 1..].iter_mut().all(|acquire| poll(acquire).is_none()));
+            drop(permit);
+        }
+
+        assert_eq!(State::lock(state).running, 0);
+    }
+
+    #[test]
+    fn test_priority() {
+        let state = create_state(1, 10);
+        let permit = poll(&mut State::acquire(state, "a", Priority::Normal)).unwrap();
+        let mut normal = State::acquire(state, "a", Priority::Normal);
+        let mut high = State::acquire(state, "b", Priority::High);
+        assert!(poll(&mut normal).is_none());
+        assert!(poll(&mut high).is_none());
+
+        // 后入队的高优先级先分配
+        drop(permit);
+        assert!(poll(&mut normal).is_none());
+        let permit = poll(&mut high).unwrap();
+        drop(permit);
+        assert!(poll(&mut normal).is_some());
+    }
+
+    #[test]
+    fn test_job_limit() {
+        let state = create_state(10, 2);
+        let first = poll(&mut State::acquire(state, "a", Priority::Normal)).unwrap();
+        let _second = poll(&mut State::acquire(state, "a", Priority::Normal)).unwrap();
+        let mut third = State::acquire(state, "a", Priority::High);
+        assert!(poll(&mut third).is_none());
+        assert_eq!(State::lock(state).jobs.get("a").copied(), Some((2, 1)));
+
+        // 其它任务不受影响
+        assert!(poll(&mut State::acquire(state, "b", Priority::Normal)).is_some());
+
+        drop(first);
+        assert!(poll(&mut third).is_some());
+    }
+
+    #[test]
+    fn test_drop_granted() {
+        let state = create_state(1, 10);
+        let permit = poll(&mut State::acquire(state, "a", Priority::Normal)).unwrap();
+        let mut acquire = State::acquire(state, "b", Priority::Normal);
+        assert!(poll(&mut acquire).is_none());
+
+        // 已分配许可但还未取走时释放, 许可归还
+        drop(permit);
+        assert!(State::lock(state).granted.len() == 1);
+        drop(acquire);
+        {
+            let state = State::lock(state);
+            assert_eq!(state.running, 0);
+            assert!(state.granted.is_empty());
+            assert!(state.jobs.is_empty());
+        }
+
+        assert!(poll(&mut State::acquire(state, "c", Priority::Normal)).is_some());
+    }
+}
@@ -1,6 +1,6 @@
 //! 文件助手
 
-use crate::config::HISTORY_FILE;
+use crate::config::{HISTORY_FILE, SETTINGS_FILE};
 use crate::error::Error;
 use crate::utils::Utils;
 use chrono::Duration;
@@ -10,65 +10,66 @@ use std::fs;
 use std::fs::File;
 use std::io::{BufReader, Read, Write};
 use std::path::{Path, PathBuf};
+use uuid::Uuid;
 
 pub struct FileUtils;
 
 impl FileUtils {
     /// 打开文件
-    pub fn open_file(file_path: &str) -> Result<File, String> {
-        let file = File::open(&file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+    pub fn open_file(file_path: &str) -> Result<File, Error> {
+        let file = File::open(&file_path).map_err(|err| Error::io(err, file_path))?;
         Ok(file)
     }
 
     /// 读取文件 - 字节
-    pub fn read_file(file_path: &str) -> Result<Vec<u8>, String> {
+    pub fn read_file(file_path: &str) -> Result<Vec<u8>, Error> {
         let mut file = Self::open_file(file_path)?;
         let mut contents: Vec<u8> = Vec::new();
-        file.read_to_end(&mut contents).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        file.read_to_end(&mut contents).map_err(|err| Error::io(err, file_path))?;
         Ok(contents)
     }
 
     /// 读取文件流
-    pub fn read_file_buffer(file_path: &str) -> Result<BufReader<File>, String> {
+    pub fn read_file_buffer(file_path: &str) -> Result<BufReader<File>, Error> {
         let file = Self::open_file(file_path)?;
         Ok(BufReader::new(file))
     }
 
     /// 读取文件 - 字符串
-    pub fn read_file_string(file_path: &str) -> Result<String, String> {
+    pub fn read_file_string(file_path: &str) -> Result<String, Error> {
         let mut file = Self::open_file(file_path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)
-            .map_err(|err| Error::Error(err.to_string()).to_string())?;
+            .map_err(|err| Error::io(err, file_path))?;
         Ok(contents)
     }
 
     /// 清空上一天的目录
-    pub fn clear_yesterdays_dirs(file_path: &PathBuf) -> Result<(), String> {
+    pub fn clear_yesterdays_dirs(file_path: &PathBuf) -> Result<(), Error> {
         info!("clear yesterdays dirs ...");
         let now = chrono::Local::now();
         let yesterday = now - Duration::days(1);
         // let yesterday_start = yesterday.date_naive().and_hms_opt(0, 0, 0).unwrap().timestamp();
         let yesterday_end = yesterday.date_naive().and_hms_opt(23, 59, 59).unwrap().timestamp();
 
-        let entries = fs::read_dir(file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let entries = fs::read_dir(file_path).map_err(|err| Error::io(err, &file_path.to_string_lossy()))?;
         for entry in entries {
             if let Ok(entry) = entry {
                 let path = entry.path();
                 let path_str = path.as_path().to_string_lossy().to_string();
-                if path_str.ends_with(HISTORY_FILE) {
+                if path_str.ends_with(HISTORY_FILE) || path_str.ends_with(SETTINGS_FILE) {
                     continue;
                 }
 
-                let metadata = path.metadata().map_err(|err| Error::Error(err.to_string()).to_string())?;
-                let modified_time = metadata.modified().map_err(|err| Error::Error(err.to_string()).to_string())?;
+                let metadata = path.metadata().map_err(|err| Error::io(err, &path_str))?;
+                let modified_time = metadata.modified().map_err(|err| Error::io(err, &path_str))?;
 
                 let modified_time = chrono::DateTime::<chrono::Local>::from(modified_time).timestamp();
                 if modified_time <= yesterday_end {
                     if path.is_dir() {
-                        fs::remove_dir_all(path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+                        fs::remove_dir_all(path).map_err(|err| Error::io(err, &path_str))?;
                     } else {
-                        fs::remove_file(path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+                        fs::remove_file(path).map_err(|err| Error::io(err, &path_str))?;
                     }
                 }
             }
@@ -78,7 +79,7 @@ impl FileUtils {
     }
 
     /// 创建临时目录
-    pub fn create_temp_dir(name: &str, need_remove_dir: bool) -> Result<PathBuf, String> {
+    pub fn create_temp_dir(name: &str, need_remove_dir: bool) -> Result<PathBuf, Error> {
         info!("create temp dir ...");
         // 获取路径(数据目录或home)
         let exec_path = Utils::get_program_dir();
@@ -87,14 +88,20 @@ impl FileUtils {
         // 清空上一天的目录
         Self::clear_yesterdays_dirs(&exec_path)?;
 
+        // 以 `.` 开头的是保留目录, 如缩略图缓存, 文件的临时目录加上 `_` 前缀, 不会与之重名, 也不会删除整个程序目录
+        let name = if name.is_empty() || name.starts_with('.') {
+            format!("_{}", name)
+        } else {
+            name.to_string()
+        };
         let unzip_path = exec_path.join(Path::new(&name));
 
         if need_remove_dir {
             if unzip_path.exists() {
-                fs::remove_dir_all(unzip_path.clone()).map_err(|err| Error::Error(err.to_string()).to_string())?;
+                fs::remove_dir_all(unzip_path.clone()).map_err(|err| Error::io(err, &unzip_path.to_string_lossy()))?;
             }
 
-            fs::create_dir_all(&unzip_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
+            fs::create_dir_all(&unzip_path).map_err(|err| Error::io(err, &unzip_path.to_string_lossy()))?;
         }
 
         Ok(unzip_path)
@@ -141,19 +148,85 @@ impl FileUtils {
     }
 
     /// 清空文件并写入新的内容
-    pub fn write_to_file_when_clear(file_path: &str, content: &str) -> Result<(), String> {
+    pub fn write_to_file_when_clear(file_path: &str, content: impl AsRef<[u8]>) -> Result<(), Error> {
         // 打开文件以进行覆盖写入
-        let mut file = File::create(&file_path).map_err(|err| Error::Error(err.to_string()).to_string())?;
-        file.write_all(content.as_bytes())
-            .map_err(|err| Error::Error(err.to_string()).to_string())?;
+        let mut file = File::create(&file_path).map_err(|err| Error::io(err, file_path))?;
+        file.write_all(content.as_ref())
+            .map_err(|err| Error::io(err, file_path))?;
         file.flush().unwrap(); // 刷新文件缓冲
         file.sync_all().unwrap(); // 写入磁盘
         drop(file); // 自动关闭文件
         Ok(())
     }
 
+    /// 同目录下的临时文件路径, 以 `.` 开头、`.tmp` 结尾
+    pub fn get_temp_file_path(file_path: &str) -> PathBuf {
+        let path = Path::new(file_path);
+        let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()))
+    }
+
+    /// 先写入同目录下的临时文件再重命名, 读取方不会读到写了一半的内容
+    pub fn write_to_file_atomic(file_path: &str, content: impl AsRef<[u8]>) -> Result<(), Error> {
+        let path = Path::new(file_path);
+        let temp_path = Self::get_temp_file_path(file_path);
+        let temp_str = temp_path.as_path().to_string_lossy().to_string();
+
+        if let Err(err) = Self::write_to_file_when_clear(&temp_str, content) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(err);
+        }
+
+        if let Err(err) = fs::rename(&temp_path, path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(Error::io(err, file_path));
+        }
+
+        Ok(())
+    }
+
+    /// 是否为同一文件, 目标文件不存在时比较所在目录
+    pub fn is_same_file(file_path: &str, output_path: &str) -> bool {
+        let output = Path::new(output_path);
+        let output = match fs::canonicalize(output) {
+            Ok(path) => path,
+            Err(_) => match (output.parent(), output.file_name()) {
+                (Some(dir), Some(name)) => {
+                    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+                    match fs::canonicalize(dir) {
+                        Ok(dir) => dir.join(name),
+                        Err(_) => return false,
+                    }
+                }
+                _ => return false,
+            },
+        };
+
+        fs::canonicalize(file_path).map(|path| path == output).unwrap_or(false)
+    }
+
+    /// 目录下不存在的文件路径, 同名文件已存在时在文件名后追加 ` (1)`、` (2)` 等
+    pub fn get_unique_file_path(dir: &Path, file_name: &str) -> PathBuf {
+        let path = dir.join(file_name);
+        if !path.exists() {
+            return path;
+        }
+
+        let name = Path::new(file_name);
+        let stem = name.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let extension = name.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+        let mut index: usize = 1;
+        loop {
+            let path = dir.join(format!("{} ({}){}", stem, index, extension));
+            if !path.exists() {
+                return path;
+            }
+            index += 1;
+        }
+    }
+
     /// 获取文件的 hash 值
-    pub fn get_file_hash(file_path: &str) -> Result<String, String> {
+    pub fn get_file_hash(file_path: &str) -> Result<String, Error> {
         let buffer = Self::read_file(file_path)?;
         if buffer.is_empty() {
             return Ok(String::new());
@@ -163,3 +236,40 @@ impl FileUtils {
         Ok(str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_same_file() {
+        let temp_dir = std::env::temp_dir().join(format!("file-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(temp_dir.join("sub")).unwrap();
+        let file_path = temp_dir.join("data.csv");
+        fs::write(&file_path, "a,b\n").unwrap();
+        let file_path = file_path.to_string_lossy().to_string();
+
+        let same = temp_dir.join("sub").join("..").join("data.csv");
+        assert!(FileUtils::is_same_file(&file_path, &same.to_string_lossy()));
+        assert!(!FileUtils::is_same_file(&file_path, &temp_dir.join("data.jsonl").to_string_lossy()));
+        assert!(!FileUtils::is_same_file(&file_path, &temp_dir.join("missing").join("data.csv").to_string_lossy()));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_unique_file_path() {
+        let temp_dir = std::env::temp_dir().join(format!("file-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        assert_eq!(FileUtils::get_unique_file_path(&temp_dir, "page-1.png"), temp_dir.join("page-1.png"));
+
+        fs::write(temp_dir.join("page-1.png"), "").unwrap();
+        fs::write(temp_dir.join("page-1 (1).png"), "").unwrap();
+        assert_eq!(FileUtils::get_unique_file_path(&temp_dir, "page-1.png"), temp_dir.join("page-1 (2).png"));
+
+        fs::write(temp_dir.join("notes"), "").unwrap();
+        assert_eq!(FileUtils::get_unique_file_path(&temp_dir, "notes"), temp_dir.join("notes (1)"));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -2,6 +2,7 @@ use base64::Engine;
 use std::path::{Path, PathBuf};
 
 pub mod file;
+pub mod sniff;
 
 pub struct Utils;
 
@@ -26,4 +27,21 @@ impl Utils {
         content.push_str(&str);
         return content;
     }
+
+    /// 转义 html 字符
+    pub fn escape_html(text: &str) -> String {
+        let mut content = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '&' => content.push_str("&amp;"),
+                '<' => content.push_str("&lt;"),
+                '>' => content.push_str("&gt;"),
+                '"' => content.push_str("&quot;"),
+                '\'' => content.push_str("&#39;"),
+                _ => content.push(c),
+            }
+        }
+
+        content
+    }
 }
@@ -0,0 +1,490 @@
+//! 根据文件头的魔数识别文件类型, 后缀只作为参考
+
+use crate::error::Error;
+use crate::utils::file::FileUtils;
+use std::io::Read;
+
+/// 读取文件头的字节数
+const HEAD_SIZE: u64 = 16 * 1024;
+
+/// 识别结果
+#[derive(Debug, Clone, Default)]
+pub struct Sniffed {
+    // 与原后缀属于同一格式时保留原后缀
+    pub suffix: String,
+    pub mime: String,
+    pub executable: bool,
+}
+
+pub struct Sniff;
+
+/// (同一格式的后缀, 第一个为默认后缀, mime), 后缀为空时保留原后缀
+type Kind = (&'static [&'static str], &'static str);
+
+// 图片
+const JPEG: Kind = (&["jpg", "jpeg"], "image/jpeg");
+const PNG: Kind = (&["png"], "image/png");
+const GIF: Kind = (&["gif"], "image/gif");
+const TIFF: Kind = (&["tiff", "tif"], "image/tiff");
+const WEBP: Kind = (&["webp"], "image/webp");
+const ICO: Kind = (&["ico"], "image/x-icon");
+const BMP: Kind = (&["bmp"], "image/bmp");
+const HEIC: Kind = (&["heic", "heif"], "image/heic");
+const AVIF: Kind = (&["avif"], "image/avif");
+const SVG: Kind = (&["svg"], "image/svg+xml");
+
+// 文档
+const PDF: Kind = (&["pdf"], "application/pdf");
+const RTF: Kind = (&["rtf"], "application/rtf");
+const FB2: Kind = (&["fb2"], "application/x-fictionbook+xml");
+const DOC: Kind = (&["doc"], "application/msword");
+const XLS: Kind = (&["xls", "xla"], "application/vnd.ms-excel");
+const PPT: Kind = (&["ppt"], "application/vnd.ms-powerpoint");
+const OLE: Kind = (&[], "application/x-ole-storage");
+const DOCX: Kind = (&["docx"], "application/vnd.openxmlformats-officedocument.wordprocessingml.document");
+const XLSX: Kind = (
+    &["xlsx", "xlsm", "xlsb", "xlam"],
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+);
+const PPTX: Kind = (&["pptx"], "application/vnd.openxmlformats-officedocument.presentationml.presentation");
+const ODT: Kind = (&["odt"], "application/vnd.oasis.opendocument.text");
+const ODS: Kind = (&["ods"], "application/vnd.oasis.opendocument.spreadsheet");
+const ODP: Kind = (&["odp"], "application/vnd.oasis.opendocument.presentation");
+const EPUB: Kind = (&["epub"], "application/epub+zip");
+const XPS: Kind = (&["xps", "oxps"], "application/oxps");
+
+// 压缩包
+const ZIP: Kind = (&["zip", "cbz"], "application/zip");
+const GZ: Kind = (&["gz", "tgz"], "application/gzip");
+const BZ2: Kind = (&["bz2"], "application/x-bzip2");
+const XZ: Kind = (&["xz"], "application/x-xz");
+const Z7: Kind = (&["7z"], "application/x-7z-compressed");
+const RAR: Kind = (&["rar"], "application/vnd.rar");
+const TAR: Kind = (&["tar"], "application/x-tar");
+const ZLIB: Kind = (&["zlib"], "application/zlib");
+
+// 可执行文件
+const ELF: Kind = (&[], "application/x-elf");
+const MACH_O: Kind = (&[], "application/x-mach-binary");
+const PE: Kind = (&[], "application/vnd.microsoft.portable-executable");
+
+// 数据库、字体
+const SQLITE: Kind = (&["db", "sqlite", "sqlite3"], "application/vnd.sqlite3");
+const TTF: Kind = (&["ttf"], "font/ttf");
+const OTF: Kind = (&["otf"], "font/otf");
+const TTC: Kind = (&["ttc"], "font/collection");
+const WOFF: Kind = (&["woff"], "font/woff");
+const WOFF2: Kind = (&["woff2"], "font/woff2");
+
+// 音视频
+const MP3: Kind = (&["mp3"], "audio/mpeg");
+const OGG: Kind = (&["ogg", "oga", "ogv", "opus"], "audio/ogg");
+const FLAC: Kind = (&["flac"], "audio/flac");
+const WAV: Kind = (&["wav"], "audio/wav");
+const AVI: Kind = (&["avi"], "video/x-msvideo");
+const MP4: Kind = (&["mp4", "m4v"], "video/mp4");
+const M4A: Kind = (&["m4a"], "audio/mp4");
+const MOV: Kind = (&["mov"], "video/quicktime");
+const MKV: Kind = (&["mkv"], "video/x-matroska");
+const WEBM: Kind = (&["webm"], "video/webm");
+
+const EXECUTABLES: [Kind; 3] = [ELF, MACH_O, PE];
+
+// 文本后缀, 只有几个字节的弱魔数不覆盖
+const TEXT_SUFFIXES: [&str; 11] = ["csv", "tsv", "psv", "txt", "json", "jsonl", "xml", "html", "htm", "md", "log"];
+
+impl Sniff {
+    /// 读取文件头识别
+    pub fn detect_file(file_path: &str, suffix: &str) -> Result<Sniffed, Error> {
+        let file = FileUtils::open_file(file_path)?;
+        let mut head: Vec<u8> = Vec::new();
+        file.take(HEAD_SIZE).read_to_end(&mut head).map_err(|err| Error::io(err, file_path))?;
+        Ok(Self::detect(&head, suffix))
+    }
+
+    /// 识别文件类型, 无法识别时保留原后缀
+    pub fn detect(data: &[u8], suffix: &str) -> Sniffed {
+        let kind = match Self::detect_kind(data, suffix) {
+            Some(kind) => kind,
+            None => {
+                return Sniffed {
+                    suffix: suffix.to_string(),
+                    mime: Self::get_text_mime(data, suffix).to_string(),
+                    executable: false,
+                }
+            }
+        };
+
+        let (suffixes, mime) = kind;
+        let suffix = match suffixes.first() {
+            Some(first) if !suffixes.contains(&suffix) => *first,
+            _ => suffix,
+        };
+
+        Sniffed {
+            suffix: suffix.to_string(),
+            mime: mime.to_string(),
+            executable: EXECUTABLES.contains(&kind),
+        }
+    }
+
+    fn detect_kind(data: &[u8], suffix: &str) -> Option<Kind> {
+        let weak = !TEXT_SUFFIXES.contains(&suffix);
+        let kind = match data {
+            [0xFF, 0xD8, 0xFF, ..] => JPEG,
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => PNG,
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => GIF,
+            [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => TIFF,
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => WEBP,
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => WAV,
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', b' ', ..] => AVI,
+            [0x00, 0x00, 0x01, 0x00, ..] if weak => ICO,
+            // 保留字段为 0
+            [b'B', b'M', _, _, _, _, 0x00, 0x00, 0x00, 0x00, ..] if weak => BMP,
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Self::detect_ftyp(data),
+            [b'%', b'P', b'D', b'F', b'-', ..] => PDF,
+            [b'{', b'\\', b'r', b't', b'f', ..] => RTF,
+            [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1, ..] => Self::detect_ole(data, suffix),
+            [b'P', b'K', 0x03, 0x04, ..] => Self::detect_zip(data),
+            [b'P', b'K', 0x05 | 0x07, 0x06 | 0x08, ..] => ZIP,
+            [0x1F, 0x8B, ..] => GZ,
+            [b'B', b'Z', b'h', ..] => BZ2,
+            [0xFD, b'7', b'z', b'X', b'Z', 0x00, ..] => XZ,
+            [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C, ..] => Z7,
+            [b'R', b'a', b'r', b'!', 0x1A, 0x07, ..] => RAR,
+            [0x7F, b'E', b'L', b'F', ..] => ELF,
+            [0xFE, 0xED, 0xFA, 0xCE | 0xCF, ..] | [0xCE | 0xCF, 0xFA, 0xED, 0xFE, ..] => MACH_O,
+            // java class 同样以 CAFEBABE 开头, 第 5~8 字节是版本号, 远大于 fat 的架构数
+            [0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, count, ..] if *count < 20 => MACH_O,
+            [b'M', b'Z', ..] if weak && Self::is_pe(data) => PE,
+            [b'S', b'Q', b'L', b'i', b't', b'e', b' ', b'f', b'o', b'r', b'm', b'a', b't', b' ', b'3', 0x00, ..] => SQLITE,
+            [0x00, 0x01, 0x00, 0x00, ..] | [b't', b'r', b'u', b'e', ..] if weak && Self::is_sfnt(data, suffix) => TTF,
+            [b'O', b'T', b'T', b'O', ..] => OTF,
+            [b't', b't', b'c', b'f', ..] => TTC,
+            [b'w', b'O', b'F', b'F', ..] => WOFF,
+            [b'w', b'O', b'F', b'2', ..] => WOFF2,
+            [b'I', b'D', b'3', ..] => MP3,
+            [0xFF, 0xFB | 0xF3 | 0xF2, ..] if weak => MP3,
+            [b'O', b'g', b'g', b'S', ..] => OGG,
+            [b'f', b'L', b'a', b'C', ..] => FLAC,
+            [0x1A, 0x45, 0xDF, 0xA3, ..] if Self::contains(data, b"webm") => WEBM,
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => MKV,
+            // zlib 头只有两个字节, 只在后缀一致时识别
+            [0x78, second, ..] if suffix == "zlib" && (0x78 * 256 + *second as u16) % 31 == 0 => ZLIB,
+            _ if data.len() > 262 && &data[257..262] == b"ustar" => TAR,
+            _ => return Self::detect_text(data),
+        };
+
+        Some(kind)
+    }
+
+    /// mp4、mov、heic 等, 根据 `ftyp` 后的品牌区分
+    fn detect_ftyp(data: &[u8]) -> Kind {
+        match data.get(8..12).unwrap_or_default() {
+            b"heic" | b"heix" | b"hevc" | b"heim" | b"heis" | b"mif1" | b"msf1" => HEIC,
+            b"avif" | b"avis" => AVIF,
+            b"qt  " => MOV,
+            b"M4A " | b"M4B " => M4A,
+            _ => MP4,
+        }
+    }
+
+    /// doc、xls、ppt 共用 OLE 格式, 根据目录中的流名称区分, 目录不在文件头时按原后缀
+    fn detect_ole(data: &[u8], suffix: &str) -> Kind {
+        let streams = [("WordDocument", DOC), ("Workbook", XLS), ("Book", XLS), ("PowerPoint Document", PPT)];
+        for (name, kind) in streams {
+            let name: Vec<u8> = name.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+            if Self::contains(data, &name) {
+                return kind;
+            }
+        }
+
+        [DOC, XLS, PPT].into_iter().find(|kind| kind.0.contains(&suffix)).unwrap_or(OLE)
+    }
+
+    /// docx、xlsx、pptx、odt、epub 等都是 zip, 根据第一个 `mimetype` 文件或目录名区分
+    fn detect_zip(data: &[u8]) -> Kind {
+        let names = Self::get_zip_names(data);
+        if names.first().map(|name| name.as_str()) == Some("mimetype") {
+            // 文件名后是扩展字段, `mimetype` 不压缩
+            let extra = u16::from_le_bytes([data[28], data[29]]) as usize;
+            let mime = data.get(38 + extra..).unwrap_or_default();
+            let kinds = [ODT, ODS, ODP, EPUB];
+            if let Some(kind) = kinds.into_iter().find(|kind| mime.starts_with(kind.1.as_bytes())) {
+                return kind;
+            }
+        }
+
+        let dirs = [("word/", DOCX), ("xl/", XLSX), ("ppt/", PPTX), ("Documents/", XPS)];
+        for (dir, kind) in dirs {
+            if names.iter().any(|name| name.starts_with(dir)) {
+                return kind;
+            }
+        }
+
+        ZIP
+    }
+
+    /// 读取文件头中本地文件头的文件名
+    fn get_zip_names(data: &[u8]) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        let mut index = 0;
+        while index + 30 <= data.len() {
+            if &data[index..index + 4] != b"PK\x03\x04" {
+                index += 1;
+                continue;
+            }
+
+            let length = u16::from_le_bytes([data[index + 26], data[index + 27]]) as usize;
+            let start = index + 30;
+            match data.get(start..start + length) {
+                Some(name) => names.push(String::from_utf8_lossy(name).to_string()),
+                None => break,
+            }
+
+            index = start + length;
+        }
+
+        names
+    }
+
+    /// `MZ` 只有两个字节, 需要检查 `e_lfanew` 指向的 `PE` 头, PE 头不在文件头中时不识别
+    fn is_pe(data: &[u8]) -> bool {
+        let Some(offset) = data.get(0x3C..0x40) else {
+            return false;
+        };
+
+        let offset = u32::from_le_bytes([offset[0], offset[1], offset[2], offset[3]]) as usize;
+        offset
+            .checked_add(4)
+            .and_then(|end| data.get(offset..end))
+            .map(|header| header == b"PE\0\0")
+            .unwrap_or(false)
+    }
+
+    /// `true` 开头的文本很常见, 后缀不是 ttf 时检查表目录的 searchRange 等字段
+    fn is_sfnt(data: &[u8], suffix: &str) -> bool {
+        if suffix == "ttf" {
+            return true;
+        }
+
+        let Some(header) = data.get(4..12) else {
+            return false;
+        };
+
+        let read = |index: usize| u16::from_be_bytes([header[index], header[index + 1]]) as u32;
+        let (tables, search_range, entry_selector, range_shift) = (read(0), read(2), read(4), read(6));
+        if tables == 0 || tables > 256 {
+            return false;
+        }
+
+        let selector = 31 - tables.leading_zeros();
+        search_range == 16 << selector && entry_selector == selector && range_shift == tables * 16 - search_range
+    }
+
+    /// svg、fb2 等 xml
+    fn detect_text(data: &[u8]) -> Option<Kind> {
+        let text = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        let start = text.iter().position(|c| !c.is_ascii_whitespace()).unwrap_or(text.len());
+        let text = &text[start..];
+        if !text.starts_with(b"<") {
+            return None;
+        }
+
+        if text.starts_with(b"<svg") || (text.starts_with(b"<?xml") && Self::contains(text, b"<svg")) {
+            return Some(SVG);
+        }
+
+        if Self::contains(text, b"<FictionBook") {
+            return Some(FB2);
+        }
+
+        None
+    }
+
+    /// 文件头没有 `\0` 时认为是文本
+    fn get_text_mime(data: &[u8], suffix: &str) -> &'static str {
+        if data.contains(&0) {
+            return "application/octet-stream";
+        }
+
+        match suffix {
+            "csv" => "text/csv",
+            "tsv" => "text/tab-separated-values",
+            "html" | "htm" => "text/html",
+            "json" => "application/json",
+            "xml" => "application/xml",
+            "md" => "text/markdown",
+            _ => "text/plain",
+        }
+    }
+
+    fn contains(data: &[u8], value: &[u8]) -> bool {
+        data.windows(value.len()).any(|window| window == value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(data: &[u8], suffix: &str) -> Sniffed {
+        Sniff::detect(data, suffix)
+    }
+
+    /// 补齐到指定长度
+    fn pad(data: &[u8], size: usize) -> Vec<u8> {
+        let mut data = data.to_vec();
+        data.resize(size.max(data.len()), 0);
+        data
+    }
+
+    /// 本地文件头, 文件名后紧跟内容
+    fn zip_entry(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut data = b"PK\x03\x04".to_vec();
+        data.extend_from_slice(&[0; 22]);
+        data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(content);
+        data
+    }
+
+    #[test]
+    fn test_magic_numbers() {
+        let ole = |stream: &str| {
+            let mut data = pad(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1], 512);
+            data.extend(stream.encode_utf16().flat_map(|c| c.to_le_bytes()));
+            data
+        };
+        let mut tar = pad(b"file.txt", 257);
+        tar.extend_from_slice(b"ustar\x0000");
+        let tar = pad(&tar, 512);
+        // 4 张表: searchRange 64, entrySelector 2, rangeShift 0
+        let ttf = [0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x40, 0x00, 0x02, 0x00, 0x00];
+
+        let cases: Vec<(Vec<u8>, &str, &str)> = vec![
+            (pad(&[0xFF, 0xD8, 0xFF, 0xE0], 16), "jpg", "image/jpeg"),
+            (pad(b"\x89PNG\r\n\x1a\n", 16), "png", "image/png"),
+            (pad(b"GIF89a", 16), "gif", "image/gif"),
+            (pad(b"II*\0", 16), "tiff", "image/tiff"),
+            (pad(b"MM\0*", 16), "tiff", "image/tiff"),
+            (pad(b"RIFF\0\0\0\0WEBPVP8 ", 16), "webp", "image/webp"),
+            (pad(b"RIFF\0\0\0\0WAVEfmt ", 16), "wav", "audio/wav"),
+            (pad(b"RIFF\0\0\0\0AVI LIST", 16), "avi", "video/x-msvideo"),
+            (pad(&[0x00, 0x00, 0x01, 0x00, 0x01], 16), "ico", "image/x-icon"),
+            (pad(b"BM\x36\0\0\0", 16), "bmp", "image/bmp"),
+            (pad(b"\0\0\0\x18ftypheic", 16), "heic", "image/heic"),
+            (pad(b"\0\0\0\x18ftypavif", 16), "avif", "image/avif"),
+            (pad(b"\0\0\0\x18ftypqt  ", 16), "mov", "video/quicktime"),
+            (pad(b"\0\0\0\x18ftypM4A ", 16), "m4a", "audio/mp4"),
+            (pad(b"\0\0\0\x18ftypisom", 16), "mp4", "video/mp4"),
+            (b"%PDF-1.7\n".to_vec(), "pdf", "application/pdf"),
+            (b"{\\rtf1\\ansi}".to_vec(), "rtf", "application/rtf"),
+            (ole("WordDocument"), "doc", "application/msword"),
+            (ole("Workbook"), "xls", "application/vnd.ms-excel"),
+            (ole("PowerPoint Document"), "ppt", "application/vnd.ms-powerpoint"),
+            (zip_entry("word/document.xml", b""), "docx", DOCX.1),
+            (zip_entry("xl/workbook.xml", b""), "xlsx", XLSX.1),
+            (zip_entry("ppt/presentation.xml", b""), "pptx", PPTX.1),
+            (zip_entry("Documents/1/FixedDocument.fdoc", b""), "xps", XPS.1),
+            (zip_entry("mimetype", ODT.1.as_bytes()), "odt", ODT.1),
+            (zip_entry("mimetype", ODS.1.as_bytes()), "ods", ODS.1),
+            (zip_entry("mimetype", EPUB.1.as_bytes()), "epub", EPUB.1),
+            (zip_entry("readme.txt", b"hello"), "zip", "application/zip"),
+            (pad(b"PK\x05\x06", 22), "zip", "application/zip"),
+            (pad(&[0x1F, 0x8B, 0x08], 16), "gz", "application/gzip"),
+            (pad(b"BZh91AY&SY", 16), "bz2", "application/x-bzip2"),
+            (pad(b"\xFD7zXZ\0", 16), "xz", "application/x-xz"),
+            (pad(b"7z\xBC\xAF\x27\x1C", 16), "7z", "application/x-7z-compressed"),
+            (pad(b"Rar!\x1A\x07\x01\x00", 16), "rar", "application/vnd.rar"),
+            (tar, "tar", "application/x-tar"),
+            (pad(b"SQLite format 3\0", 32), "db", "application/vnd.sqlite3"),
+            (pad(&ttf, 32), "ttf", "font/ttf"),
+            (pad(b"OTTO", 16), "otf", "font/otf"),
+            (pad(b"ttcf", 16), "ttc", "font/collection"),
+            (pad(b"wOFF", 16), "woff", "font/woff"),
+            (pad(b"wOF2", 16), "woff2", "font/woff2"),
+            (pad(b"ID3\x04", 16), "mp3", "audio/mpeg"),
+            (pad(&[0xFF, 0xFB, 0x90, 0x64], 16), "mp3", "audio/mpeg"),
+            (pad(b"OggS", 16), "ogg", "audio/ogg"),
+            (pad(b"fLaC", 16), "flac", "audio/flac"),
+            (pad(b"\x1A\x45\xDF\xA3\x9F\x42\x82\x84webm", 32), "webm", "video/webm"),
+            (pad(b"\x1A\x45\xDF\xA3\x9F\x42\x82\x88matroska", 32), "mkv", "video/x-matroska"),
+            (b"<?xml version=\"1.0\"?><svg></svg>".to_vec(), "svg", "image/svg+xml"),
+            (b"<?xml version=\"1.0\"?><FictionBook>".to_vec(), "fb2", "application/x-fictionbook+xml"),
+        ];
+
+        // 后缀错误时按文件头识别
+        for (data, suffix, mime) in cases {
+            let sniffed = detect(&data, "bin");
+            assert_eq!((sniffed.suffix.as_str(), sniffed.mime.as_str()), (suffix, mime), "{}", suffix);
+            assert!(!sniffed.executable, "{}", suffix);
+        }
+
+        // 同一格式保留原后缀
+        assert_eq!(detect(&pad(&[0xFF, 0xD8, 0xFF], 16), "jpeg").suffix, "jpeg");
+        assert_eq!(detect(&pad(b"\x78\x9C", 16), "zlib").mime, "application/zlib");
+    }
+
+    #[test]
+    fn test_executables() {
+        let mut pe = pad(b"MZ", 0x80);
+        pe[0x3C] = 0x40;
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let cases: Vec<(Vec<u8>, &str)> = vec![
+            (pad(b"\x7FELF", 16), "application/x-elf"),
+            (pad(&[0xCF, 0xFA, 0xED, 0xFE], 16), "application/x-mach-binary"),
+            (pad(&[0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x02], 16), "application/x-mach-binary"),
+            (pe, "application/vnd.microsoft.portable-executable"),
+        ];
+
+        for (data, mime) in cases {
+            let sniffed = detect(&data, "exe");
+            assert_eq!(sniffed.mime, mime);
+            assert_eq!(sniffed.suffix, "exe");
+            assert!(sniffed.executable);
+        }
+
+        // java class 的版本号远大于 fat 的架构数
+        assert!(!detect(&pad(&[0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x34], 16), "class").executable);
+        // MZ 后没有 PE 头
+        let mut dos = pad(b"MZ", 0x80);
+        dos[0x3C] = 0x40;
+        assert!(!detect(&dos, "exe").executable);
+
+        // 以国家代码 MZ 开头的 csv, 偏移量是文本, 远超文件头
+        let csv = b"MZ,Mozambique,Maputo,33000000\nZA,South Africa,Pretoria,60000000\nZM,Zambia,Lusaka,20000000\n";
+        assert!(!detect(csv, "csv").executable);
+        assert!(!detect(csv, "dat").executable);
+        assert_eq!(detect(csv, "csv").mime, "text/csv");
+    }
+
+    #[test]
+    fn test_text() {
+        let csv = b"true,false,1\nfalse,true,2\n";
+        let sniffed = detect(csv, "csv");
+        assert_eq!((sniffed.suffix.as_str(), sniffed.mime.as_str()), ("csv", "text/csv"));
+
+        // 后缀不是文本时, `true` 开头但表目录不合理也不识别为 ttf
+        assert_eq!(detect(csv, "dat").suffix, "dat");
+        assert_eq!(detect(b"true", "json").mime, "application/json");
+        assert_eq!(detect(b"true", "bin").suffix, "bin");
+        assert_eq!(detect(&pad(b"true", 32), "ttf").mime, "font/ttf");
+
+        // 弱魔数不覆盖文本后缀
+        let ttf = pad(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x40, 0x00, 0x02, 0x00, 0x00], 32);
+        assert_eq!(detect(&ttf, "csv").suffix, "csv");
+        assert_eq!(detect(&pad(&[0x00, 0x00, 0x01, 0x00], 16), "txt").suffix, "txt");
+        assert_eq!(detect(&pad(&[0xFF, 0xFB, b'a', b','], 16), "csv").suffix, "csv");
+        assert_eq!(detect(b"BM,name\n", "csv").suffix, "csv");
+
+        // 强魔数仍然识别
+        assert_eq!(detect(&pad(b"PK\x05\x06", 22), "csv").suffix, "zip");
+
+        assert_eq!(detect(b"<html></html>", "html").mime, "text/html");
+        assert_eq!(detect(b"# title", "md").mime, "text/markdown");
+        assert_eq!(detect(b"hello", "").mime, "text/plain");
+        assert_eq!(detect(b"a\0b", "dat").mime, "application/octet-stream");
+    }
+}